@@ -1,14 +1,30 @@
-use gst::ffi::GstBuffer;
+use gst::ffi::{GstBuffer, GstStructure};
 use gst_video::ffi::GstVideoInfo;
 use gst_video::VideoInfo;
-use gst::glib::translate::FromGlibPtrNone;
-use std::ffi::{c_char, c_uint, c_void, CStr};
+use gst::glib::translate::{FromGlibPtrNone, IntoGlibPtr, ToGlibPtr};
+use std::ffi::{c_char, c_int, c_uint, c_void, CStr};
+use std::os::fd::{FromRawFd, OwnedFd};
+use std::path::PathBuf;
 use std::ptr;
-use waylanddisplaycore::{Tracer, WaylandDisplay};
+use waylanddisplaycore::{
+    ClientLimits, DisplayMode, FrameCrop, GlobalFilter, OutputInfo, OutputSubpixel, OutputTransform, Overlay, RgbaImage, Tracer,
+    WallpaperMode, WaylandDisplay, WaylandSocket, PRIMARY_OUTPUT,
+};
 use tracing_subscriber;
 
 #[no_mangle]
 pub extern "C" fn display_init(render_node: *const c_char) -> *mut WaylandDisplay {
+    display_init_with_socket(render_node, ptr::null(), -1)
+}
+
+/// Like `display_init`, but listens on `socket_name` (a name in `XDG_RUNTIME_DIR` or an absolute path)
+/// or takes ownership of the listening socket `socket_fd`, if it isn't negative.
+#[no_mangle]
+pub extern "C" fn display_init_with_socket(
+    render_node: *const c_char,
+    socket_name: *const c_char,
+    socket_fd: c_int,
+) -> *mut WaylandDisplay {
     let render_node = if !render_node.is_null() {
         Some(
             unsafe { CStr::from_ptr(render_node) }
@@ -18,10 +34,24 @@ pub extern "C" fn display_init(render_node: *const c_char) -> *mut WaylandDispla
     } else {
         None
     };
+    let socket = if socket_fd >= 0 {
+        WaylandSocket::Fd(unsafe { OwnedFd::from_raw_fd(socket_fd) })
+    } else if !socket_name.is_null() {
+        let socket_name = unsafe { CStr::from_ptr(socket_name) }
+            .to_string_lossy()
+            .into_owned();
+        if socket_name.starts_with('/') {
+            WaylandSocket::Path(PathBuf::from(socket_name))
+        } else {
+            WaylandSocket::Name(socket_name)
+        }
+    } else {
+        WaylandSocket::Auto
+    };
 
     tracing_subscriber::fmt::try_init().ok();
 
-    match WaylandDisplay::new(render_node) {
+    match WaylandDisplay::new_with_socket(render_node, socket) {
         Ok(dpy) => Box::into_raw(Box::new(dpy)),
         Err(err) => {
             tracing::error!(?err, "Failed to create wayland display.");
@@ -97,6 +127,303 @@ pub extern "C" fn display_add_input_device(dpy: *mut WaylandDisplay, path: *cons
     display.add_input_device(path);
 }
 
+#[no_mangle]
+pub extern "C" fn display_set_eos_on_last_client(dpy: *mut WaylandDisplay, enabled: bool) {
+    let display = unsafe { &mut *dpy };
+    display.set_eos_on_last_client(enabled);
+}
+
+/// Lets the compositor switch to software rendering, if the GPU can't be recovered after a context loss.
+#[no_mangle]
+pub extern "C" fn display_set_software_fallback(dpy: *mut WaylandDisplay, enabled: bool) {
+    let display = unsafe { &mut *dpy };
+    display.set_software_fallback(enabled);
+}
+
+/// Sets the output scale, e.g. 1.5 to render the UI at 150%. Can be changed at any time.
+/// The scale is clamped to 0.25 - 8, NaN and infinite values are ignored.
+#[no_mangle]
+pub extern "C" fn display_set_output_scale(dpy: *mut WaylandDisplay, scale: f64) {
+    let display = unsafe { &mut *dpy };
+    display.set_output_scale(scale);
+}
+
+/// Rotates and/or flips the desktop inside the video frames, using the values of `wl_output.transform`
+/// (0 = normal, 1-3 = 90/180/270 degrees counter-clockwise, 4-7 = flipped and rotated).
+#[no_mangle]
+pub extern "C" fn display_set_output_transform(dpy: *mut WaylandDisplay, transform: c_uint) {
+    let display = unsafe { &mut *dpy };
+    match OutputTransform::try_from(transform) {
+        Ok(transform) => display.set_output_transform(transform),
+        Err(value) => tracing::error!(value, "Invalid output transform"),
+    }
+}
+
+/// Renders the desktop at `width`x`height`, scaled to fit the frames. 0 renders at the frame size.
+#[no_mangle]
+pub extern "C" fn display_set_render_size(dpy: *mut WaylandDisplay, width: c_uint, height: c_uint) {
+    let display = unsafe { &mut *dpy };
+    let size = (width > 0 && height > 0).then_some((width as i32, height as i32));
+    display.set_render_size(size);
+}
+
+/// Shows only part of the desktop in the frames: `none`, `focused-window` or `WIDTHxHEIGHT+X+Y`.
+#[no_mangle]
+pub extern "C" fn display_set_frame_crop(dpy: *mut WaylandDisplay, crop: *const c_char) {
+    let display = unsafe { &mut *dpy };
+    if crop.is_null() {
+        display.set_frame_crop(FrameCrop::None);
+        return;
+    }
+    match unsafe { CStr::from_ptr(crop) }.to_string_lossy().parse::<FrameCrop>() {
+        Ok(crop) => display.set_frame_crop(crop),
+        Err(err) => tracing::error!("{}", err),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn display_set_letterbox(dpy: *mut WaylandDisplay, enabled: bool) {
+    let display = unsafe { &mut *dpy };
+    display.set_letterbox(enabled);
+}
+
+/// Sets the color behind the windows, channels range from 0.0 to 1.0.
+#[no_mangle]
+pub extern "C" fn display_set_background_color(dpy: *mut WaylandDisplay, r: f32, g: f32, b: f32, a: f32) {
+    let display = unsafe { &mut *dpy };
+    display.set_background_color([r, g, b, a]);
+}
+
+/// Shows the image at `path` behind the windows, `NULL` removes it.
+/// `mode` is 0 (stretch), 1 (fit), 2 (fill) or 3 (center).
+///
+/// The image is decoded before returning, which can take a moment for large files.
+#[no_mangle]
+pub extern "C" fn display_set_wallpaper(dpy: *mut WaylandDisplay, path: *const c_char, mode: c_uint) {
+    let display = unsafe { &mut *dpy };
+    match WallpaperMode::try_from(mode) {
+        Ok(mode) => display.set_wallpaper_mode(mode),
+        Err(value) => tracing::error!(value, "Invalid wallpaper mode"),
+    }
+    if path.is_null() {
+        display.set_wallpaper(None);
+        return;
+    }
+    let path = unsafe { CStr::from_ptr(path) }.to_string_lossy().into_owned();
+    match RgbaImage::load(&path) {
+        Ok(image) => display.set_wallpaper(Some(image)),
+        Err(err) => tracing::error!(path, "{}", err),
+    }
+}
+
+/// Copies `width`x`height` RGBA pixels without row padding.
+fn overlay_image(data: *const u8, width: c_int, height: c_int) -> Option<RgbaImage> {
+    if data.is_null() || width <= 0 || height <= 0 {
+        tracing::error!(width, height, "Invalid overlay image");
+        return None;
+    }
+    let data = unsafe { std::slice::from_raw_parts(data, width as usize * height as usize * 4) };
+    RgbaImage::new(width, height, data.to_vec())
+}
+
+/// Renders text, `font` is a pango font description without size and may be `NULL`.
+#[cfg(feature = "text")]
+fn overlay_text(text: *const c_char, font: *const c_char, size: c_uint, color: [f32; 4]) -> Option<RgbaImage> {
+    if text.is_null() {
+        tracing::error!("Overlay text is null");
+        return None;
+    }
+    let text = unsafe { CStr::from_ptr(text) }.to_string_lossy();
+    let font = if font.is_null() {
+        "Sans".into()
+    } else {
+        unsafe { CStr::from_ptr(font) }.to_string_lossy()
+    };
+    match RgbaImage::from_text(&text, &font, size, color) {
+        Ok(image) => Some(image),
+        Err(err) => {
+            tracing::error!("{}", err);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "text"))]
+fn overlay_text(_text: *const c_char, _font: *const c_char, _size: c_uint, _color: [f32; 4]) -> Option<RgbaImage> {
+    tracing::error!("Built without text overlays, enable the `text` feature");
+    None
+}
+
+/// Draws `width`x`height` RGBA pixels on top of the frames of the primary output,
+/// higher `z` on top. Returns the id of the overlay, or -1 on failure.
+#[no_mangle]
+pub extern "C" fn display_add_overlay_image(
+    dpy: *mut WaylandDisplay,
+    data: *const u8,
+    width: c_int,
+    height: c_int,
+    x: c_int,
+    y: c_int,
+    z: c_int,
+) -> i64 {
+    let display = unsafe { &mut *dpy };
+    overlay_image(data, width, height)
+        .and_then(|image| display.add_overlay(Overlay { image, x, y, z }))
+        .map_or(-1, |id| id as i64)
+}
+
+/// Like `display_add_overlay_image`, for text of `size` pixels in a color with channels from 0.0 to 1.0.
+#[no_mangle]
+pub extern "C" fn display_add_overlay_text(
+    dpy: *mut WaylandDisplay,
+    text: *const c_char,
+    font: *const c_char,
+    size: c_uint,
+    r: f32,
+    g: f32,
+    b: f32,
+    a: f32,
+    x: c_int,
+    y: c_int,
+    z: c_int,
+) -> i64 {
+    let display = unsafe { &mut *dpy };
+    overlay_text(text, font, size, [r, g, b, a])
+        .and_then(|image| display.add_overlay(Overlay { image, x, y, z }))
+        .map_or(-1, |id| id as i64)
+}
+
+#[no_mangle]
+pub extern "C" fn display_update_overlay_image(
+    dpy: *mut WaylandDisplay,
+    id: u64,
+    data: *const u8,
+    width: c_int,
+    height: c_int,
+    x: c_int,
+    y: c_int,
+    z: c_int,
+) {
+    let display = unsafe { &mut *dpy };
+    if let Some(image) = overlay_image(data, width, height) {
+        display.update_overlay(id, Overlay { image, x, y, z });
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn display_update_overlay_text(
+    dpy: *mut WaylandDisplay,
+    id: u64,
+    text: *const c_char,
+    font: *const c_char,
+    size: c_uint,
+    r: f32,
+    g: f32,
+    b: f32,
+    a: f32,
+    x: c_int,
+    y: c_int,
+    z: c_int,
+) {
+    let display = unsafe { &mut *dpy };
+    if let Some(image) = overlay_text(text, font, size, [r, g, b, a]) {
+        display.update_overlay(id, Overlay { image, x, y, z });
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn display_remove_overlay(dpy: *mut WaylandDisplay, id: u64) {
+    let display = unsafe { &mut *dpy };
+    display.remove_overlay(id);
+}
+
+/// Sets the identity and physical properties of the output, has to be called before `display_set_video_info`.
+/// `NULL` strings keep the defaults, `subpixel` uses the values of `wl_output.subpixel`.
+#[no_mangle]
+pub extern "C" fn display_set_output_info(
+    dpy: *mut WaylandDisplay,
+    name: *const c_char,
+    make: *const c_char,
+    model: *const c_char,
+    serial: *const c_char,
+    width_mm: c_int,
+    height_mm: c_int,
+    subpixel: c_uint,
+) {
+    let display = unsafe { &mut *dpy };
+    let to_string = |string: *const c_char| {
+        (!string.is_null()).then(|| unsafe { CStr::from_ptr(string) }.to_string_lossy().into_owned())
+    };
+    let defaults = OutputInfo::default();
+    let subpixel = OutputSubpixel::try_from(subpixel).unwrap_or_else(|value| {
+        tracing::error!(value, "Invalid output subpixel layout");
+        OutputSubpixel::Unknown
+    });
+    display.set_output_info(OutputInfo {
+        name: to_string(name).unwrap_or(defaults.name),
+        make: to_string(make).unwrap_or(defaults.make),
+        model: to_string(model).unwrap_or(defaults.model),
+        serial: to_string(serial),
+        physical_size: (width_mm.max(0), height_mm.max(0)),
+        subpixel,
+    });
+}
+
+/// Sets the modes (`WIDTHxHEIGHT@REFRESH`, e.g. `1920x1080@60`) clients may request besides the current one,
+/// `modes` is an array of `n_modes` strings. Requests are reported as `wayland.mode-requested` events.
+#[no_mangle]
+pub extern "C" fn display_set_modes(dpy: *mut WaylandDisplay, modes: *const *const c_char, n_modes: c_uint) {
+    display_set_output_modes(dpy, PRIMARY_OUTPUT, modes, n_modes);
+}
+
+/// Like `display_set_modes`, for an output from `display_add_output`.
+#[no_mangle]
+pub extern "C" fn display_set_output_modes(
+    dpy: *mut WaylandDisplay,
+    output: c_uint,
+    modes: *const *const c_char,
+    n_modes: c_uint,
+) {
+    let display = unsafe { &mut *dpy };
+    let modes: &[*const c_char] = if modes.is_null() {
+        &[]
+    } else {
+        unsafe { std::slice::from_raw_parts(modes, n_modes as usize) }
+    };
+    let modes = modes
+        .iter()
+        .map(|mode| unsafe { CStr::from_ptr(*mode) }.to_string_lossy())
+        .filter_map(|mode| match mode.parse::<DisplayMode>() {
+            Ok(mode) => Some(mode),
+            Err(err) => {
+                tracing::error!(%err, "Ignoring mode");
+                None
+            }
+        })
+        .collect();
+    display.set_output_modes(output, modes);
+}
+
+/// Sets per-client quotas, 0 means unlimited. Clients going over them are disconnected.
+#[no_mangle]
+pub extern "C" fn display_set_client_limits(
+    dpy: *mut WaylandDisplay,
+    max_surfaces: c_uint,
+    max_shm_pool_size: u64,
+    max_buffer_width: c_int,
+    max_buffer_height: c_int,
+    max_frame_callbacks: c_uint,
+) {
+    let display = unsafe { &mut *dpy };
+    display.set_client_limits(ClientLimits {
+        max_surfaces: (max_surfaces > 0).then_some(max_surfaces as usize),
+        max_shm_pool_size: (max_shm_pool_size > 0).then_some(max_shm_pool_size as usize),
+        max_buffer_width: (max_buffer_width > 0).then_some(max_buffer_width),
+        max_buffer_height: (max_buffer_height > 0).then_some(max_buffer_height),
+        max_frame_callbacks: (max_frame_callbacks > 0).then_some(max_frame_callbacks as usize),
+    });
+}
+
 #[no_mangle]
 pub extern "C" fn display_set_video_info(dpy: *mut WaylandDisplay, info: *const GstVideoInfo) {
     let display = unsafe { &mut *dpy };
@@ -108,6 +435,46 @@ pub extern "C" fn display_set_video_info(dpy: *mut WaylandDisplay, info: *const
     display.set_video_info(video_info);
 }
 
+/// Adds another output named `name` and returns its id, or -1 on failure.
+/// It shows up for clients once `display_set_output_video_info` was called.
+#[no_mangle]
+pub extern "C" fn display_add_output(dpy: *mut WaylandDisplay, name: *const c_char) -> i64 {
+    let display = unsafe { &mut *dpy };
+    if name.is_null() {
+        tracing::error!("Output name is null");
+        return -1;
+    }
+    let info = OutputInfo {
+        name: unsafe { CStr::from_ptr(name) }.to_string_lossy().into_owned(),
+        ..OutputInfo::default()
+    };
+    display.add_output(info).map_or(-1, i64::from)
+}
+
+#[no_mangle]
+pub extern "C" fn display_set_output_video_info(dpy: *mut WaylandDisplay, output: c_uint, info: *const GstVideoInfo) {
+    let display = unsafe { &mut *dpy };
+    if info.is_null() {
+        tracing::error!("Video Info is null");
+        return;
+    }
+    let video_info = unsafe { VideoInfo::from_glib_none(info) };
+
+    display.set_output_video_info(output, video_info);
+}
+
+#[no_mangle]
+pub extern "C" fn display_remove_output(dpy: *mut WaylandDisplay, output: c_uint) {
+    let display = unsafe { &mut *dpy };
+    display.remove_output(output);
+}
+
+#[no_mangle]
+pub extern "C" fn display_move_window_to_output(dpy: *mut WaylandDisplay, window: u64, output: c_uint) {
+    let display = unsafe { &mut *dpy };
+    display.move_window_to_output(window, output);
+}
+
 #[no_mangle]
 pub extern "C" fn display_keyboard_input(dpy: *mut WaylandDisplay, key: c_uint, pressed: bool) {
     let display = unsafe { &mut *dpy };
@@ -126,6 +493,13 @@ pub extern "C" fn display_pointer_motion_absolute(dpy: *mut WaylandDisplay, x: f
     display.pointer_motion_absolute(x, y);
 }
 
+/// Like `display_pointer_motion_absolute`, but in pixels of the frames of an output from `display_add_output`.
+#[no_mangle]
+pub extern "C" fn display_pointer_motion_absolute_on_output(dpy: *mut WaylandDisplay, output: c_uint, x: f64, y: f64) {
+    let display = unsafe { &mut *dpy };
+    display.pointer_motion_absolute_on_output(output, x, y);
+}
+
 #[no_mangle]
 pub extern "C" fn display_pointer_button(dpy: *mut WaylandDisplay, button: c_uint, pressed: bool) {
     let display = unsafe { &mut *dpy };
@@ -138,6 +512,202 @@ pub extern "C" fn display_pointer_axis(dpy: *mut WaylandDisplay, x: f64, y: f64)
     display.pointer_axis(x, y);
 }
 
+struct UserData(*mut c_void);
+
+unsafe impl Send for UserData {}
+
+#[no_mangle]
+pub extern "C" fn display_set_event_callback(
+    dpy: *mut WaylandDisplay,
+    callback: extern "C" fn(*const GstStructure, *mut c_void),
+    user_data: *mut c_void,
+) {
+    let display = unsafe { &mut *dpy };
+    let user_data = UserData(user_data);
+    display.set_event_callback(move |event| {
+        let structure = event.to_structure();
+        callback(structure.as_ptr(), user_data.0);
+    });
+}
+
+/// Writes up to `max_len` window descriptions into `windows` and returns the total amount of windows.
+/// The caller owns the written structures and has to release them with `display_windows_free`.
+#[no_mangle]
+pub extern "C" fn display_get_windows(
+    dpy: *mut WaylandDisplay,
+    windows: *mut *mut GstStructure,
+    max_len: c_uint,
+) -> c_uint {
+    let display = unsafe { &mut *dpy };
+    let infos = display.windows();
+    if !windows.is_null() {
+        let client_windows = unsafe { std::slice::from_raw_parts_mut(windows, max_len as usize) };
+        for (i, info) in infos.iter().take(max_len as usize).enumerate() {
+            client_windows[i] = unsafe { info.to_structure().into_glib_ptr() };
+        }
+    }
+
+    infos.len() as c_uint
+}
+
+/// Frees the structures written by `display_get_windows`, `len` being the smaller of its `max_len` and
+/// its result, and resets them to `NULL`.
+/// The array itself stays owned by the caller.
+#[no_mangle]
+pub extern "C" fn display_windows_free(windows: *mut *mut GstStructure, len: c_uint) {
+    if windows.is_null() {
+        return;
+    }
+    let windows = unsafe { std::slice::from_raw_parts_mut(windows, len as usize) };
+    for window in windows {
+        if !window.is_null() {
+            unsafe { gst::ffi::gst_structure_free(*window) };
+            *window = ptr::null_mut();
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn display_focus_window(dpy: *mut WaylandDisplay, id: u64) {
+    let display = unsafe { &mut *dpy };
+    display.focus_window(id);
+}
+
+#[no_mangle]
+pub extern "C" fn display_raise_window(dpy: *mut WaylandDisplay, id: u64) {
+    let display = unsafe { &mut *dpy };
+    display.raise_window(id);
+}
+
+#[no_mangle]
+pub extern "C" fn display_close_window(dpy: *mut WaylandDisplay, id: u64) {
+    let display = unsafe { &mut *dpy };
+    display.close_window(id);
+}
+
+#[no_mangle]
+pub extern "C" fn display_kill_window(dpy: *mut WaylandDisplay, id: u64) {
+    let display = unsafe { &mut *dpy };
+    display.kill_window(id);
+}
+
+/// Starts `command` connected to the display, returns its pid or -1 on failure.
+/// `args` and `envs` (`KEY=VALUE`) are arrays of `n_args` and `n_envs` strings.
+#[no_mangle]
+pub extern "C" fn display_spawn(
+    dpy: *mut WaylandDisplay,
+    command: *const c_char,
+    args: *const *const c_char,
+    n_args: c_uint,
+    envs: *const *const c_char,
+    n_envs: c_uint,
+) -> i64 {
+    let display = unsafe { &mut *dpy };
+    let to_strings = |strings: *const *const c_char, len: c_uint| -> Vec<String> {
+        if strings.is_null() {
+            return Vec::new();
+        }
+        unsafe { std::slice::from_raw_parts(strings, len as usize) }
+            .iter()
+            .map(|string| unsafe { CStr::from_ptr(*string) }.to_string_lossy().into_owned())
+            .collect()
+    };
+    let command = unsafe { CStr::from_ptr(command) }
+        .to_string_lossy()
+        .into_owned();
+    let args = to_strings(args, n_args);
+    let envs = to_strings(envs, n_envs);
+
+    match display.spawn(
+        &command,
+        args.iter().map(String::as_str),
+        envs.iter().flat_map(|var| var.split_once('=')),
+    ) {
+        Ok(pid) => pid as i64,
+        Err(err) => {
+            tracing::error!(?err, command, "Failed to start application.");
+            -1
+        }
+    }
+}
+
+/// Adds a client on the already connected stream socket `fd`, the display takes ownership of it.
+/// `restricted` clients can't see privileged protocols. Returns the client id or -1 on failure.
+#[no_mangle]
+pub extern "C" fn display_insert_client_fd(dpy: *mut WaylandDisplay, fd: c_int, restricted: bool) -> i64 {
+    let display = unsafe { &mut *dpy };
+    match display.insert_client_fd(unsafe { OwnedFd::from_raw_fd(fd) }, restricted) {
+        Ok(id) => id as i64,
+        Err(err) => {
+            tracing::error!(?err, "Failed to add wayland client.");
+            -1
+        }
+    }
+}
+
+/// Builds a filter from interface names, a `NULL` `allow` list allows every interface.
+fn global_filter(
+    allow: *const *const c_char,
+    n_allow: c_uint,
+    deny: *const *const c_char,
+    n_deny: c_uint,
+) -> GlobalFilter {
+    let to_strings = |strings: *const *const c_char, len: c_uint| -> Vec<String> {
+        unsafe { std::slice::from_raw_parts(strings, len as usize) }
+            .iter()
+            .map(|string| unsafe { CStr::from_ptr(*string) }.to_string_lossy().into_owned())
+            .collect()
+    };
+    GlobalFilter {
+        allow: (!allow.is_null()).then(|| to_strings(allow, n_allow)),
+        deny: if deny.is_null() { Vec::new() } else { to_strings(deny, n_deny) },
+    }
+}
+
+/// Sets the globals (by interface name, e.g. `zwlr_layer_shell_v1`) that restricted or other clients
+/// connecting from now on can see. A `NULL` `allow` list allows every interface not in `deny`.
+#[no_mangle]
+pub extern "C" fn display_set_global_filter(
+    dpy: *mut WaylandDisplay,
+    restricted: bool,
+    allow: *const *const c_char,
+    n_allow: c_uint,
+    deny: *const *const c_char,
+    n_deny: c_uint,
+) {
+    let display = unsafe { &mut *dpy };
+    display.set_global_filter(restricted, global_filter(allow, n_allow, deny, n_deny));
+}
+
+/// Like `display_insert_client_fd` for a restricted client with its own global filter,
+/// see `display_set_global_filter`.
+#[no_mangle]
+pub extern "C" fn display_insert_client_fd_with_filter(
+    dpy: *mut WaylandDisplay,
+    fd: c_int,
+    allow: *const *const c_char,
+    n_allow: c_uint,
+    deny: *const *const c_char,
+    n_deny: c_uint,
+) -> i64 {
+    let display = unsafe { &mut *dpy };
+    let filter = global_filter(allow, n_allow, deny, n_deny);
+    match display.insert_client_fd_with_filter(unsafe { OwnedFd::from_raw_fd(fd) }, filter) {
+        Ok(id) => id as i64,
+        Err(err) => {
+            tracing::error!(?err, "Failed to add wayland client.");
+            -1
+        }
+    }
+}
+
+/// Returns `false` once the compositor thread died, the display has to be finished and created again.
+#[no_mangle]
+pub extern "C" fn display_is_alive(dpy: *mut WaylandDisplay) -> bool {
+    let display = unsafe { &mut *dpy };
+    display.is_alive()
+}
+
 #[no_mangle]
 pub extern "C" fn display_get_frame(dpy: *mut WaylandDisplay) -> *mut GstBuffer {
     let display = unsafe { &mut *dpy };
@@ -157,3 +727,48 @@ pub extern "C" fn display_get_frame(dpy: *mut WaylandDisplay) -> *mut GstBuffer
         }
     }
 }
+
+/// Renders a single window at its own size, `info` is filled with the format and size of the frame.
+#[no_mangle]
+pub extern "C" fn display_get_window_frame(
+    dpy: *mut WaylandDisplay,
+    window: u64,
+    info: *mut GstVideoInfo,
+) -> *mut GstBuffer {
+    let display = unsafe { &mut *dpy };
+    match display.window_frame(window) {
+        Ok((mut frame, video_info)) => {
+            if !info.is_null() {
+                unsafe { *info = *video_info.to_glib_none().0 };
+            }
+            let ptr = frame.make_mut().as_mut_ptr();
+            std::mem::forget(frame);
+            ptr
+        }
+        Err(err) => {
+            tracing::error!("Rendering error: {}", err);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Like `display_get_frame`, for an output added with `display_add_output`.
+#[no_mangle]
+pub extern "C" fn display_get_output_frame(dpy: *mut WaylandDisplay, output: c_uint) -> *mut GstBuffer {
+    let display = unsafe { &mut *dpy };
+    let _span = match display.tracer.as_ref() {
+        Some(tracer) => Some(tracer.trace("display_get_output_frame")),
+        None => None
+    };
+    match display.output_frame(output) {
+        Ok(mut frame) => {
+            let ptr = frame.make_mut().as_mut_ptr();
+            std::mem::forget(frame);
+            ptr
+        }
+        Err(err) => {
+            tracing::error!("Rendering error: {}", err);
+            ptr::null_mut()
+        }
+    }
+}
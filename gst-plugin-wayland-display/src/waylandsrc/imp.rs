@@ -1,3 +1,5 @@
+use std::os::fd::BorrowedFd;
+use std::path::PathBuf;
 use std::sync::Mutex;
 
 use gst::message::Application;
@@ -7,23 +9,51 @@ use gst::subclass::prelude::*;
 use gst::{glib, Event, Fraction};
 use gst::{
     glib::{once_cell::sync::Lazy, ValueArray},
-    LibraryError,
+    LibraryError, ResourceError,
 };
 use gst::{prelude::*, Structure};
 
+use gst_gl::prelude::*;
+
 use gst_base::subclass::base_src::CreateSuccess;
 use gst_base::subclass::prelude::*;
 use gst_base::traits::BaseSrcExt;
 
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::Registry;
-use waylanddisplaycore::WaylandDisplay;
+use waylanddisplaycore::{
+    ClientLimits, DisplayError, DisplayEvent, DisplayHandle, DisplayMode, FrameCrop, FrameError, GlHandles, GlSync, GlobalFilter,
+    HdrMetadata, ImageError, OutputInfo, OutputSubpixel, OutputTransform, Overlay, RgbaImage,
+    WallpaperMode, WaylandDisplay, WaylandSocket, MAX_OUTPUT_SCALE, MIN_OUTPUT_SCALE, PRIMARY_OUTPUT, PRIVILEGED_GLOBALS,
+};
 
 use crate::utils::{GstLayer, CAT};
 
+/// How often a frame is rendered again after the GL context got lost, before giving up.
+const MAX_GL_ATTEMPTS: u32 = 3;
+
 pub struct WaylandDisplaySrc {
     state: Mutex<Option<State>>,
     settings: Mutex<Settings>,
+    /// HDR metadata to put into the caps with the next buffer.
+    pending_hdr_metadata: Mutex<Option<Option<HdrMetadata>>>,
+    /// Mode a client asked for, preferred when (re)negotiating.
+    requested_mode: Mutex<Option<DisplayMode>>,
+    /// Set once the application exited with `exit-action=eos` or the captured window closed,
+    /// the next `create` ends the stream.
+    eos_pending: Mutex<bool>,
+    /// Set once the application failed with `exit-action=error`, the next `create` returns an error.
+    error_pending: Mutex<bool>,
+    /// Set once the renderer got recreated, the GL objects shared with the old one are unusable.
+    gl_lost: Mutex<bool>,
+    /// Window streamed instead of the primary output, 0 for none.
+    ///
+    /// Not part of the settings, as the event callback checks it while properties may wait for the compositor.
+    window: Mutex<u64>,
+    /// Set while the caps follow the captured window, the output keeps its size then.
+    window_caps: Mutex<bool>,
+    /// Changes the display without the state lock, which `create` holds while waiting for the compositor.
+    handle: Mutex<Option<DisplayHandle>>,
 }
 
 impl Default for WaylandDisplaySrc {
@@ -31,18 +61,249 @@ impl Default for WaylandDisplaySrc {
         WaylandDisplaySrc {
             state: Mutex::new(None),
             settings: Mutex::new(Settings::default()),
+            pending_hdr_metadata: Mutex::new(None),
+            requested_mode: Mutex::new(None),
+            eos_pending: Mutex::new(false),
+            error_pending: Mutex::new(false),
+            gl_lost: Mutex::new(false),
+            window: Mutex::new(0),
+            window_caps: Mutex::new(false),
+            handle: Mutex::new(None),
         }
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstWaylandDisplayExitAction")]
+pub enum ExitAction {
+    #[default]
+    #[enum_value(name = "Keep streaming", nick = "none")]
+    None = 0,
+    #[enum_value(name = "Send EOS", nick = "eos")]
+    Eos = 1,
+    #[enum_value(name = "Post an error if the application failed, EOS otherwise", nick = "error")]
+    Error = 2,
+}
+
+/// Converts a color in the `0xAARRGGBB` format of GStreamer's compositors.
+fn argb_to_rgba(argb: u32) -> [f32; 4] {
+    let channel = |shift: u32| ((argb >> shift) & 0xff) as f32 / 255.0;
+    [channel(16), channel(8), channel(0), channel(24)]
+}
+
+#[derive(Debug)]
 pub struct Settings {
     render_node: Option<String>,
     input_devices: Vec<String>,
+    command: Option<String>,
+    args: Vec<String>,
+    envs: Vec<String>,
+    exit_action: ExitAction,
+    eos_on_last_client: bool,
+    socket_name: Option<String>,
+    socket_fd: i32,
+    max_surfaces: u32,
+    max_shm_pool_size: u64,
+    max_buffer_width: u32,
+    max_buffer_height: u32,
+    max_frame_callbacks: u32,
+    restricted_globals: Vec<String>,
+    software_fallback: bool,
+    output_scale: f64,
+    output_transform: OutputTransform,
+    render_width: u32,
+    render_height: u32,
+    crop: FrameCrop,
+    letterbox: bool,
+    background_color: u32,
+    wallpaper: Option<String>,
+    wallpaper_mode: WallpaperMode,
+    modes: Vec<DisplayMode>,
+    output_info: OutputInfo,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            render_node: None,
+            input_devices: Vec::new(),
+            command: None,
+            args: Vec::new(),
+            envs: Vec::new(),
+            exit_action: ExitAction::None,
+            eos_on_last_client: false,
+            socket_name: None,
+            socket_fd: -1,
+            max_surfaces: 0,
+            max_shm_pool_size: 0,
+            max_buffer_width: 0,
+            max_buffer_height: 0,
+            max_frame_callbacks: 0,
+            restricted_globals: PRIVILEGED_GLOBALS.iter().map(|interface| interface.to_string()).collect(),
+            software_fallback: false,
+            output_scale: 1.0,
+            output_transform: OutputTransform::Normal,
+            render_width: 0,
+            render_height: 0,
+            crop: FrameCrop::None,
+            letterbox: false,
+            background_color: 0xff000000,
+            wallpaper: None,
+            wallpaper_mode: WallpaperMode::Fill,
+            modes: Vec::new(),
+            output_info: OutputInfo::default(),
+        }
+    }
+}
+
+impl Settings {
+    fn socket(&self) -> Result<WaylandSocket, std::io::Error> {
+        if self.socket_fd >= 0 {
+            let fd = unsafe { BorrowedFd::borrow_raw(self.socket_fd) }.try_clone_to_owned()?;
+            return Ok(WaylandSocket::Fd(fd));
+        }
+
+        Ok(match self.socket_name.as_deref() {
+            Some(path) if path.starts_with('/') => WaylandSocket::Path(PathBuf::from(path)),
+            Some(name) => WaylandSocket::Name(name.to_owned()),
+            None => WaylandSocket::Auto,
+        })
+    }
+
+    fn client_limits(&self) -> ClientLimits {
+        let limit = |value: u64| (value > 0).then_some(value);
+        ClientLimits {
+            max_surfaces: limit(self.max_surfaces as u64).map(|value| value as usize),
+            max_shm_pool_size: limit(self.max_shm_pool_size).map(|value| value as usize),
+            max_buffer_width: limit(self.max_buffer_width as u64).map(|value| value as i32),
+            max_buffer_height: limit(self.max_buffer_height as u64).map(|value| value as i32),
+            max_frame_callbacks: limit(self.max_frame_callbacks as u64).map(|value| value as usize),
+        }
+    }
+
+    fn render_size(&self) -> Option<(i32, i32)> {
+        (self.render_width > 0 && self.render_height > 0)
+            .then_some((self.render_width as i32, self.render_height as i32))
+    }
 }
 
 pub struct State {
     display: WaylandDisplay,
+    gl: Option<GlState>,
+    /// Whether `memory:GLMemory` got negotiated.
+    gl_output: bool,
+    /// The video info last handed to the compositor.
+    video_info: Option<gst_video::VideoInfo>,
+}
+
+/// GStreamer GL objects sharing the compositor's EGL display and context.
+pub struct GlState {
+    display: gst_gl::GLDisplay,
+    /// The compositor's own context, only used to share objects with.
+    wrapped: gst_gl::GLContext,
+    context: gst_gl::GLContext,
+}
+
+impl GlState {
+    fn new(handles: GlHandles) -> Result<GlState, glib::BoolError> {
+        let display = unsafe { gst_gl_egl::GLDisplayEGL::with_egl_display(handles.egl_display)? }
+            .upcast::<gst_gl::GLDisplay>();
+        let wrapped = unsafe {
+            gst_gl::GLContext::new_wrapped(
+                &display,
+                handles.egl_context,
+                gst_gl::GLPlatform::EGL,
+                gst_gl::GLAPI::GLES2,
+            )
+        }
+        .ok_or_else(|| glib::bool_error!("Failed to wrap the compositor's EGL context"))?;
+        let context = gst_gl::GLContext::new(&display);
+        context
+            .create(Some(&wrapped))
+            .map_err(|err| glib::bool_error!("Failed to create shared GL context: {}", err))?;
+
+        Ok(GlState {
+            display,
+            wrapped,
+            context,
+        })
+    }
+}
+
+/// A `GL | WRITE` map of a GL memory's texture. Unmapping tells GstGL the texture changed,
+/// so CPU maps downstream download it again.
+struct GlTextureMap<'a> {
+    memory: &'a gst::MemoryRef,
+    info: gst::ffi::GstMapInfo,
+}
+
+impl<'a> GlTextureMap<'a> {
+    fn new(memory: &'a gst::MemoryRef) -> Option<Self> {
+        memory.downcast_memory_ref::<gst_gl::GLMemory>()?;
+        let mut info = std::mem::MaybeUninit::<gst::ffi::GstMapInfo>::zeroed();
+        // Safety: GL memory mapped with `GST_MAP_GL` points to the id of its texture
+        let mapped = unsafe {
+            gst::ffi::gst_memory_map(
+                memory.as_ptr() as *mut _,
+                info.as_mut_ptr(),
+                gst::ffi::GST_MAP_WRITE | gst_gl::ffi::GST_MAP_GL,
+            )
+        };
+        (mapped != glib::ffi::GFALSE).then(|| GlTextureMap {
+            memory,
+            info: unsafe { info.assume_init() },
+        })
+    }
+
+    fn texture_id(&self) -> u32 {
+        unsafe { *(self.info.data as *const u32) }
+    }
+}
+
+impl Drop for GlTextureMap<'_> {
+    fn drop(&mut self) {
+        unsafe { gst::ffi::gst_memory_unmap(self.memory.as_ptr() as *mut _, &mut self.info) };
+    }
+}
+
+/// Attaches the compositor's sync object to a buffer, downstream GL elements wait on it before
+/// using the texture and the meta deletes it in `context` once the buffer is freed.
+fn add_gl_sync_meta(buffer: &mut gst::BufferRef, context: &gst_gl::GLContext, sync: GlSync) {
+    unsafe {
+        gst_gl::ffi::gst_buffer_add_gl_sync_meta_full(
+            context.as_ptr(),
+            buffer.as_mut_ptr(),
+            sync.sync as glib::ffi::gpointer,
+        );
+    }
+}
+
+fn gl_caps() -> gst::Caps {
+    VideoCapsBuilder::new()
+        .features([gst_gl::CAPS_FEATURE_MEMORY_GL_MEMORY])
+        .format_list([VideoFormat::Rgba, VideoFormat::Rgb10a2Le])
+        .height_range(..i32::MAX)
+        .width_range(..i32::MAX)
+        .framerate_range(Fraction::new(1, 1)..Fraction::new(i32::MAX, 1))
+        .field("texture-target", "2D")
+        .build()
+}
+
+fn system_caps() -> gst::Caps {
+    VideoCapsBuilder::new()
+        .format_list([
+            VideoFormat::Rgbx,
+            VideoFormat::Rgba,
+            VideoFormat::Bgrx,
+            VideoFormat::Bgra,
+            VideoFormat::Rgb10a2Le,
+            VideoFormat::Bgr10a2Le,
+        ])
+        .height_range(..i32::MAX)
+        .width_range(..i32::MAX)
+        .framerate_range(Fraction::new(1, 1)..Fraction::new(i32::MAX, 1))
+        .build()
 }
 
 #[glib::object_subclass]
@@ -53,13 +314,357 @@ impl ObjectSubclass for WaylandDisplaySrc {
     type Interfaces = ();
 }
 
+impl WaylandDisplaySrc {
+    /// Renders the next frame of the output or the captured window, returns the size of the frame
+    /// and whether it is in GL memory.
+    #[allow(clippy::type_complexity)]
+    fn render_frame(
+        &self,
+    ) -> Result<(Result<gst::Buffer, FrameError>, Option<(u32, u32)>, bool), gst::FlowError> {
+        let mut state_guard = self.state.lock().unwrap();
+        let Some(state) = state_guard.as_mut() else {
+            return Err(gst::FlowError::Eos);
+        };
+
+        let window = *self.window.lock().unwrap();
+        let subscriber = Registry::default().with(GstLayer);
+        let mut frame_size = state
+            .video_info
+            .as_ref()
+            .map(|info| (info.width(), info.height()));
+        // until the switch to system memory got negotiated, the output keeps being streamed
+        let result = if window != 0 && !state.gl_output {
+            tracing::subscriber::with_default(subscriber, || state.display.window_frame(window)).map(
+                |(buffer, info)| {
+                    // the stream follows the window as it resizes
+                    frame_size = Some((info.width(), info.height()));
+                    buffer
+                },
+            )
+        } else if state.gl_output {
+            let context = state.gl.as_ref().ok_or(gst::FlowError::NotNegotiated)?.context.clone();
+            let pool = self.obj().buffer_pool().ok_or(gst::FlowError::NotNegotiated)?;
+            let mut buffer = pool.acquire_buffer(None)?;
+            let sync = {
+                let map = GlTextureMap::new(buffer.peek_memory(0)).ok_or(gst::FlowError::NotNegotiated)?;
+                let texture = map.texture_id();
+                tracing::subscriber::with_default(subscriber, || state.display.frame_into_texture(texture))
+            };
+            sync.map(|sync| {
+                add_gl_sync_meta(buffer.make_mut(), &context, sync);
+                buffer
+            })
+        } else {
+            tracing::subscriber::with_default(subscriber, || state.display.frame())
+        };
+
+        Ok((result, frame_size, state.gl_output))
+    }
+
+    fn with_display<T>(&self, f: impl FnOnce(&WaylandDisplay) -> T) -> Option<T> {
+        let state = self.state.lock().unwrap();
+        state.as_ref().map(|state| f(&state.display))
+    }
+
+    /// Like `with_display`, without waiting for `create`, so it is safe to use from signal handlers,
+    /// property setters and any thread, including the compositor's.
+    fn with_handle<T>(&self, f: impl FnOnce(&DisplayHandle) -> T) -> Option<T> {
+        let handle = self.handle.lock().unwrap().clone();
+        handle.as_ref().map(f)
+    }
+
+    /// Shares the compositor's EGL context with GStreamer, if it renders with GL.
+    fn init_gl(&self, display: &WaylandDisplay) -> Option<GlState> {
+        let handles = display.gl_handles()?;
+        match GlState::new(handles) {
+            Ok(gl) => {
+                gst_gl::functions::gl_element_propagate_display_context(&*self.obj(), &gl.display);
+                Some(gl)
+            }
+            Err(err) => {
+                gst::warning!(CAT, imp: self, "GL memory output unavailable: {}", err);
+                None
+            }
+        }
+    }
+
+    /// Rebuilds the GL objects after the renderer got recreated and renegotiates GL memory output,
+    /// which falls back to system memory if the new renderer doesn't use GL.
+    fn refresh_gl(&self) -> Result<(), gst::FlowError> {
+        let mut state_guard = self.state.lock().unwrap();
+        let Some(state) = state_guard.as_mut() else {
+            return Ok(());
+        };
+        // the shared contexts have to go before new ones get created
+        std::mem::drop(state.gl.take());
+        state.gl = self.init_gl(&state.display);
+        let renegotiate = state.gl_output;
+        // negotiation calls back into `caps` and `decide_allocation`
+        drop(state_guard);
+
+        if renegotiate && !self.obj().negotiate() {
+            gst::error!(CAT, imp: self, "Failed to renegotiate after the rendering context got lost");
+            return Err(gst::FlowError::NotNegotiated);
+        }
+        Ok(())
+    }
+
+    /// Decodes a wallpaper on a thread of its own and shows it once done, unless another one got set meanwhile.
+    ///
+    /// Decoding runs a pipeline and may take a while, the caller isn't blocked by it.
+    fn load_wallpaper(&self, path: String) {
+        let weak_elem = self.obj().downgrade();
+        let result = std::thread::Builder::new()
+            .name(String::from("wallpaper-decoder"))
+            .spawn(move || {
+                let image = RgbaImage::load(&path);
+                let Some(elem) = weak_elem.upgrade() else {
+                    return;
+                };
+                let imp = elem.imp();
+                imp.with_handle(|handle| {
+                    if imp.settings.lock().unwrap().wallpaper.as_deref() != Some(path.as_str()) {
+                        return;
+                    }
+                    match image {
+                        Ok(image) => handle.set_wallpaper(Some(image)),
+                        Err(err) => {
+                            gst::warning!(CAT, obj: elem, "Ignoring wallpaper {}: {}", path, err);
+                            handle.set_wallpaper(None);
+                        }
+                    }
+                });
+            });
+        if let Err(err) = result {
+            gst::warning!(CAT, imp: self, "Failed to start decoding the wallpaper: {}", err);
+        }
+    }
+
+    /// Puts the HDR metadata of the content into the caps, for encoders to pick up.
+    ///
+    /// The compositor's output isn't reconfigured for this, see `set_caps`.
+    fn update_hdr_caps(&self, metadata: Option<&HdrMetadata>) {
+        let Some(caps) = self.obj().src_pad().current_caps() else {
+            return;
+        };
+
+        let mut caps = caps.copy();
+        {
+            let caps = caps.get_mut().unwrap();
+            if let Some(structure) = caps.structure_mut(0) {
+                structure.remove_fields(["mastering-display-info", "content-light-level"]);
+            }
+            if let Some(info) = metadata.and_then(HdrMetadata::mastering_display_info) {
+                let _ = info.add_to_caps(caps);
+            }
+            if let Some(level) = metadata.and_then(HdrMetadata::content_light_level) {
+                let _ = level.add_to_caps(caps);
+            }
+        }
+        if let Err(err) = self.obj().set_caps(&caps) {
+            gst::warning!(CAT, imp: self, "Failed to update HDR metadata in caps: {}", err);
+        }
+    }
+
+    /// Changes the size in the caps to the one of the frames, without resizing the output.
+    fn update_frame_size(&self, width: u32, height: u32) -> Result<(), gst::FlowError> {
+        let Some(caps) = self.obj().src_pad().current_caps() else {
+            return Ok(());
+        };
+        let current = caps.structure(0).and_then(|structure| {
+            Some((structure.get::<i32>("width").ok()?, structure.get::<i32>("height").ok()?))
+        });
+        if current == Some((width as i32, height as i32)) {
+            return Ok(());
+        }
+
+        let mut caps = caps.copy();
+        if let Some(structure) = caps.get_mut().unwrap().structure_mut(0) {
+            structure.set("width", width as i32);
+            structure.set("height", height as i32);
+        }
+        *self.window_caps.lock().unwrap() = true;
+        let result = self.obj().set_caps(&caps);
+        *self.window_caps.lock().unwrap() = false;
+        result.map_err(|err| {
+            gst::error!(CAT, imp: self, "Failed to change the frame size to {}x{}: {}", width, height, err);
+            gst::FlowError::NotNegotiated
+        })
+    }
+
+    /// Answers GL context queries with the context sharing the compositor's objects.
+    fn handle_context_query(&self, query: &mut gst::QueryRef) -> bool {
+        if !matches!(query.view(), gst::QueryView::Context(_)) {
+            return false;
+        }
+        let state = self.state.lock().unwrap();
+        let Some(gl) = state.as_ref().and_then(|state| state.gl.as_ref()) else {
+            return false;
+        };
+        gst_gl::functions::gl_handle_context_query(
+            &*self.obj(),
+            query,
+            Some(&gl.display),
+            Some(&gl.context),
+            Some(&gl.wrapped),
+        )
+    }
+}
+
+fn window_action_signal(name: &str, action: fn(&DisplayHandle, u64)) -> glib::subclass::Signal {
+    glib::subclass::Signal::builder(name)
+        .param_types([u64::static_type()])
+        .action()
+        .class_handler(move |_, args| {
+            let elem = args[0]
+                .get::<super::WaylandDisplaySrc>()
+                .expect("signal arg");
+            let id = args[1].get::<u64>().expect("signal arg");
+            elem.imp().with_handle(|handle| action(handle, id));
+            None
+        })
+        .build()
+}
+
+/// An action signal adding (returning the new id, 0 on failure) or updating (taking the id first)
+/// an overlay, made from the `content` arguments and placed by the `x`, `y` and `z` arguments following them.
+fn overlay_signal(
+    name: &str,
+    update: bool,
+    content: &[glib::Type],
+    image: fn(&[glib::Value]) -> Result<RgbaImage, ImageError>,
+) -> glib::subclass::Signal {
+    let id: &[glib::Type] = if update { &[glib::Type::U64] } else { &[] };
+    let params = id
+        .iter()
+        .chain(content)
+        .copied()
+        .chain([glib::Type::I32; 3])
+        .collect::<Vec<_>>();
+    let builder = glib::subclass::Signal::builder(name).param_types(params).action();
+    let builder = if update { builder } else { builder.return_type::<u64>() };
+    builder
+        .class_handler(move |_, args| {
+            let elem = args[0]
+                .get::<super::WaylandDisplaySrc>()
+                .expect("signal arg");
+            let (id, args) = if update {
+                (args[1].get::<u64>().expect("signal arg"), &args[2..])
+            } else {
+                (0, &args[1..])
+            };
+            let (content, position) = args.split_at(args.len() - 3);
+            let [x, y, z] = [0, 1, 2].map(|i| position[i].get::<i32>().expect("signal arg"));
+
+            let overlay = match image(content) {
+                Ok(image) => Some(Overlay { image, x, y, z }),
+                Err(err) => {
+                    gst::warning!(CAT, obj: elem, "Ignoring overlay: {}", err);
+                    None
+                }
+            };
+            // may be emitted from the compositor's thread, e.g. by a bus sync handler, so nothing waits for it
+            let overlays = elem.imp().with_handle(DisplayHandle::overlays);
+            let added = overlay.zip(overlays).and_then(|(overlay, overlays)| {
+                if update {
+                    overlays.update(id, overlay);
+                    None
+                } else {
+                    overlays.add(overlay)
+                }
+            });
+            (!update).then(|| added.unwrap_or(0).to_value())
+        })
+        .build()
+}
+
+/// An action signal removing an overlay by the id the `add-overlay-*` signals returned.
+fn remove_overlay_signal() -> glib::subclass::Signal {
+    glib::subclass::Signal::builder("remove-overlay")
+        .param_types([u64::static_type()])
+        .action()
+        .class_handler(|_, args| {
+            let elem = args[0]
+                .get::<super::WaylandDisplaySrc>()
+                .expect("signal arg");
+            let id = args[1].get::<u64>().expect("signal arg");
+            elem.imp().with_handle(|handle| handle.overlays().remove(id));
+            None
+        })
+        .build()
+}
+
+/// Overlay content from an image file, see [`RgbaImage::load`].
+fn overlay_file(args: &[glib::Value]) -> Result<RgbaImage, ImageError> {
+    let path = args[0].get::<Option<String>>().expect("signal arg");
+    RgbaImage::load(path.unwrap_or_default())
+}
+
+/// Overlay content from text, font description, size in pixels and `0xAARRGGBB` color, see [`RgbaImage::from_text`].
+#[cfg(feature = "text")]
+fn overlay_text(args: &[glib::Value]) -> Result<RgbaImage, ImageError> {
+    let text = args[0].get::<Option<String>>().expect("signal arg");
+    let font = args[1].get::<Option<String>>().expect("signal arg");
+    let size = args[2].get::<u32>().expect("signal arg");
+    let color = args[3].get::<u32>().expect("signal arg");
+    RgbaImage::from_text(
+        &text.unwrap_or_default(),
+        font.as_deref().unwrap_or("Sans"),
+        size,
+        argb_to_rgba(color),
+    )
+}
+
 impl ObjectImpl for WaylandDisplaySrc {
+    fn signals() -> &'static [glib::subclass::Signal] {
+        static SIGNALS: Lazy<Vec<glib::subclass::Signal>> = Lazy::new(|| {
+            // text, font description, size in pixels and color
+            #[cfg(feature = "text")]
+            const TEXT_OVERLAY_PARAMS: [glib::Type; 4] =
+                [glib::Type::STRING, glib::Type::STRING, glib::Type::U32, glib::Type::U32];
+            vec![
+                glib::subclass::Signal::builder("list-windows")
+                    .return_type::<gst::Array>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0]
+                            .get::<super::WaylandDisplaySrc>()
+                            .expect("signal arg");
+                        let windows = elem
+                            .imp()
+                            .with_handle(DisplayHandle::windows)
+                            .unwrap_or_default();
+                        Some(
+                            gst::Array::from_values(
+                                windows.iter().map(|info| info.to_structure().to_send_value()),
+                            )
+                            .to_value(),
+                        )
+                    })
+                    .build(),
+                window_action_signal("focus-window", DisplayHandle::focus_window),
+                window_action_signal("raise-window", DisplayHandle::raise_window),
+                window_action_signal("close-window", DisplayHandle::close_window),
+                window_action_signal("kill-window", DisplayHandle::kill_window),
+                overlay_signal("add-overlay-image", false, &[glib::Type::STRING], overlay_file),
+                overlay_signal("update-overlay-image", true, &[glib::Type::STRING], overlay_file),
+                #[cfg(feature = "text")]
+                overlay_signal("add-overlay-text", false, &TEXT_OVERLAY_PARAMS, overlay_text),
+                #[cfg(feature = "text")]
+                overlay_signal("update-overlay-text", true, &TEXT_OVERLAY_PARAMS, overlay_text),
+                remove_overlay_signal(),
+            ]
+        });
+
+        SIGNALS.as_ref()
+    }
+
     fn properties() -> &'static [glib::ParamSpec] {
         static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
             vec![
                 glib::ParamSpecString::builder("render-node")
                     .nick("DRM Render Node")
-                    .blurb("DRM Render Node to use (e.g. /dev/dri/renderD128")
+                    .blurb("DRM Render Node to use (e.g. /dev/dri/renderD128), software, pixman or auto[:driver|pci-id|path,...]")
                     .construct()
                     .build(),
                 glib::ParamSpecString::builder("mouse")
@@ -72,6 +677,177 @@ impl ObjectImpl for WaylandDisplaySrc {
                     .blurb("Input device to use (e.g. /dev/input/event0")
                     .construct()
                     .build(),
+                glib::ParamSpecString::builder("command")
+                    .nick("Command")
+                    .blurb("Application to start once the compositor is running")
+                    .build(),
+                gst::ParamSpecArray::builder("args")
+                    .nick("Arguments")
+                    .blurb("Arguments passed to the command")
+                    .element_spec(&glib::ParamSpecString::builder("arg").build())
+                    .build(),
+                gst::ParamSpecArray::builder("env")
+                    .nick("Environment")
+                    .blurb("Additional environment variables (KEY=VALUE) for the command")
+                    .element_spec(&glib::ParamSpecString::builder("var").build())
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("exit-action", ExitAction::None)
+                    .nick("Exit Action")
+                    .blurb("What to do once the command exits")
+                    .build(),
+                glib::ParamSpecString::builder("socket-name")
+                    .nick("Socket Name")
+                    .blurb("Wayland socket name in XDG_RUNTIME_DIR or an absolute socket path, picks a free name if unset")
+                    .build(),
+                glib::ParamSpecInt::builder("socket-fd")
+                    .nick("Socket FD")
+                    .blurb("Already bound and listening unix socket to accept clients on, overrides socket-name")
+                    .minimum(-1)
+                    .default_value(-1)
+                    .build(),
+                glib::ParamSpecBoolean::builder("eos-on-last-client")
+                    .nick("EOS on last client")
+                    .blurb("End the stream once the last wayland client disconnected")
+                    .default_value(false)
+                    .build(),
+                glib::ParamSpecBoolean::builder("software-fallback")
+                    .nick("Software fallback")
+                    .blurb("Switch to software rendering if the GPU can't be recovered after a context loss")
+                    .default_value(false)
+                    .build(),
+                glib::ParamSpecDouble::builder("output-scale")
+                    .nick("Output Scale")
+                    .blurb("Scale of the output, e.g. 1.5 to render the UI at 150%")
+                    .minimum(MIN_OUTPUT_SCALE)
+                    .maximum(MAX_OUTPUT_SCALE)
+                    .default_value(1.0)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("output-transform", OutputTransform::Normal)
+                    .nick("Output Transform")
+                    .blurb("Rotation and flipping of the desktop inside the video frames")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("render-width")
+                    .nick("Render Width")
+                    .blurb("Width to render the desktop at, scaled to fit the frames, 0 for the frame width")
+                    .maximum(i32::MAX as u32)
+                    .default_value(0)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("render-height")
+                    .nick("Render Height")
+                    .blurb("Height to render the desktop at, scaled to fit the frames, 0 for the frame height")
+                    .maximum(i32::MAX as u32)
+                    .default_value(0)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecString::builder("crop")
+                    .nick("Crop")
+                    .blurb("Part of the desktop shown in the frames: none, focused-window or WIDTHxHEIGHT+X+Y")
+                    .default_value(Some("none"))
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("letterbox")
+                    .nick("Letterbox")
+                    .blurb("Scale fullscreen clients with a fixed size to fit the frames, keeping their aspect ratio")
+                    .default_value(false)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("background-color")
+                    .nick("Background Color")
+                    .blurb("Color behind the windows, as 0xAARRGGBB")
+                    .default_value(0xff000000)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecString::builder("wallpaper")
+                    .nick("Wallpaper")
+                    .blurb("Path of an image shown behind the windows")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("wallpaper-mode", WallpaperMode::Fill)
+                    .nick("Wallpaper Mode")
+                    .blurb("How the wallpaper is fitted to the output")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt64::builder("window")
+                    .nick("Window")
+                    .blurb("Id of a window (see wayland.window-created) to stream instead of the output, at its own size, 0 for the output")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecString::builder("output-name")
+                    .nick("Output Name")
+                    .blurb("Connector name of the output, e.g. HDMI-A-1")
+                    .default_value(Some("HEADLESS-1"))
+                    .build(),
+                glib::ParamSpecString::builder("output-make")
+                    .nick("Output Make")
+                    .blurb("Manufacturer of the output")
+                    .default_value(Some("Virtual"))
+                    .build(),
+                glib::ParamSpecString::builder("output-model")
+                    .nick("Output Model")
+                    .blurb("Model of the output")
+                    .default_value(Some("Wolf"))
+                    .build(),
+                glib::ParamSpecString::builder("output-serial")
+                    .nick("Output Serial")
+                    .blurb("Serial number of the output")
+                    .build(),
+                glib::ParamSpecInt::builder("output-width-mm")
+                    .nick("Output Width")
+                    .blurb("Physical width of the output in millimeters, 0 if unknown")
+                    .minimum(0)
+                    .default_value(0)
+                    .build(),
+                glib::ParamSpecInt::builder("output-height-mm")
+                    .nick("Output Height")
+                    .blurb("Physical height of the output in millimeters, 0 if unknown")
+                    .minimum(0)
+                    .default_value(0)
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("output-subpixel", OutputSubpixel::Unknown)
+                    .nick("Output Subpixel")
+                    .blurb("Subpixel layout of the output")
+                    .build(),
+                gst::ParamSpecArray::builder("modes")
+                    .nick("Modes")
+                    .blurb("Modes (WIDTHxHEIGHT@REFRESH) clients may request besides the negotiated one")
+                    .element_spec(&glib::ParamSpecString::builder("mode").build())
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("max-surfaces")
+                    .nick("Max Surfaces")
+                    .blurb("Maximum number of surfaces per client, 0 for unlimited")
+                    .default_value(0)
+                    .build(),
+                glib::ParamSpecUInt64::builder("max-shm-pool-size")
+                    .nick("Max SHM Pool Size")
+                    .blurb("Maximum size in bytes of a client shm pool, 0 for unlimited")
+                    .default_value(0)
+                    .build(),
+                glib::ParamSpecUInt::builder("max-buffer-width")
+                    .nick("Max Buffer Width")
+                    .blurb("Maximum width of client buffers, 0 for unlimited")
+                    .maximum(i32::MAX as u32)
+                    .default_value(0)
+                    .build(),
+                glib::ParamSpecUInt::builder("max-buffer-height")
+                    .nick("Max Buffer Height")
+                    .blurb("Maximum height of client buffers, 0 for unlimited")
+                    .maximum(i32::MAX as u32)
+                    .default_value(0)
+                    .build(),
+                glib::ParamSpecUInt::builder("max-frame-callbacks")
+                    .nick("Max Frame Callbacks")
+                    .blurb("Maximum number of pending frame callbacks per client, 0 for unlimited")
+                    .default_value(0)
+                    .build(),
+                gst::ParamSpecArray::builder("restricted-globals")
+                    .nick("Restricted Globals")
+                    .blurb("Interfaces (e.g. zwlr_layer_shell_v1) hidden from sandboxed clients")
+                    .element_spec(&glib::ParamSpecString::builder("interface").build())
+                    .build(),
             ]
         });
 
@@ -104,6 +880,208 @@ impl ObjectImpl for WaylandDisplaySrc {
                     settings.input_devices.push(actual_val.unwrap());
                 }
             }
+            "command" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.command = value
+                    .get::<Option<String>>()
+                    .expect("Type checked upstream");
+            }
+            "args" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.args = value
+                    .get::<gst::Array>()
+                    .expect("Type checked upstream")
+                    .iter()
+                    .map(|arg| arg.get::<String>().expect("Type checked upstream"))
+                    .collect();
+            }
+            "env" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.envs = value
+                    .get::<gst::Array>()
+                    .expect("Type checked upstream")
+                    .iter()
+                    .map(|var| var.get::<String>().expect("Type checked upstream"))
+                    .collect();
+            }
+            "exit-action" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.exit_action = value.get::<ExitAction>().expect("Type checked upstream");
+            }
+            "socket-name" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.socket_name = value
+                    .get::<Option<String>>()
+                    .expect("Type checked upstream");
+            }
+            "socket-fd" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.socket_fd = value.get::<i32>().expect("Type checked upstream");
+            }
+            "eos-on-last-client" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.eos_on_last_client = value.get::<bool>().expect("Type checked upstream");
+            }
+            "software-fallback" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.software_fallback = value.get::<bool>().expect("Type checked upstream");
+            }
+            "output-scale" => {
+                let scale = value.get::<f64>().expect("Type checked upstream");
+                self.settings.lock().unwrap().output_scale = scale;
+                self.with_handle(|handle| handle.set_output_scale(scale));
+            }
+            "output-transform" => {
+                let transform = value.get::<OutputTransform>().expect("Type checked upstream");
+                self.settings.lock().unwrap().output_transform = transform;
+                self.with_handle(|handle| handle.set_output_transform(transform));
+            }
+            "render-width" | "render-height" => {
+                let mut settings = self.settings.lock().unwrap();
+                let size = value.get::<u32>().expect("Type checked upstream");
+                if pspec.name() == "render-width" {
+                    settings.render_width = size;
+                } else {
+                    settings.render_height = size;
+                }
+                let render_size = settings.render_size();
+                drop(settings);
+                self.with_handle(|handle| handle.set_render_size(render_size));
+            }
+            "crop" => {
+                let crop = value
+                    .get::<Option<String>>()
+                    .expect("Type checked upstream")
+                    .unwrap_or_default();
+                let crop = match crop.parse::<FrameCrop>() {
+                    Ok(crop) => crop,
+                    Err(err) => {
+                        gst::warning!(CAT, imp: self, "Ignoring crop: {}", err);
+                        return;
+                    }
+                };
+                self.settings.lock().unwrap().crop = crop;
+                self.with_handle(|handle| handle.set_frame_crop(crop));
+            }
+            "letterbox" => {
+                let enabled = value.get::<bool>().expect("Type checked upstream");
+                self.settings.lock().unwrap().letterbox = enabled;
+                self.with_handle(|handle| handle.set_letterbox(enabled));
+            }
+            "background-color" => {
+                let color = value.get::<u32>().expect("Type checked upstream");
+                self.settings.lock().unwrap().background_color = color;
+                self.with_handle(|handle| handle.set_background_color(argb_to_rgba(color)));
+            }
+            "wallpaper" => {
+                let path = value.get::<Option<String>>().expect("Type checked upstream");
+                self.settings.lock().unwrap().wallpaper = path.clone();
+                // before starting, the wallpaper gets loaded in `start`
+                if let Some(handle) = self.with_handle(DisplayHandle::clone) {
+                    match path {
+                        Some(path) => self.load_wallpaper(path),
+                        None => handle.set_wallpaper(None),
+                    }
+                }
+            }
+            "wallpaper-mode" => {
+                let mode = value.get::<WallpaperMode>().expect("Type checked upstream");
+                self.settings.lock().unwrap().wallpaper_mode = mode;
+                self.with_handle(|handle| handle.set_wallpaper_mode(mode));
+            }
+            "window" => {
+                let window = value.get::<u64>().expect("Type checked upstream");
+                let previous = std::mem::replace(&mut *self.window.lock().unwrap(), window);
+                if (previous == 0) != (window == 0) {
+                    // window frames are only available in system memory
+                    self.obj().src_pad().mark_reconfigure();
+                }
+            }
+            "output-name" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.output_info.name = value
+                    .get::<Option<String>>()
+                    .expect("Type checked upstream")
+                    .unwrap_or_else(|| OutputInfo::default().name);
+            }
+            "output-make" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.output_info.make = value
+                    .get::<Option<String>>()
+                    .expect("Type checked upstream")
+                    .unwrap_or_else(|| OutputInfo::default().make);
+            }
+            "output-model" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.output_info.model = value
+                    .get::<Option<String>>()
+                    .expect("Type checked upstream")
+                    .unwrap_or_else(|| OutputInfo::default().model);
+            }
+            "output-serial" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.output_info.serial = value
+                    .get::<Option<String>>()
+                    .expect("Type checked upstream");
+            }
+            "output-width-mm" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.output_info.physical_size.0 = value.get::<i32>().expect("Type checked upstream");
+            }
+            "output-height-mm" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.output_info.physical_size.1 = value.get::<i32>().expect("Type checked upstream");
+            }
+            "output-subpixel" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.output_info.subpixel = value.get::<OutputSubpixel>().expect("Type checked upstream");
+            }
+            "modes" => {
+                let modes = value
+                    .get::<gst::Array>()
+                    .expect("Type checked upstream")
+                    .iter()
+                    .map(|mode| mode.get::<String>().expect("Type checked upstream"))
+                    .filter_map(|mode| match mode.parse::<DisplayMode>() {
+                        Ok(mode) => Some(mode),
+                        Err(err) => {
+                            gst::warning!(CAT, imp: self, "Ignoring mode: {}", err);
+                            None
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                self.settings.lock().unwrap().modes = modes.clone();
+                self.with_handle(|handle| handle.set_modes(modes));
+            }
+            "max-surfaces" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.max_surfaces = value.get::<u32>().expect("Type checked upstream");
+            }
+            "max-shm-pool-size" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.max_shm_pool_size = value.get::<u64>().expect("Type checked upstream");
+            }
+            "max-buffer-width" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.max_buffer_width = value.get::<u32>().expect("Type checked upstream");
+            }
+            "max-buffer-height" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.max_buffer_height = value.get::<u32>().expect("Type checked upstream");
+            }
+            "max-frame-callbacks" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.max_frame_callbacks = value.get::<u32>().expect("Type checked upstream");
+            }
+            "restricted-globals" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.restricted_globals = value
+                    .get::<gst::Array>()
+                    .expect("Type checked upstream")
+                    .iter()
+                    .map(|interface| interface.get::<String>().expect("Type checked upstream"))
+                    .collect();
+            }
             _ => unreachable!(),
         }
     }
@@ -111,11 +1089,15 @@ impl ObjectImpl for WaylandDisplaySrc {
     fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
         match pspec.name() {
             "render-node" => {
+                // report the node actually chosen, once the compositor is running
+                if let Some(render_node) = self.with_display(|display| display.render_node().to_owned()) {
+                    return render_node.to_value();
+                }
                 let settings = self.settings.lock().unwrap();
                 settings
                     .render_node
                     .clone()
-                    .unwrap_or_else(|| String::from("/dev/dri/renderD128"))
+                    .unwrap_or_else(|| String::from("auto"))
                     .to_value()
             }
             "mouse" => {
@@ -126,6 +1108,132 @@ impl ObjectImpl for WaylandDisplaySrc {
                 let settings = self.settings.lock().unwrap();
                 settings.input_devices.join(",").to_value()
             }
+            "command" => {
+                let settings = self.settings.lock().unwrap();
+                settings.command.to_value()
+            }
+            "args" => {
+                let settings = self.settings.lock().unwrap();
+                gst::Array::from_values(settings.args.iter().map(|arg| arg.to_send_value())).to_value()
+            }
+            "env" => {
+                let settings = self.settings.lock().unwrap();
+                gst::Array::from_values(settings.envs.iter().map(|var| var.to_send_value())).to_value()
+            }
+            "exit-action" => {
+                let settings = self.settings.lock().unwrap();
+                settings.exit_action.to_value()
+            }
+            "socket-name" => {
+                let settings = self.settings.lock().unwrap();
+                settings.socket_name.to_value()
+            }
+            "socket-fd" => {
+                let settings = self.settings.lock().unwrap();
+                settings.socket_fd.to_value()
+            }
+            "eos-on-last-client" => {
+                let settings = self.settings.lock().unwrap();
+                settings.eos_on_last_client.to_value()
+            }
+            "software-fallback" => {
+                let settings = self.settings.lock().unwrap();
+                settings.software_fallback.to_value()
+            }
+            "output-scale" => {
+                let settings = self.settings.lock().unwrap();
+                settings.output_scale.to_value()
+            }
+            "output-transform" => {
+                let settings = self.settings.lock().unwrap();
+                settings.output_transform.to_value()
+            }
+            "render-width" => {
+                let settings = self.settings.lock().unwrap();
+                settings.render_width.to_value()
+            }
+            "render-height" => {
+                let settings = self.settings.lock().unwrap();
+                settings.render_height.to_value()
+            }
+            "crop" => {
+                let settings = self.settings.lock().unwrap();
+                settings.crop.to_string().to_value()
+            }
+            "letterbox" => {
+                let settings = self.settings.lock().unwrap();
+                settings.letterbox.to_value()
+            }
+            "background-color" => {
+                let settings = self.settings.lock().unwrap();
+                settings.background_color.to_value()
+            }
+            "wallpaper" => {
+                let settings = self.settings.lock().unwrap();
+                settings.wallpaper.to_value()
+            }
+            "wallpaper-mode" => {
+                let settings = self.settings.lock().unwrap();
+                settings.wallpaper_mode.to_value()
+            }
+            "window" => self.window.lock().unwrap().to_value(),
+            "output-name" => {
+                let settings = self.settings.lock().unwrap();
+                settings.output_info.name.to_value()
+            }
+            "output-make" => {
+                let settings = self.settings.lock().unwrap();
+                settings.output_info.make.to_value()
+            }
+            "output-model" => {
+                let settings = self.settings.lock().unwrap();
+                settings.output_info.model.to_value()
+            }
+            "output-serial" => {
+                let settings = self.settings.lock().unwrap();
+                settings.output_info.serial.to_value()
+            }
+            "output-width-mm" => {
+                let settings = self.settings.lock().unwrap();
+                settings.output_info.physical_size.0.to_value()
+            }
+            "output-height-mm" => {
+                let settings = self.settings.lock().unwrap();
+                settings.output_info.physical_size.1.to_value()
+            }
+            "output-subpixel" => {
+                let settings = self.settings.lock().unwrap();
+                settings.output_info.subpixel.to_value()
+            }
+            "modes" => {
+                let settings = self.settings.lock().unwrap();
+                gst::Array::from_values(settings.modes.iter().map(|mode| mode.to_string().to_send_value())).to_value()
+            }
+            "max-surfaces" => {
+                let settings = self.settings.lock().unwrap();
+                settings.max_surfaces.to_value()
+            }
+            "max-shm-pool-size" => {
+                let settings = self.settings.lock().unwrap();
+                settings.max_shm_pool_size.to_value()
+            }
+            "max-buffer-width" => {
+                let settings = self.settings.lock().unwrap();
+                settings.max_buffer_width.to_value()
+            }
+            "max-buffer-height" => {
+                let settings = self.settings.lock().unwrap();
+                settings.max_buffer_height.to_value()
+            }
+            "max-frame-callbacks" => {
+                let settings = self.settings.lock().unwrap();
+                settings.max_frame_callbacks.to_value()
+            }
+            "restricted-globals" => {
+                let settings = self.settings.lock().unwrap();
+                gst::Array::from_values(settings.restricted_globals.iter().map(|interface| interface.to_send_value()))
+                    .to_value()
+            }
             _ => unreachable!(),
         }
     }
@@ -160,12 +1268,8 @@ impl ElementImpl for WaylandDisplaySrc {
 
     fn pad_templates() -> &'static [gst::PadTemplate] {
         static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
-            let caps = gst_video::VideoCapsBuilder::new()
-                .format(VideoFormat::Rgbx)
-                .height_range(..i32::MAX)
-                .width_range(..i32::MAX)
-                .framerate_range(Fraction::new(1, 1)..Fraction::new(i32::MAX, 1))
-                .build();
+            let mut caps = gl_caps();
+            caps.merge(system_caps());
             let src_pad_template = gst::PadTemplate::new(
                 "src",
                 gst::PadDirection::Src,
@@ -199,22 +1303,26 @@ impl ElementImpl for WaylandDisplaySrc {
     }
 
     fn query(&self, query: &mut gst::QueryRef) -> bool {
-        ElementImplExt::parent_query(self, query)
+        self.handle_context_query(query) || ElementImplExt::parent_query(self, query)
     }
 }
 
 impl BaseSrcImpl for WaylandDisplaySrc {
     fn query(&self, query: &mut gst::QueryRef) -> bool {
-        BaseSrcImplExt::parent_query(self, query)
+        self.handle_context_query(query) || BaseSrcImplExt::parent_query(self, query)
     }
 
     fn caps(&self, filter: Option<&gst::Caps>) -> Option<gst::Caps> {
-        let mut caps = VideoCapsBuilder::new()
-            .format(VideoFormat::Rgbx)
-            .height_range(..i32::MAX)
-            .width_range(..i32::MAX)
-            .framerate_range(Fraction::new(1, 1)..Fraction::new(i32::MAX, 1))
-            .build();
+        // GL memory is preferred, but only possible with a GL renderer and for the whole output
+        let has_gl = *self.window.lock().unwrap() == 0
+            && self
+                .state
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map_or(true, |state| state.gl.is_some());
+        let mut caps = if has_gl { gl_caps() } else { gst::Caps::new_empty() };
+        caps.merge(system_caps());
 
         if let Some(filter) = filter {
             caps = caps.intersect(filter);
@@ -227,6 +1335,54 @@ impl BaseSrcImpl for WaylandDisplaySrc {
         self.parent_negotiate()
     }
 
+    fn fixate(&self, mut caps: gst::Caps) -> gst::Caps {
+        if let Some(mode) = *self.requested_mode.lock().unwrap() {
+            let caps = caps.make_mut();
+            for structure in caps.iter_mut() {
+                structure.fixate_field_nearest_int("width", mode.width);
+                structure.fixate_field_nearest_int("height", mode.height);
+                structure.fixate_field_nearest_fraction("framerate", mode.framerate());
+            }
+        }
+        self.parent_fixate(caps)
+    }
+
+    fn decide_allocation(
+        &self,
+        query: &mut gst::query::Allocation,
+    ) -> Result<(), gst::LoggableError> {
+        let (caps, _) = query.get_owned();
+        let gl_output = caps.as_ref().map_or(false, |caps| {
+            caps.features(0)
+                .map_or(false, |features| features.contains(gst_gl::CAPS_FEATURE_MEMORY_GL_MEMORY))
+        });
+
+        if gl_output {
+            let mut state = self.state.lock().unwrap();
+            let state = state
+                .as_mut()
+                .ok_or_else(|| gst::loggable_error!(CAT, "Element not started"))?;
+            let gl = state
+                .gl
+                .as_ref()
+                .ok_or_else(|| gst::loggable_error!(CAT, "GL memory negotiated without a GL context"))?;
+            let info = gst_video::VideoInfo::from_caps(caps.as_ref().unwrap())?;
+
+            // the base class configures and activates whatever pool comes first
+            let pool = gst_gl::GLBufferPool::new(&gl.context);
+            if query.allocation_pools().is_empty() {
+                query.add_allocation_pool(Some(&pool), info.size() as u32, 2, 0);
+            } else {
+                query.set_nth_allocation_pool(0, Some(&pool), info.size() as u32, 2, 0);
+            }
+            state.gl_output = true;
+        } else if let Some(state) = self.state.lock().unwrap().as_mut() {
+            state.gl_output = false;
+        }
+
+        self.parent_decide_allocation(query)
+    }
+
     fn event(&self, event: &Event) -> bool {
         if event.type_() == gst::EventType::CustomUpstream {
             let structure = event.structure().expect("Unable to get message structure");
@@ -314,13 +1470,15 @@ impl BaseSrcImpl for WaylandDisplaySrc {
 
     fn set_caps(&self, caps: &gst::Caps) -> Result<(), gst::LoggableError> {
         let video_info = gst_video::VideoInfo::from_caps(caps).expect("failed to get video info");
-        self.state
-            .lock()
-            .unwrap()
-            .as_mut()
-            .unwrap()
-            .display
-            .set_video_info(video_info);
+        {
+            let mut state = self.state.lock().unwrap();
+            let state = state.as_mut().unwrap();
+            // HDR metadata updates and window sizes only touch the caps, the output stays as it is
+            if state.video_info.as_ref() != Some(&video_info) && !*self.window_caps.lock().unwrap() {
+                state.display.set_video_info(video_info.clone());
+                state.video_info = Some(video_info);
+            }
+        }
 
         self.parent_set_caps(caps)
     }
@@ -335,15 +1493,132 @@ impl BaseSrcImpl for WaylandDisplaySrc {
         let elem = self.obj().upcast_ref::<gst::Element>().to_owned();
         let subscriber = Registry::default().with(GstLayer);
 
-        let Ok(mut display) = tracing::subscriber::with_default(subscriber, || {
-            WaylandDisplay::new(settings.render_node.clone())
-        }) else {
-            return Err(gst::error_msg!(LibraryError::Failed, ("Failed to open drm node {}, if you want to utilize software rendering set `render-node=software`.", settings.render_node.as_deref().unwrap_or("/dev/dri/renderD128"))));
+        let socket = settings.socket().map_err(|err| {
+            gst::error_msg!(ResourceError::OpenReadWrite, ("Invalid socket-fd {}: {}", settings.socket_fd, err))
+        })?;
+        let mut display = match tracing::subscriber::with_default(subscriber, || {
+            WaylandDisplay::new_with_socket(settings.render_node.clone(), socket)
+        }) {
+            Ok(display) => display,
+            Err(DisplayError::NoRenderTarget { tried, error }) => {
+                return Err(gst::error_msg!(LibraryError::Failed, ("No usable render node, tried {}", tried.join(", ")), ["{}", error]));
+            }
+            Err(err @ (DisplayError::RenderNode(_) | DisplayError::NoEglDevice | DisplayError::Egl(_) | DisplayError::Renderer(_) | DisplayError::Pixman(_))) => {
+                return Err(gst::error_msg!(LibraryError::Failed, ("Failed to open drm node {}, if you want to utilize software rendering set `render-node=software`.", settings.render_node.as_deref().unwrap_or("auto")), ["{}", err]));
+            }
+            Err(err @ DisplayError::Socket(_)) => {
+                return Err(gst::error_msg!(ResourceError::OpenReadWrite, ("{}", err)));
+            }
+            Err(err) => {
+                return Err(gst::error_msg!(LibraryError::Init, ("Failed to start compositor"), ["{}", err]));
+            }
         };
 
         for path in &settings.input_devices {
             display.add_input_device(path);
         }
+        display.set_eos_on_last_client(settings.eos_on_last_client);
+        display.set_client_limits(settings.client_limits());
+        display.set_global_filter(
+            true,
+            GlobalFilter {
+                allow: None,
+                deny: settings.restricted_globals.clone(),
+            },
+        );
+        display.set_software_fallback(settings.software_fallback);
+        display.set_output_scale(settings.output_scale);
+        display.set_output_transform(settings.output_transform);
+        display.set_render_size(settings.render_size());
+        display.set_frame_crop(settings.crop);
+        display.set_letterbox(settings.letterbox);
+        display.set_background_color(argb_to_rgba(settings.background_color));
+        if let Some(path) = settings.wallpaper.clone() {
+            // shows up once decoded, the decoder waits for the state set up here
+            self.load_wallpaper(path);
+        }
+        display.set_wallpaper_mode(settings.wallpaper_mode);
+        display.set_modes(settings.modes.clone());
+        display.set_output_info(settings.output_info.clone());
+
+        let weak_elem = self.obj().downgrade();
+        let exit_action = settings.exit_action;
+        display.set_event_callback(move |event| {
+            if let Some(elem) = weak_elem.upgrade() {
+                let message = gst::message::Element::builder(event.to_structure())
+                    .src(&elem)
+                    .build();
+                if let Err(err) = elem.post_message(message) {
+                    gst::warning!(CAT, "Failed to post event to gstreamer bus: {}", err);
+                }
+
+                if let DisplayEvent::QuotaExceeded { client_id, quota, .. } = event {
+                    gst::element_warning!(
+                        elem,
+                        ResourceError::NoSpaceLeft,
+                        ("Client {} exceeded its {} quota and was disconnected", client_id, quota)
+                    );
+                }
+
+                if let DisplayEvent::ContextLost { recovered, render_node } = &event {
+                    // the state is locked by a `create` waiting for a frame, it rebuilds the GL objects itself
+                    *elem.imp().gl_lost.lock().unwrap() = true;
+                    if *recovered {
+                        gst::element_warning!(
+                            elem,
+                            ResourceError::Failed,
+                            ("Rendering context lost, recovered on {}", render_node)
+                        );
+                    } else {
+                        gst::element_error!(
+                            elem,
+                            ResourceError::Failed,
+                            ("Rendering context lost and the renderer couldn't be recreated")
+                        );
+                    }
+                }
+
+                if let DisplayEvent::HdrMetadataChanged {
+                    output: PRIMARY_OUTPUT,
+                    metadata,
+                } = &event
+                {
+                    *elem.imp().pending_hdr_metadata.lock().unwrap() = Some(metadata.clone());
+                }
+
+                if let DisplayEvent::WindowDestroyed(id) = &event {
+                    if *id == *elem.imp().window.lock().unwrap() {
+                        gst::info!(CAT, obj: elem, "Captured window {} closed, ending stream", id);
+                        *elem.imp().eos_pending.lock().unwrap() = true;
+                    }
+                }
+
+                if let DisplayEvent::ModeRequested { output: PRIMARY_OUTPUT, mode } = event {
+                    // downstream gets asked for the new mode with the next buffer
+                    *elem.imp().requested_mode.lock().unwrap() = Some(mode);
+                    elem.src_pad().mark_reconfigure();
+                }
+
+                if let DisplayEvent::ProcessExited { exit_code, .. } = event {
+                    match exit_action {
+                        ExitAction::None => {}
+                        ExitAction::Error if exit_code != Some(0) => {
+                            gst::element_error!(
+                                elem,
+                                ResourceError::Failed,
+                                ("Application exited with status {}", exit_code.unwrap_or(-1))
+                            );
+                            *elem.imp().error_pending.lock().unwrap() = true;
+                        }
+                        ExitAction::Eos | ExitAction::Error => {
+                            // pushing EOS from the compositor thread would deadlock with a `create`
+                            // waiting for a frame, so it ends the stream itself
+                            *elem.imp().eos_pending.lock().unwrap() = true;
+                        }
+                    }
+                }
+            }
+        });
 
         let mut structure = Structure::builder("wayland.src");
         for (key, var) in display.env_vars().flat_map(|var| var.split_once("=")) {
@@ -354,17 +1629,52 @@ impl BaseSrcImpl for WaylandDisplaySrc {
             gst::warning!(CAT, "Failed to post environment to gstreamer bus: {}", err);
         }
 
-        *state = Some(State { display });
+        if let Some(command) = settings.command.as_deref() {
+            let subscriber = Registry::default().with(GstLayer);
+            let envs = settings.envs.iter().flat_map(|var| var.split_once('='));
+            let pid = tracing::subscriber::with_default(subscriber, || {
+                display.spawn(command, settings.args.iter().map(String::as_str), envs)
+            })
+            .map_err(|err| {
+                gst::error_msg!(ResourceError::Failed, ("Failed to start {}: {}", command, err))
+            })?;
+
+            let structure = Structure::builder("wayland.process-started")
+                .field("pid", pid)
+                .field("command", command)
+                .build();
+            if let Err(err) = elem.post_message(gst::message::Element::builder(structure).src(&elem).build()) {
+                gst::warning!(CAT, "Failed to post process to gstreamer bus: {}", err);
+            }
+        }
+
+        let gl = self.init_gl(&display);
+        *self.handle.lock().unwrap() = Some(display.handle());
+
+        *state = Some(State {
+            display,
+            gl,
+            gl_output: false,
+            video_info: None,
+        });
 
         Ok(())
     }
 
     fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.handle.lock().unwrap() = None;
         let mut state = self.state.lock().unwrap();
         if let Some(state) = state.take() {
+            // the shared contexts have to go before the compositor's own one
+            std::mem::drop(state.gl);
             let subscriber = Registry::default().with(GstLayer);
             tracing::subscriber::with_default(subscriber, || std::mem::drop(state.display));
         }
+        *self.pending_hdr_metadata.lock().unwrap() = None;
+        *self.requested_mode.lock().unwrap() = None;
+        *self.eos_pending.lock().unwrap() = false;
+        *self.error_pending.lock().unwrap() = false;
+        *self.gl_lost.lock().unwrap() = false;
         Ok(())
     }
 
@@ -378,14 +1688,57 @@ impl PushSrcImpl for WaylandDisplaySrc {
         &self,
         _buffer: Option<&mut gst::BufferRef>,
     ) -> Result<CreateSuccess, gst::FlowError> {
-        let mut state_guard = self.state.lock().unwrap();
-        let Some(state) = state_guard.as_mut() else {
-            return Err(gst::FlowError::Eos);
+        let mut attempts = 0;
+        let (result, frame_size) = loop {
+            if std::mem::take(&mut *self.gl_lost.lock().unwrap()) {
+                self.refresh_gl()?;
+            }
+            let (result, frame_size, gl_output) = self.render_frame()?;
+            if !(gl_output && *self.gl_lost.lock().unwrap()) {
+                break (result, frame_size);
+            }
+            // the texture belongs to the lost context, render again once the GL objects are rebuilt
+            attempts += 1;
+            if attempts >= MAX_GL_ATTEMPTS {
+                gst::element_imp_error!(
+                    self,
+                    ResourceError::Failed,
+                    ("GL context kept getting lost, no frame could be rendered")
+                );
+                return Err(gst::FlowError::Error);
+            }
         };
 
-        let subscriber = Registry::default().with(GstLayer);
-        tracing::subscriber::with_default(subscriber, || {
-            state.display.frame().map(CreateSuccess::NewBuffer)
-        })
+        if result.is_ok() {
+            if let Some((width, height)) = frame_size {
+                self.update_frame_size(width, height)?;
+            }
+            if let Some(metadata) = self.pending_hdr_metadata.lock().unwrap().take() {
+                self.update_hdr_caps(metadata.as_ref());
+            }
+        }
+
+        if *self.error_pending.lock().unwrap() {
+            // the error message got posted when the application exited
+            return Err(gst::FlowError::Error);
+        }
+        if *self.eos_pending.lock().unwrap() {
+            gst::info!(CAT, imp: self, "Application exited or window closed, ending stream");
+            return Err(gst::FlowError::Eos);
+        }
+
+        match result {
+            Ok(buffer) => Ok(CreateSuccess::NewBuffer(buffer)),
+            Err(FrameError::CompositorDied) => {
+                gst::element_imp_error!(
+                    self,
+                    ResourceError::Failed,
+                    ("Compositor thread died, restart the element to recover"),
+                    ["{}", FrameError::CompositorDied]
+                );
+                Err(gst::FlowError::Error)
+            }
+            Err(err) => Err(err.into()),
+        }
     }
 }
@@ -0,0 +1,112 @@
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            element::{
+                memory::{MemoryBuffer, MemoryRenderBuffer, MemoryRenderBufferRenderElement},
+                solid::{SolidColorBuffer, SolidColorRenderElement},
+                Kind,
+            },
+            ImportAll, ImportMem, Renderer, Texture,
+        },
+    },
+    utils::{Logical, Point, Rectangle, Size, Transform},
+};
+
+use super::{State, ViewElement};
+use crate::{RgbaImage, WallpaperMode};
+
+/// An image shown behind the windows.
+pub struct Wallpaper {
+    buffer: MemoryRenderBuffer,
+    size: Size<i32, Logical>,
+}
+
+impl Wallpaper {
+    fn new(image: &RgbaImage) -> Self {
+        // RGBA bytes are ABGR in little endian words
+        let buffer = MemoryBuffer::from_slice(
+            &image.premultiplied_data(),
+            Fourcc::Abgr8888,
+            (image.width(), image.height()),
+        );
+        Wallpaper {
+            buffer: MemoryRenderBuffer::from_memory(buffer, 1, Transform::Normal, None),
+            size: (image.width(), image.height()).into(),
+        }
+    }
+
+    /// Where the wallpaper is drawn on an output, and which part of it.
+    fn geometry(
+        &self,
+        mode: WallpaperMode,
+        output_size: Size<i32, Logical>,
+    ) -> (Rectangle<f64, Logical>, Option<Rectangle<f64, Logical>>) {
+        let image = self.size.to_f64();
+        let output = output_size.to_f64();
+        let centered = |size: Size<f64, Logical>| {
+            let loc = ((output.w - size.w) / 2.0, (output.h - size.h) / 2.0);
+            Rectangle::from_loc_and_size(loc, size)
+        };
+
+        match mode {
+            WallpaperMode::Stretch => (Rectangle::from_loc_and_size((0.0, 0.0), output), None),
+            WallpaperMode::Fit => {
+                let zoom = f64::min(output.w / image.w, output.h / image.h);
+                (centered((image.w * zoom, image.h * zoom).into()), None)
+            }
+            WallpaperMode::Fill => {
+                let zoom = f64::max(output.w / image.w, output.h / image.h);
+                let src_size = Size::from((output.w / zoom, output.h / zoom));
+                let src_loc = ((image.w - src_size.w) / 2.0, (image.h - src_size.h) / 2.0);
+                (
+                    Rectangle::from_loc_and_size((0.0, 0.0), output),
+                    Some(Rectangle::from_loc_and_size(src_loc, src_size)),
+                )
+            }
+            WallpaperMode::Center => (centered(image), None),
+        }
+    }
+}
+
+/// Elements for the background of an output, in output coordinates and from top to bottom.
+pub fn background_elements<R>(
+    renderer: &mut R,
+    color: &SolidColorBuffer,
+    wallpaper: Option<&Wallpaper>,
+    mode: WallpaperMode,
+    output_size: Size<i32, Logical>,
+    scale: f64,
+) -> Result<Vec<ViewElement<R>>, R::Error>
+where
+    R: Renderer + ImportAll + ImportMem,
+    R::TextureId: Texture + Clone + 'static,
+{
+    let mut elements = Vec::new();
+    if let Some(wallpaper) = wallpaper {
+        let (geometry, src) = wallpaper.geometry(mode, output_size);
+        elements.push(ViewElement::Memory(MemoryRenderBufferRenderElement::from_buffer(
+            renderer,
+            geometry.loc.to_physical(scale),
+            &wallpaper.buffer,
+            None,
+            src,
+            Some(geometry.size.to_i32_round()),
+            Kind::Unspecified,
+        )?));
+    }
+    elements.push(ViewElement::Color(SolidColorRenderElement::from_buffer(
+        color,
+        Point::from((0, 0)),
+        scale,
+        1.0,
+        Kind::Unspecified,
+    )));
+    Ok(elements)
+}
+
+impl State {
+    pub fn set_wallpaper(&mut self, image: Option<RgbaImage>) {
+        self.wallpaper = image.as_ref().map(Wallpaper::new);
+    }
+}
@@ -0,0 +1,151 @@
+use std::time::{Duration, Instant};
+
+use gst_video::{VideoFormat, VideoInfo};
+use smithay::{
+    backend::renderer::{
+        damage::OutputDamageTracker,
+        element::{surface::WaylandSurfaceRenderElement, AsRenderElements},
+        gles::{GlesRenderbuffer, GlesRenderer},
+        pixman::PixmanRenderer,
+        Bind, Unbind,
+    },
+    utils::{Point, Transform},
+};
+
+use super::{
+    download_frame, frame_error, pixman_frame, swap_buffers_error, RenderBackend, State,
+    PRIMARY_OUTPUT,
+};
+use crate::FrameError;
+
+/// Offscreen target of a captured window, kept until the window is gone or changes its size.
+pub struct WindowCapture {
+    video_info: VideoInfo,
+    scale: f64,
+    dtr: OutputDamageTracker,
+    renderbuffer: Option<GlesRenderbuffer>,
+    last_render: Option<Instant>,
+}
+
+impl State {
+    /// Drops the offscreen target of a window that is gone.
+    pub fn remove_window_capture(&mut self, id: u64) {
+        self.window_captures.remove(&id);
+    }
+
+    /// Time left until the next frame of a window is due, captures follow the framerate of the primary output.
+    pub fn window_frame_delay(&self, id: u64) -> Option<Duration> {
+        let last_render = self.window_captures.get(&id)?.last_render?;
+        let framerate = self.virtual_output(PRIMARY_OUTPUT)?.video_info.fps();
+        if framerate.numer() <= 0 || framerate.denom() <= 0 {
+            return None;
+        }
+        let duration = Duration::from_secs_f64(framerate.denom() as f64 / framerate.numer() as f64);
+        duration
+            .checked_sub(Instant::now().duration_since(last_render))
+            .filter(|wait| !wait.is_zero())
+    }
+
+    /// Renders a single window, rebuilding the renderer once if the rendering context got lost.
+    pub fn render_window_frame(&mut self, id: u64, now: Instant) -> Result<(gst::Buffer, VideoInfo), FrameError> {
+        if self.renderer_lost {
+            return Err(FrameError::ContextLost);
+        }
+        let result = match self.create_window_frame(id) {
+            Err(FrameError::ContextLost) if self.recover_renderer() => self.create_window_frame(id),
+            result => result,
+        };
+        if let Some(capture) = self.window_captures.get_mut(&id) {
+            capture.last_render = Some(now);
+        }
+        result
+    }
+
+    /// Renders a window with its popups and subsurfaces offscreen, at the size of its geometry
+    /// and independent of the output it is shown on.
+    fn create_window_frame(&mut self, id: u64) -> Result<(gst::Buffer, VideoInfo), FrameError> {
+        let Some(window) = self.find_window(id) else {
+            tracing::warn!(id, "No such window.");
+            self.window_captures.remove(&id);
+            return Err(FrameError::Render);
+        };
+
+        let scale = self.output_scale;
+        let geometry = window.geometry();
+        let size = geometry.size.to_physical_precise_round(scale);
+        if size.w <= 0 || size.h <= 0 {
+            return Err(FrameError::Render);
+        }
+        // frames use the format negotiated for the primary output, the size follows the window
+        let format = self
+            .virtual_output(PRIMARY_OUTPUT)
+            .map_or(VideoFormat::Rgba, |output| output.video_info.format());
+        let video_info = VideoInfo::builder(format, size.w as u32, size.h as u32)
+            .build()
+            .map_err(|_| FrameError::Render)?;
+        // the window geometry excludes client side shadows, they start at negative coordinates
+        let location = (Point::from((0, 0)) - geometry.loc).to_physical_precise_round(scale);
+
+        // the stream follows the window, a new target is only needed once it got resized
+        let reusable = self
+            .window_captures
+            .get(&id)
+            .map_or(false, |capture| capture.video_info == video_info && capture.scale == scale);
+        if !reusable {
+            let last_render = self.window_captures.get(&id).and_then(|capture| capture.last_render);
+            let renderbuffer = self.renderer.create_renderbuffer(&video_info).map_err(|err| {
+                tracing::error!(?err, "Failed to create renderbuffer.");
+                FrameError::Render
+            })?;
+            self.window_captures.insert(
+                id,
+                WindowCapture {
+                    video_info: video_info.clone(),
+                    scale,
+                    dtr: OutputDamageTracker::new(size, scale, Transform::Normal),
+                    renderbuffer,
+                    last_render,
+                },
+            );
+        }
+        let capture = self.window_captures.get_mut(&id).unwrap();
+        let dtr = &mut capture.dtr;
+
+        let buffer = match &mut self.renderer {
+            RenderBackend::Gles { renderer, .. } => {
+                let elements: Vec<WaylandSurfaceRenderElement<GlesRenderer>> =
+                    window.render_elements(renderer, location, scale.into(), 1.0);
+                let Some(renderbuffer) = capture.renderbuffer.clone() else {
+                    return Err(FrameError::Render);
+                };
+
+                renderer.bind(renderbuffer).map_err(swap_buffers_error)?;
+                let buffer = dtr
+                    .render_output(renderer, 0, &elements, [0.0, 0.0, 0.0, 0.0])
+                    .map_err(frame_error)
+                    .and_then(|_| download_frame(renderer, &video_info));
+                renderer.unbind().map_err(swap_buffers_error)?;
+                buffer?
+            }
+            RenderBackend::Pixman(renderer) => {
+                let elements: Vec<WaylandSurfaceRenderElement<PixmanRenderer>> =
+                    window.render_elements(renderer, location, scale.into(), 1.0);
+
+                pixman_frame(renderer, &video_info, |renderer| {
+                    dtr.render_output(renderer, 0, &elements, [0.0, 0.0, 0.0, 0.0])
+                        .map_err(frame_error)
+                })?
+                .0
+            }
+        };
+
+        // windows that are only captured still need frame callbacks to keep drawing
+        if let Some(output) = self.window_output(&window) {
+            window.send_frame(output, self.clock.now(), Some(Duration::ZERO), |_, _| {
+                Some(output.clone())
+            });
+        }
+
+        Ok((buffer, video_info))
+    }
+}
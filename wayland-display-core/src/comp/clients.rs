@@ -0,0 +1,250 @@
+use std::{
+    os::unix::net::UnixStream,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use smithay::{
+    backend::renderer::buffer_dimensions,
+    reexports::wayland_server::{
+        backend::{ClientData, ClientId, DisconnectReason, ProtocolError},
+        protocol::{wl_display, wl_surface::WlSurface},
+        Client, Resource,
+    },
+    wayland::{
+        compositor::{
+            add_destruction_hook, add_pre_commit_hook, with_states, BufferAssignment,
+            CompositorClientState, SurfaceAttributes,
+        },
+        security_context::SecurityContext,
+    },
+};
+
+use super::State;
+use crate::{ClientUsage, DisplayEvent, GlobalFilter, QuotaViolation};
+
+static NEXT_CLIENT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Clients, that disconnected since the last time the event loop was idle.
+pub type DisconnectedClients = Arc<Mutex<Vec<(u64, DisconnectReason)>>>;
+
+#[derive(Debug)]
+pub struct ClientState {
+    pub compositor_state: CompositorClientState,
+    pub id: u64,
+    /// Restricted (sandboxed) clients can't see privileged globals.
+    pub restricted: bool,
+    /// The globals this client can see.
+    pub globals: GlobalFilter,
+    pub security_context: Option<SecurityContext>,
+    disconnected: DisconnectedClients,
+}
+
+/// Whether a client may see the global of `interface`, according to its [`GlobalFilter`].
+pub fn can_view_global(client: &Client, interface: &str) -> bool {
+    client
+        .get_data::<ClientState>()
+        .map_or(false, |data| data.globals.allows(interface))
+}
+
+impl ClientData for ClientState {
+    fn initialized(&self, _client_id: ClientId) {}
+
+    fn disconnected(&self, _client_id: ClientId, reason: DisconnectReason) {
+        // we don't have access to the state here, so let the event loop pick it up
+        self.disconnected.lock().unwrap().push((self.id, reason));
+    }
+}
+
+impl State {
+    /// Adds a new client connected through `stream` and returns its id.
+    ///
+    /// Without an explicit `globals` filter, the one configured for (un)restricted clients applies.
+    pub fn insert_client(
+        &mut self,
+        stream: UnixStream,
+        restricted: bool,
+        globals: Option<GlobalFilter>,
+        security_context: Option<SecurityContext>,
+    ) -> std::io::Result<u64> {
+        let id = NEXT_CLIENT_ID.fetch_add(1, Ordering::SeqCst);
+        let globals = globals.unwrap_or_else(|| {
+            if restricted {
+                self.restricted_globals.clone()
+            } else {
+                self.trusted_globals.clone()
+            }
+        });
+        let client = self.dh.insert_client(
+            stream,
+            Arc::new(ClientState {
+                compositor_state: CompositorClientState::default(),
+                id,
+                restricted,
+                globals,
+                security_context: security_context.clone(),
+                disconnected: self.disconnected_clients.clone(),
+            }),
+        )?;
+
+        let credentials = client.get_credentials(&self.dh).ok();
+        tracing::info!(id, ?credentials, restricted, ?security_context, "Client connected.");
+        self.emit(DisplayEvent::ClientConnected {
+            id,
+            pid: credentials.as_ref().map(|credentials| credentials.pid),
+            uid: credentials.as_ref().map(|credentials| credentials.uid),
+            restricted,
+            sandbox_engine: security_context
+                .as_ref()
+                .and_then(|context| context.sandbox_engine.clone()),
+            app_id: security_context.and_then(|context| context.app_id),
+        });
+        self.clients.insert(id, credentials);
+
+        Ok(id)
+    }
+
+    pub fn handle_disconnected_clients(&mut self) {
+        let disconnected = std::mem::take(&mut *self.disconnected_clients.lock().unwrap());
+        for (id, reason) in disconnected {
+            let credentials = self.clients.remove(&id).flatten();
+            self.client_surfaces.remove(&id);
+            let reason = match reason {
+                DisconnectReason::ConnectionClosed => String::from("connection closed"),
+                DisconnectReason::ProtocolError(err) => format!(
+                    "protocol error {} on {}@{}: {}",
+                    err.code, err.object_interface, err.object_id, err.message
+                ),
+            };
+            tracing::info!(id, ?credentials, reason, "Client disconnected.");
+            self.emit(DisplayEvent::ClientDisconnected {
+                id,
+                pid: credentials.as_ref().map(|credentials| credentials.pid),
+                uid: credentials.as_ref().map(|credentials| credentials.uid),
+                reason,
+            });
+
+            if self.eos_on_last_client && self.clients.is_empty() {
+                tracing::info!("Last client disconnected, ending stream.");
+                self.eos = true;
+            }
+        }
+    }
+
+    /// Accounts a newly created surface and installs the commit time quota checks.
+    pub fn track_surface(&mut self, surface: &WlSurface) {
+        let Some(client) = surface.client() else {
+            return;
+        };
+        let Some(id) = client.get_data::<ClientState>().map(|data| data.id) else {
+            return;
+        };
+
+        let surfaces = self.client_surfaces.entry(id).or_default();
+        surfaces.push(surface.clone());
+        let usage = ClientUsage {
+            surfaces: Some(surfaces.len()),
+            ..Default::default()
+        };
+        if let Some(violation) = self.limits.check(&usage) {
+            self.quota_exceeded(&client, violation);
+            return;
+        }
+
+        add_destruction_hook(surface, move |state: &mut State, surface: &WlSurface| {
+            if let Some(surfaces) = state.client_surfaces.get_mut(&id) {
+                surfaces.retain(|tracked| tracked != surface);
+            }
+        });
+        add_pre_commit_hook(surface, |state: &mut State, _dh, surface: &WlSurface| {
+            state.check_commit_limits(surface);
+        });
+    }
+
+    /// Checks the size of a new or resized shm pool against the client's quota, before it gets mapped.
+    pub fn check_shm_pool_size(&mut self, client: &Client, size: i32) -> bool {
+        // negative sizes are a protocol error of their own
+        let usage = ClientUsage {
+            shm_pool_size: usize::try_from(size).ok(),
+            ..Default::default()
+        };
+        match self.limits.check(&usage) {
+            Some(violation) => {
+                self.quota_exceeded(client, violation);
+                false
+            }
+            None => true,
+        }
+    }
+
+    fn check_commit_limits(&mut self, surface: &WlSurface) {
+        let Some(client) = surface.client() else {
+            return;
+        };
+        let Some(id) = client.get_data::<ClientState>().map(|data| data.id) else {
+            return;
+        };
+        let buffer = with_states(surface, |states| {
+            match states.cached_state.get::<SurfaceAttributes>().pending().buffer.as_ref() {
+                Some(BufferAssignment::NewBuffer(buffer)) => Some(buffer.clone()),
+                _ => None,
+            }
+        });
+        // callbacks pending on this commit get appended to the ones of all surfaces not yet sent
+        let frame_callbacks = self
+            .client_surfaces
+            .get(&id)
+            .into_iter()
+            .flatten()
+            .map(|tracked| {
+                with_states(tracked, |states| {
+                    let mut attributes = states.cached_state.get::<SurfaceAttributes>();
+                    let pending = if tracked == surface {
+                        attributes.pending().frame_callbacks.len()
+                    } else {
+                        0
+                    };
+                    pending + attributes.current().frame_callbacks.len()
+                })
+            })
+            .sum::<usize>();
+
+        let usage = ClientUsage {
+            frame_callbacks: Some(frame_callbacks),
+            buffer_size: buffer
+                .as_ref()
+                .and_then(buffer_dimensions)
+                .map(|size| (size.w, size.h)),
+            ..Default::default()
+        };
+        if let Some(violation) = self.limits.check(&usage) {
+            self.quota_exceeded(&client, violation);
+        }
+    }
+
+    fn quota_exceeded(&mut self, client: &Client, violation: QuotaViolation) {
+        let Some(client_id) = client.get_data::<ClientState>().map(|data| data.id) else {
+            return;
+        };
+        let QuotaViolation { quota, limit, value } = violation;
+        tracing::warn!(client_id, quota, limit, value, "Client exceeded quota, disconnecting.");
+        self.emit(DisplayEvent::QuotaExceeded {
+            client_id,
+            quota,
+            limit,
+            value,
+        });
+
+        client.kill(
+            &self.dh,
+            ProtocolError {
+                code: wl_display::Error::NoMemory as u32,
+                object_id: 1,
+                object_interface: wl_display::WlDisplay::interface().name.to_string(),
+                message: format!("{} quota exceeded ({} > {})", quota, value, limit),
+            },
+        );
+    }
+}
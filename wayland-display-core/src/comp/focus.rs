@@ -1,7 +1,7 @@
 use std::borrow::Cow;
 use smithay::{
     backend::input::KeyState,
-    desktop::{PopupKind, Window, WindowSurface},
+    desktop::{LayerSurface, PopupKind, Window, WindowSurface},
     input::{
         keyboard::{KeyboardTarget, KeysymHandle, ModifiersState},
         pointer::{AxisFrame, ButtonEvent, MotionEvent, PointerTarget, RelativeMotionEvent, GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent, GesturePinchEndEvent, GesturePinchUpdateEvent, GestureSwipeBeginEvent, GestureSwipeEndEvent, GestureSwipeUpdateEvent},
@@ -18,6 +18,7 @@ use crate::comp::State;
 pub enum FocusTarget {
     Wayland(Window),
     Popup(PopupKind),
+    LayerSurface(LayerSurface),
 }
 
 impl IsAlive for FocusTarget {
@@ -25,6 +26,7 @@ impl IsAlive for FocusTarget {
         match self {
             FocusTarget::Wayland(w) => w.alive(),
             FocusTarget::Popup(p) => p.alive(),
+            FocusTarget::LayerSurface(l) => l.alive(),
         }
     }
 }
@@ -41,6 +43,12 @@ impl From<PopupKind> for FocusTarget {
     }
 }
 
+impl From<LayerSurface> for FocusTarget {
+    fn from(l: LayerSurface) -> Self {
+        FocusTarget::LayerSurface(l)
+    }
+}
+
 impl KeyboardTarget<State> for FocusTarget {
     fn enter(
         &self,
@@ -56,6 +64,9 @@ impl KeyboardTarget<State> for FocusTarget {
             FocusTarget::Popup(p) => {
                 KeyboardTarget::enter(p.wl_surface(), seat, data, keys, serial)
             }
+            FocusTarget::LayerSurface(l) => {
+                KeyboardTarget::enter(l.wl_surface(), seat, data, keys, serial)
+            }
         }
     }
 
@@ -65,6 +76,7 @@ impl KeyboardTarget<State> for FocusTarget {
                 WindowSurface::Wayland(w) => {KeyboardTarget::leave(w.wl_surface(), seat, data, serial)}
             }
             FocusTarget::Popup(p) => KeyboardTarget::leave(p.wl_surface(), seat, data, serial),
+            FocusTarget::LayerSurface(l) => KeyboardTarget::leave(l.wl_surface(), seat, data, serial),
         }
     }
 
@@ -81,7 +93,8 @@ impl KeyboardTarget<State> for FocusTarget {
             FocusTarget::Wayland(w) => match w.underlying_surface(){
                 WindowSurface::Wayland(w) => {KeyboardTarget::key(w.wl_surface(), seat, data, key, state, serial, time)}
             },
-            FocusTarget::Popup(p) => KeyboardTarget::key(p.wl_surface(), seat, data, key, state, serial, time)
+            FocusTarget::Popup(p) => KeyboardTarget::key(p.wl_surface(), seat, data, key, state, serial, time),
+            FocusTarget::LayerSurface(l) => KeyboardTarget::key(l.wl_surface(), seat, data, key, state, serial, time),
         }
     }
 
@@ -97,6 +110,7 @@ impl KeyboardTarget<State> for FocusTarget {
                 WindowSurface::Wayland(w) => {KeyboardTarget::modifiers(w.wl_surface(), seat, data, modifiers, serial)}
             },
             FocusTarget::Popup(p) => p.wl_surface().modifiers(seat, data, modifiers, serial),
+            FocusTarget::LayerSurface(l) => l.wl_surface().modifiers(seat, data, modifiers, serial),
         }
     }
 }
@@ -108,6 +122,7 @@ impl PointerTarget<State> for FocusTarget {
                 WindowSurface::Wayland(w) => {PointerTarget::enter(w.wl_surface(), seat, data, event)}
             },
             FocusTarget::Popup(p) => PointerTarget::enter(p.wl_surface(), seat, data, event),
+            FocusTarget::LayerSurface(l) => PointerTarget::enter(l.wl_surface(), seat, data, event),
         }
     }
 
@@ -117,6 +132,7 @@ impl PointerTarget<State> for FocusTarget {
                 WindowSurface::Wayland(w) => {PointerTarget::motion(w.wl_surface(), seat, data, event)}
             },
             FocusTarget::Popup(p) => PointerTarget::motion(p.wl_surface(), seat, data, event),
+            FocusTarget::LayerSurface(l) => PointerTarget::motion(l.wl_surface(), seat, data, event),
         }
     }
 
@@ -131,6 +147,7 @@ impl PointerTarget<State> for FocusTarget {
                 WindowSurface::Wayland(w) => {PointerTarget::relative_motion(w.wl_surface(), seat, data, event)}
             },
             FocusTarget::Popup(p) => PointerTarget::relative_motion(p.wl_surface(), seat, data, event),
+            FocusTarget::LayerSurface(l) => PointerTarget::relative_motion(l.wl_surface(), seat, data, event),
         }
     }
 
@@ -140,6 +157,7 @@ impl PointerTarget<State> for FocusTarget {
                 WindowSurface::Wayland(w) => {PointerTarget::button(w.wl_surface(), seat, data, event)}
             },
             FocusTarget::Popup(p) => PointerTarget::button(p.wl_surface(), seat, data, event),
+            FocusTarget::LayerSurface(l) => PointerTarget::button(l.wl_surface(), seat, data, event),
         }
     }
 
@@ -149,6 +167,7 @@ impl PointerTarget<State> for FocusTarget {
                 WindowSurface::Wayland(w) => {PointerTarget::axis(w.wl_surface(), seat, data, frame)}
             },
             FocusTarget::Popup(p) => PointerTarget::axis(p.wl_surface(), seat, data, frame),
+            FocusTarget::LayerSurface(l) => PointerTarget::axis(l.wl_surface(), seat, data, frame),
         }
     }
 
@@ -158,6 +177,7 @@ impl PointerTarget<State> for FocusTarget {
                 WindowSurface::Wayland(w) => {PointerTarget::frame(w.wl_surface(), seat, data)}
             },
             FocusTarget::Popup(p) => { PointerTarget::frame(p.wl_surface(), seat, data) }
+            FocusTarget::LayerSurface(l) => { PointerTarget::frame(l.wl_surface(), seat, data) }
         }
     }
 
@@ -166,7 +186,8 @@ impl PointerTarget<State> for FocusTarget {
             FocusTarget::Wayland(w) => match w.underlying_surface() {
                 WindowSurface::Wayland(w) => {PointerTarget::gesture_swipe_begin(w.wl_surface(), seat, data, event)}
             },
-            FocusTarget::Popup(p) => PointerTarget::gesture_swipe_begin(p.wl_surface(), seat, data, event)
+            FocusTarget::Popup(p) => PointerTarget::gesture_swipe_begin(p.wl_surface(), seat, data, event),
+            FocusTarget::LayerSurface(l) => PointerTarget::gesture_swipe_begin(l.wl_surface(), seat, data, event),
         }
     }
 
@@ -175,7 +196,8 @@ impl PointerTarget<State> for FocusTarget {
             FocusTarget::Wayland(w) => match w.underlying_surface() {
                 WindowSurface::Wayland(w) => {PointerTarget::gesture_swipe_update(w.wl_surface(), seat, data, event)}
             },
-            FocusTarget::Popup(p) => PointerTarget::gesture_swipe_update(p.wl_surface(), seat, data, event)
+            FocusTarget::Popup(p) => PointerTarget::gesture_swipe_update(p.wl_surface(), seat, data, event),
+            FocusTarget::LayerSurface(l) => PointerTarget::gesture_swipe_update(l.wl_surface(), seat, data, event),
         }
     }
 
@@ -184,7 +206,8 @@ impl PointerTarget<State> for FocusTarget {
             FocusTarget::Wayland(w) => match w.underlying_surface() {
                 WindowSurface::Wayland(w) => {PointerTarget::gesture_swipe_end(w.wl_surface(), seat, data, event)}
             },
-            FocusTarget::Popup(p) => PointerTarget::gesture_swipe_end(p.wl_surface(), seat, data, event)
+            FocusTarget::Popup(p) => PointerTarget::gesture_swipe_end(p.wl_surface(), seat, data, event),
+            FocusTarget::LayerSurface(l) => PointerTarget::gesture_swipe_end(l.wl_surface(), seat, data, event),
         }
     }
 
@@ -193,7 +216,8 @@ impl PointerTarget<State> for FocusTarget {
             FocusTarget::Wayland(w) => match w.underlying_surface() {
                 WindowSurface::Wayland(w) => {PointerTarget::gesture_pinch_begin(w.wl_surface(), seat, data, event)}
             },
-            FocusTarget::Popup(p) => PointerTarget::gesture_pinch_begin(p.wl_surface(), seat, data, event)
+            FocusTarget::Popup(p) => PointerTarget::gesture_pinch_begin(p.wl_surface(), seat, data, event),
+            FocusTarget::LayerSurface(l) => PointerTarget::gesture_pinch_begin(l.wl_surface(), seat, data, event),
         }
     }
 
@@ -202,7 +226,8 @@ impl PointerTarget<State> for FocusTarget {
             FocusTarget::Wayland(w) => match w.underlying_surface() {
                 WindowSurface::Wayland(w) => {PointerTarget::gesture_pinch_update(w.wl_surface(), seat, data, event)}
             },
-            FocusTarget::Popup(p) => PointerTarget::gesture_pinch_update(p.wl_surface(), seat, data, event)
+            FocusTarget::Popup(p) => PointerTarget::gesture_pinch_update(p.wl_surface(), seat, data, event),
+            FocusTarget::LayerSurface(l) => PointerTarget::gesture_pinch_update(l.wl_surface(), seat, data, event),
         }
     }
 
@@ -211,7 +236,8 @@ impl PointerTarget<State> for FocusTarget {
             FocusTarget::Wayland(w) => match w.underlying_surface() {
                 WindowSurface::Wayland(w) => {PointerTarget::gesture_pinch_end(w.wl_surface(), seat, data, event)}
             },
-            FocusTarget::Popup(p) => PointerTarget::gesture_pinch_end(p.wl_surface(), seat, data, event)
+            FocusTarget::Popup(p) => PointerTarget::gesture_pinch_end(p.wl_surface(), seat, data, event),
+            FocusTarget::LayerSurface(l) => PointerTarget::gesture_pinch_end(l.wl_surface(), seat, data, event),
         }
     }
 
@@ -220,7 +246,8 @@ impl PointerTarget<State> for FocusTarget {
             FocusTarget::Wayland(w) => match w.underlying_surface() {
                 WindowSurface::Wayland(w) => {PointerTarget::gesture_hold_begin(w.wl_surface(), seat, data, event)}
             },
-            FocusTarget::Popup(p) => PointerTarget::gesture_hold_begin(p.wl_surface(), seat, data, event)
+            FocusTarget::Popup(p) => PointerTarget::gesture_hold_begin(p.wl_surface(), seat, data, event),
+            FocusTarget::LayerSurface(l) => PointerTarget::gesture_hold_begin(l.wl_surface(), seat, data, event),
         }
     }
 
@@ -229,7 +256,8 @@ impl PointerTarget<State> for FocusTarget {
             FocusTarget::Wayland(w) => match w.underlying_surface() {
                 WindowSurface::Wayland(w) => {PointerTarget::gesture_hold_end(w.wl_surface(), seat, data, event)}
             },
-            FocusTarget::Popup(p) => PointerTarget::gesture_hold_end(p.wl_surface(), seat, data, event)
+            FocusTarget::Popup(p) => PointerTarget::gesture_hold_end(p.wl_surface(), seat, data, event),
+            FocusTarget::LayerSurface(l) => PointerTarget::gesture_hold_end(l.wl_surface(), seat, data, event),
         }
     }
 
@@ -239,6 +267,7 @@ impl PointerTarget<State> for FocusTarget {
                 WindowSurface::Wayland(w) => {PointerTarget::leave(w.wl_surface(), seat, data, serial, time)}
             },
             FocusTarget::Popup(p) => PointerTarget::leave(p.wl_surface(), seat, data, serial, time),
+            FocusTarget::LayerSurface(l) => PointerTarget::leave(l.wl_surface(), seat, data, serial, time),
         }
     }
 }
@@ -248,6 +277,7 @@ impl WaylandFocus for FocusTarget {
         match self {
             FocusTarget::Wayland(w) => w.wl_surface(),
             FocusTarget::Popup(p) => Some(Cow::Borrowed(p.wl_surface())),
+            FocusTarget::LayerSurface(l) => Some(Cow::Borrowed(l.wl_surface())),
         }
     }
 
@@ -255,6 +285,7 @@ impl WaylandFocus for FocusTarget {
         match self {
             FocusTarget::Wayland(w) => w.same_client_as(object_id),
             FocusTarget::Popup(p) => p.wl_surface().same_client_as(object_id),
+            FocusTarget::LayerSurface(l) => l.wl_surface().same_client_as(object_id),
         }
     }
 }
@@ -266,6 +297,7 @@ impl TouchTarget<State> for FocusTarget{
                 WindowSurface::Wayland(w) => {TouchTarget::down(w.wl_surface(), seat, data, event, seq)}
             }
             FocusTarget::Popup(p) => {TouchTarget::down(p.wl_surface(), seat, data, event, seq)}
+            FocusTarget::LayerSurface(l) => {TouchTarget::down(l.wl_surface(), seat, data, event, seq)}
         }
     }
 
@@ -275,6 +307,7 @@ impl TouchTarget<State> for FocusTarget{
                 WindowSurface::Wayland(w) => {TouchTarget::up(w.wl_surface(), seat, data, event, seq)}
             }
             FocusTarget::Popup(p) => {TouchTarget::up(p.wl_surface(), seat, data, event, seq)}
+            FocusTarget::LayerSurface(l) => {TouchTarget::up(l.wl_surface(), seat, data, event, seq)}
         }
     }
 
@@ -284,6 +317,7 @@ impl TouchTarget<State> for FocusTarget{
                 WindowSurface::Wayland(w) => {TouchTarget::motion(w.wl_surface(), seat, data, event, seq)}
             }
             FocusTarget::Popup(p) => {TouchTarget::motion(p.wl_surface(), seat, data, event, seq)}
+            FocusTarget::LayerSurface(l) => {TouchTarget::motion(l.wl_surface(), seat, data, event, seq)}
         }
     }
 
@@ -293,6 +327,7 @@ impl TouchTarget<State> for FocusTarget{
                 WindowSurface::Wayland(w) => {TouchTarget::frame(w.wl_surface(), seat, data, seq)}
             }
             FocusTarget::Popup(p) => {TouchTarget::frame(p.wl_surface(), seat, data, seq)}
+            FocusTarget::LayerSurface(l) => {TouchTarget::frame(l.wl_surface(), seat, data, seq)}
         }
     }
 
@@ -302,6 +337,7 @@ impl TouchTarget<State> for FocusTarget{
                 WindowSurface::Wayland(w) => {TouchTarget::cancel(w.wl_surface(), seat, data, seq)}
             }
             FocusTarget::Popup(p) => {TouchTarget::cancel(p.wl_surface(), seat, data, seq)}
+            FocusTarget::LayerSurface(l) => {TouchTarget::cancel(l.wl_surface(), seat, data, seq)}
         }
     }
 
@@ -311,6 +347,7 @@ impl TouchTarget<State> for FocusTarget{
                 WindowSurface::Wayland(w) => {TouchTarget::shape(w.wl_surface(), seat, data, event, seq)}
             }
             FocusTarget::Popup(p) => {TouchTarget::shape(p.wl_surface(), seat, data, event, seq)}
+            FocusTarget::LayerSurface(l) => {TouchTarget::shape(l.wl_surface(), seat, data, event, seq)}
         }
     }
 
@@ -320,6 +357,7 @@ impl TouchTarget<State> for FocusTarget{
                 WindowSurface::Wayland(w) => {TouchTarget::orientation(w.wl_surface(), seat, data, event, seq)}
             }
             FocusTarget::Popup(p) => {TouchTarget::orientation(p.wl_surface(), seat, data, event, seq)}
+            FocusTarget::LayerSurface(l) => {TouchTarget::orientation(l.wl_surface(), seat, data, event, seq)}
         }
     }
 }
@@ -1,4 +1,4 @@
-use super::{focus::FocusTarget, State};
+use super::{focus::FocusTarget, State, PRIMARY_OUTPUT};
 use smithay::input::keyboard::Keysym;
 use smithay::reexports::input::event::pointer::PointerEventTrait;
 use smithay::wayland::seat::WaylandFocus;
@@ -10,6 +10,7 @@ use smithay::{
         },
         libinput::LibinputInputBackend,
     },
+    desktop::{layer_map_for_output, WindowSurfaceType},
     input::{
         keyboard::{keysyms, FilterResult},
         pointer::{AxisFrame, ButtonEvent, MotionEvent, RelativeMotionEvent},
@@ -18,8 +19,11 @@ use smithay::{
         input::LibinputInterface,
         rustix::fs::{open, Mode, OFlags},
     },
-    utils::{Logical, Point, Serial, SERIAL_COUNTER},
-    wayland::pointer_constraints::{with_pointer_constraint, PointerConstraint},
+    utils::{Logical, Physical, Point, Serial, Size, SERIAL_COUNTER},
+    wayland::{
+        pointer_constraints::{with_pointer_constraint, PointerConstraint},
+        shell::wlr_layer::{KeyboardInteractivity, Layer},
+    },
 };
 use std::{os::unix::io::OwnedFd, path::Path, time::Instant};
 
@@ -103,12 +107,11 @@ impl State {
     ) {
         self.last_pointer_movement = Instant::now();
         let serial = SERIAL_COUNTER.next_serial();
+        let delta = self.frame_delta_to_logical(delta);
+        let delta_unaccelerated = self.frame_delta_to_logical(delta_unaccelerated);
 
         let pointer = self.seat.get_pointer().unwrap();
-        let under = self
-            .space
-            .element_under(self.pointer_location)
-            .map(|(w, pos)| (w.clone().into(), pos));
+        let under = self.surface_under(self.pointer_location);
 
         /* Check if the pointer is locked or confined (pointer constraints protocol) */
         let mut pointer_locked = false;
@@ -146,9 +149,8 @@ impl State {
         self.pointer_location += delta;
         self.pointer_location = self.clamp_coords(self.pointer_location);
         let new_under = self
-            .space
-            .element_under(self.pointer_location)
-            .map(|(w, pos)| (w.clone().into(), pos.to_f64()));
+            .surface_under(self.pointer_location)
+            .map(|(target, pos)| (target, pos.to_f64()));
 
         // If pointer is locked, only emit relative motion
         if !pointer_locked {
@@ -209,9 +211,8 @@ impl State {
 
         let pointer = self.seat.get_pointer().unwrap();
         let under = self
-            .space
-            .element_under(self.pointer_location)
-            .map(|(w, pos)| (w.clone().into(), pos.to_f64().clone()));
+            .surface_under(self.pointer_location)
+            .map(|(target, pos)| (target, pos.to_f64()));
         pointer.motion(
             self,
             under.clone(),
@@ -295,19 +296,16 @@ impl State {
                 self.pointer_motion(event.time_usec(), event.delta(), event.delta_unaccel());
             }
             InputEvent::PointerMotionAbsolute { event } => {
-                if let Some(output) = self.output.as_ref() {
-                    let output_size = output
-                        .current_mode()
-                        .unwrap()
-                        .size
-                        .to_f64()
-                        .to_logical(output.current_scale().fractional_scale())
-                        .to_i32_round();
-
-                    let new_x = event.absolute_x_transformed(output_size.w);
-                    let new_y = event.absolute_y_transformed(output_size.h);
-
-                    self.pointer_motion_absolute(event.time_usec(), (new_x, new_y).into());
+                if let Some(frame_size) = self.frame_size(PRIMARY_OUTPUT) {
+                    // input devices cover the whole video frame of the primary output
+                    let position = (
+                        event.absolute_x_transformed(frame_size.w),
+                        event.absolute_y_transformed(frame_size.h),
+                    )
+                        .into();
+                    if let Some(position) = self.frame_to_logical(PRIMARY_OUTPUT, position) {
+                        self.pointer_motion_absolute(event.time_usec(), position);
+                    }
                 }
             }
             InputEvent::PointerButton { event, .. } => {
@@ -339,15 +337,58 @@ impl State {
         }
     }
 
-    fn clamp_coords(&self, pos: Point<f64, Logical>) -> Point<f64, Logical> {
-        if let Some(output) = self.output.as_ref() {
-            if let Some(mode) = output.current_mode() {
-                return (
-                    pos.x.max(0.0).min((mode.size.w - 2) as f64),
-                    pos.y.max(0.0).min((mode.size.h - 2) as f64),
-                )
-                    .into();
-            }
+    /// Size of the video frames of an output.
+    fn frame_size(&self, id: u32) -> Option<Size<i32, Physical>> {
+        let video_info = &self.virtual_output(id)?.video_info;
+        Some((video_info.width() as i32, video_info.height() as i32).into())
+    }
+
+    /// Maps a position in pixels of an output's video frames into the (scaled, rotated and cropped) desktop,
+    /// `None` if there is no such output.
+    pub fn frame_to_logical(&self, id: u32, position: Point<f64, Physical>) -> Option<Point<f64, Logical>> {
+        let output = &self.virtual_output(id)?.output;
+        let frame_size = self.frame_size(id)?;
+        let output_geo = self.space.output_geometry(output)?;
+
+        let position = output
+            .current_transform()
+            .invert()
+            .transform_point_in(position, &frame_size.to_f64());
+        let position = match self.frame_view(id) {
+            Some(view) => view.frame_to_output(position),
+            None => position,
+        };
+        // outputs are placed next to each other on the desktop
+        Some(position.to_logical(output.current_scale().fractional_scale()) + output_geo.loc.to_f64())
+    }
+
+    /// Rotates, flips and scales relative motion like the desktop inside the video frames,
+    /// turning frame pixels into logical ones.
+    fn frame_delta_to_logical(&self, delta: Point<f64, Logical>) -> Point<f64, Logical> {
+        let zoom = self.frame_view(PRIMARY_OUTPUT).map_or(1.0, |view| view.zoom);
+        match self.output.as_ref() {
+            // in an empty area, the transformation only rotates and flips
+            Some(output) => output
+                .current_transform()
+                .invert()
+                .transform_point_in(delta, &(0.0, 0.0).into())
+                .downscale(zoom * output.current_scale().fractional_scale()),
+            None => delta,
+        }
+    }
+
+    pub fn clamp_coords(&self, pos: Point<f64, Logical>) -> Point<f64, Logical> {
+        let desktop = self
+            .space
+            .outputs()
+            .filter_map(|output| self.space.output_geometry(output))
+            .reduce(|desktop, output_geo| desktop.merge(output_geo));
+        if let Some(desktop) = desktop {
+            return (
+                pos.x.max(desktop.loc.x as f64).min((desktop.loc.x + desktop.size.w - 2) as f64),
+                pos.y.max(desktop.loc.y as f64).min((desktop.loc.y + desktop.size.h - 2) as f64),
+            )
+                .into();
         }
         pos
     }
@@ -365,15 +406,58 @@ impl State {
         // see here for a discussion about that issue:
         // https://gitlab.freedesktop.org/wayland/wayland/-/issues/294
         if !pointer.is_grabbed() && !keyboard.is_grabbed() {
-            if let Some((window, _)) = self
-                .space
-                .element_under(self.pointer_location)
-                .map(|(w, p)| (w.clone(), p))
-            {
-                self.space.raise_element(&window, true);
-                keyboard.set_focus(self, Some(FocusTarget::from(window)), serial);
+            // layer surfaces with exclusive keyboard interactivity don't give up their focus
+            if let Some(FocusTarget::LayerSurface(layer)) = keyboard.current_focus() {
+                if layer.cached_state().keyboard_interactivity == KeyboardInteractivity::Exclusive {
+                    return;
+                }
+            }
+
+            if let Some((target, _)) = self.surface_under(self.pointer_location) {
+                match &target {
+                    FocusTarget::Wayland(window) => self.space.raise_element(window, true),
+                    FocusTarget::LayerSurface(layer) if !layer.can_receive_keyboard_focus() => return,
+                    _ => {}
+                }
+                keyboard.set_focus(self, Some(target), serial);
                 return;
             }
         }
     }
+
+    /// Finds the surface under the given position, taking layer surfaces into account.
+    pub fn surface_under(&self, pos: Point<f64, Logical>) -> Option<(FocusTarget, Point<i32, Logical>)> {
+        let Some(output) = self.space.output_under(pos).next().or(self.output.as_ref()) else {
+            return self
+                .space
+                .element_under(pos)
+                .map(|(w, loc)| (w.clone().into(), loc));
+        };
+        let output_geo = self.space.output_geometry(output)?;
+        let layers = layer_map_for_output(output);
+
+        let layer_under = |layer: Layer| {
+            let layer_pos = pos - output_geo.loc.to_f64();
+            layers.layer_under(layer, layer_pos).and_then(|layer| {
+                let layer_loc = layers.layer_geometry(layer).unwrap().loc;
+                let (surface, surface_loc) =
+                    layer.surface_under(layer_pos - layer_loc.to_f64(), WindowSurfaceType::ALL)?;
+                // popups of layer surfaces get their own input, everything else goes to the layer
+                Some(match self.popups.find_popup(&surface) {
+                    Some(popup) => (popup.into(), output_geo.loc + layer_loc + surface_loc),
+                    None => (layer.clone().into(), output_geo.loc + layer_loc),
+                })
+            })
+        };
+
+        layer_under(Layer::Overlay)
+            .or_else(|| layer_under(Layer::Top))
+            .or_else(|| {
+                self.space
+                    .element_under(pos)
+                    .map(|(w, loc)| (w.clone().into(), loc))
+            })
+            .or_else(|| layer_under(Layer::Bottom))
+            .or_else(|| layer_under(Layer::Background))
+    }
 }
@@ -1,23 +1,26 @@
 use std::{
+    cell::Cell,
     collections::{HashMap, HashSet},
     ffi::CString,
+    process::Child,
+    rc::Rc,
     sync::{mpsc::Sender, Arc, Mutex, Weak},
     time::{Duration, Instant},
 };
 use super::Command;
-use gst_video::VideoInfo;
 use once_cell::sync::Lazy;
 use smithay::{
     backend::{
-        allocator::{Fourcc, dmabuf::Dmabuf},
-        drm::{DrmNode, NodeType},
+        allocator::Fourcc,
+        drm::{CreateDrmNodeError, DrmNode, NodeType},
         egl::{EGLContext, EGLDevice, EGLDisplay},
         libinput::LibinputInputBackend,
         renderer::{
             element::memory::{MemoryRenderBuffer, MemoryBuffer},
-            damage::{OutputDamageTracker, Error as DTRError},
-            gles::{GlesRenderbuffer, GlesRenderer},
-            Bind, Offscreen,
+            damage::OutputDamageTracker,
+            gles::GlesRenderer,
+            pixman::PixmanRenderer,
+            utils::Buffer,
         },
     },
     desktop::{
@@ -26,35 +29,42 @@ use smithay::{
             surface_primary_scanout_output, update_surface_primary_scanout_output,
             OutputPresentationFeedback,
         },
-        PopupManager, Space, Window,
+        layer_map_for_output, LayerSurface, PopupManager, Space, Window,
     },
     input::{keyboard::XkbConfig, pointer::CursorImageStatus, Seat, SeatState},
-    output::{Mode as OutputMode, Output, PhysicalProperties, Subpixel},
+    output::{Output, Scale},
     reexports::{
         calloop::{
+            self,
             channel::{Channel, Event},
             generic::Generic,
+            ping::{make_ping, Ping},
             timer::{TimeoutAction, Timer},
             EventLoop, Interest, LoopHandle, Mode, PostAction,
         },
         input::Libinput,
         wayland_protocols::wp::presentation_time::server::wp_presentation_feedback,
         wayland_server::{
-            backend::{GlobalId, ClientData, ClientId, DisconnectReason},
+            backend::{Credentials, GlobalId},
+            protocol::wl_surface::WlSurface,
             Display, DisplayHandle,
         },
     },
-    utils::{Clock, Logical, Monotonic, Physical, Point, Rectangle, Size, Transform},
+    utils::{Clock, Logical, Monotonic, Point, Rectangle, Transform},
     wayland::{
-        compositor::{with_states, CompositorState, CompositorClientState},
+        compositor::{with_states, CompositorState, SurfaceData},
         dmabuf::{DmabufGlobal, DmabufState},
+        fractional_scale::{with_fractional_scale, FractionalScaleManagerState},
         output::OutputManagerState,
         presentation::PresentationState,
-        shell::xdg::{XdgShellState, XdgToplevelSurfaceData, SurfaceCachedState},
-        shm::ShmState,
-        socket::ListeningSocketSource,
+        shell::{
+            wlr_layer::WlrLayerShellState,
+            xdg::{XdgShellState, XdgToplevelSurfaceData, SurfaceCachedState},
+        },
+        shm::{fourcc_to_shm_format, ShmState},
         viewporter::ViewporterState,
         relative_pointer::RelativePointerManagerState,
+        security_context::SecurityContextState,
         pointer_constraints::PointerConstraintsState,
         selection::data_device::DataDeviceState,
     },
@@ -62,45 +72,84 @@ use smithay::{
 use smithay::backend::input::AxisSource;
 use tracing::debug;
 
+mod background;
+mod capture;
+mod clients;
 mod focus;
 mod input;
+mod outputs;
+mod overlays;
+mod passthrough;
 mod rendering;
+mod view;
+mod windows;
 
+pub use self::background::*;
+pub use self::capture::*;
+pub use self::clients::*;
 pub use self::focus::*;
 pub use self::input::*;
+pub use self::outputs::*;
+pub use self::overlays::*;
 pub use self::rendering::*;
-use crate::{utils::RenderTarget, wayland::protocols::wl_drm::create_drm_global};
+pub use self::view::*;
+pub use self::windows::*;
+use crate::{
+    utils::{Listener, RenderTarget},
+    wayland::protocols::{
+        color_management::ColorManagementState,
+        output_management::{HeadInfo, OutputManagementState},
+        wl_drm::create_drm_global_with_filter,
+    },
+    ClientLimits, DisplayError, DisplayEvent, DisplayMode, EventCallback, FrameCrop, FrameError,
+    GlobalFilter, OutputInfo, WallpaperMode,
+};
 
-static EGL_DISPLAYS: Lazy<Mutex<HashMap<Option<DrmNode>, Weak<EGLDisplay>>>> =
-    Lazy::new(|| Mutex::new(HashMap::new()));
+/// How often started applications are checked for having exited.
+const CHILD_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
-#[derive(Debug, Default)]
-pub struct ClientState {
-    pub compositor_state: CompositorClientState,
-}
+/// How long the wayland socket isn't polled after accepting a client failed.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
 
-impl ClientData for ClientState {
-    fn initialized(&self, _client_id: ClientId) {}
-    fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
-}
+static EGL_DISPLAYS: Lazy<Mutex<HashMap<Option<DrmNode>, Weak<EGLDisplay>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
 
 #[allow(dead_code)]
 pub(crate) struct State {
-    handle: LoopHandle<'static, State>,
+    pub handle: LoopHandle<'static, State>,
     should_quit: bool,
     clock: Clock<Monotonic>,
+    /// Whether a timer is checking on started applications.
+    reaping_children: bool,
 
     // render
-    dtr: Option<OutputDamageTracker>,
-    renderbuffer: Option<GlesRenderbuffer>,
-    pub renderer: GlesRenderer,
-    egl_display_ref: Arc<EGLDisplay>,
+    pub renderer: RenderBackend,
+    pub render_target: RenderTarget,
+    software_fallback: bool,
+    /// Set once the renderer couldn't be recreated after a context loss, frames fail from then on.
+    renderer_lost: bool,
     dmabuf_global: Option<(DmabufGlobal, GlobalId)>,
-    last_render: Option<Instant>,
 
     // management
+    /// The primary output, also part of `outputs`.
     pub output: Option<Output>,
-    pub video_info: Option<VideoInfo>,
+    pub outputs: Vec<VirtualOutput>,
+    output_infos: HashMap<u32, OutputInfo>,
+    next_output_id: u32,
+    output_scale: f64,
+    output_transform: Transform,
+    render_size: Option<(i32, i32)>,
+    frame_crop: FrameCrop,
+    letterbox: bool,
+    background_color: [f32; 4],
+    wallpaper: Option<Wallpaper>,
+    wallpaper_mode: WallpaperMode,
+    trusted_globals: GlobalFilter,
+    restricted_globals: GlobalFilter,
+    overlays: Vec<OverlayBuffer>,
+    window_captures: HashMap<u64, WindowCapture>,
+    /// Modes clients may request, per output.
+    output_modes: HashMap<u32, Vec<DisplayMode>>,
     pub seat: Seat<Self>,
     pub space: Space<Window>,
     pub popups: PopupManager,
@@ -110,13 +159,27 @@ pub(crate) struct State {
     pub cursor_state: CursorImageStatus,
     surpressed_keys: HashSet<u32>,
     pub pending_windows: Vec<Window>,
+    pub pending_layers: Vec<LayerSurface>,
+    /// Client buffers handed downstream by passthrough, released once downstream dropped them.
+    leased_buffers: Vec<(Weak<()>, Buffer)>,
+    buffer_release: Ping,
     input_context: Libinput,
+    event_callback: Option<EventCallback>,
+    clients: HashMap<u64, Option<Credentials>>,
+    disconnected_clients: DisconnectedClients,
+    eos_on_last_client: bool,
+    eos: bool,
+    limits: ClientLimits,
+    /// Live surfaces of every client, for the per-client quotas.
+    client_surfaces: HashMap<u64, Vec<WlSurface>>,
 
     // wayland state
     pub dh: DisplayHandle,
     pub compositor_state: CompositorState,
     pub data_device_state: DataDeviceState,
     pub dmabuf_state: DmabufState,
+    pub layer_shell_state: WlrLayerShellState,
+    security_context_state: SecurityContextState,
     output_state: OutputManagerState,
     presentation_state: PresentationState,
     relative_ptr_state: RelativePointerManagerState,
@@ -125,34 +188,300 @@ pub(crate) struct State {
     pub shell_state: XdgShellState,
     pub shm_state: ShmState,
     viewporter_state: ViewporterState,
+    fractional_scale_state: FractionalScaleManagerState,
+    pub color_management_state: ColorManagementState,
+    pub output_management_state: OutputManagementState,
     cursor_event_count: i32,
 }
 
-pub fn get_egl_device_for_node(drm_node: &DrmNode) -> EGLDevice {
+pub fn get_egl_device_for_node(drm_node: &DrmNode) -> Result<EGLDevice, DisplayError> {
     let drm_node = drm_node
         .node_with_type(NodeType::Render)
         .and_then(Result::ok)
         .unwrap_or(drm_node.clone());
-    EGLDevice::enumerate()
-        .expect("Failed to enumerate EGLDevices")
-        .find(|d| d.try_get_render_node().unwrap_or_default() == Some(drm_node))
-        .expect("Unable to find EGLDevice for drm-node")
+    EGLDevice::enumerate()?
+        .find(|device| match device.try_get_render_node() {
+            Ok(node) => node == Some(drm_node),
+            Err(err) => {
+                tracing::warn!(?err, "Failed to query the render node of an EGL device, skipping it.");
+                false
+            }
+        })
+        .ok_or(DisplayError::NoEglDevice)
+}
+
+impl State {
+    /// Applies a changed output scale or transform, resizing toplevels and layers to the new logical size.
+    fn update_output_state(&mut self) {
+        let scale = self.output_scale;
+        for virtual_output in &mut self.outputs {
+            let output = &virtual_output.output;
+            output.change_current_state(
+                None,
+                Some(self.output_transform),
+                Some(output_scale(scale)),
+                None,
+            );
+            layer_map_for_output(output).arrange();
+            // everything has to be redrawn with the new scale and transform
+            virtual_output.dtr = OutputDamageTracker::from_output(output);
+            virtual_output.view_dtr = None;
+            for layer in layer_map_for_output(output).layers() {
+                layer.with_surfaces(|_, states| set_preferred_scale(states, scale));
+            }
+        }
+        self.arrange_outputs();
+        self.pointer_location = self.clamp_coords(self.pointer_location);
+        for window in self.space.elements() {
+            self.send_preferred_scale(window);
+        }
+        self.configure_toplevels();
+        self.update_output_modes();
+    }
+
+    /// Advertises the allowed modes of every output, plus the current one, through `wl_output`
+    /// and `wlr-output-management`. Also keeps the position of the outputs up to date.
+    fn update_output_modes(&mut self) {
+        for virtual_output in &self.outputs {
+            let output = &virtual_output.output;
+            let Some(current) = output.current_mode() else {
+                continue;
+            };
+            let allowed = self
+                .output_modes
+                .get(&virtual_output.id)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+
+            for mode in output.modes() {
+                if mode != current && !allowed.contains(&mode.into()) {
+                    output.delete_mode(mode);
+                }
+            }
+            for mode in allowed {
+                output.add_mode((*mode).into());
+            }
+
+            let mut modes = allowed.to_vec();
+            if !modes.contains(&current.into()) {
+                modes.insert(0, current.into());
+            }
+            let physical = output.physical_properties();
+            let position = self
+                .space
+                .output_geometry(output)
+                .map_or((0, 0), |geometry| (geometry.loc.x, geometry.loc.y));
+            let head = HeadInfo {
+                name: output.name(),
+                description: output.description(),
+                make: physical.make,
+                model: physical.model,
+                serial: self
+                    .output_infos
+                    .get(&virtual_output.id)
+                    .and_then(|info| info.serial.clone()),
+                physical_size: (physical.size.w, physical.size.h),
+                modes,
+                current_mode: current.into(),
+                preferred_mode: output.preferred_mode().map(Into::into),
+                position,
+                transform: output.current_transform(),
+                scale: output.current_scale().fractional_scale(),
+            };
+            self.output_management_state.update::<State>(&self.dh, head);
+        }
+    }
+
+    pub fn current_mode(&self, id: u32) -> Option<DisplayMode> {
+        self.virtual_output(id)?.output.current_mode().map(Into::into)
+    }
+
+    /// Resolves a mode requested by a client to the current or an allowed one of an output,
+    /// a `refresh` of 0 matches any.
+    pub fn allowed_mode(&self, id: u32, mode: DisplayMode) -> Option<DisplayMode> {
+        self.current_mode(id)
+            .into_iter()
+            .chain(self.output_modes.get(&id).into_iter().flatten().copied())
+            .find(|allowed| {
+                allowed.width == mode.width
+                    && allowed.height == mode.height
+                    && (mode.refresh == 0 || allowed.refresh == mode.refresh)
+            })
+    }
+
+    /// Tells every surface of a window the (fractional) scale it should render at.
+    pub fn send_preferred_scale(&self, window: &Window) {
+        window.with_surfaces(|_, states| set_preferred_scale(states, self.output_scale));
+    }
+
+    pub fn output_scale(&self) -> f64 {
+        self.output_scale
+    }
+
+    /// Sends a new size to every mapped toplevel, fitting them into the area of their output
+    /// not reserved by layer surfaces.
+    pub fn configure_toplevels(&mut self) {
+        for window in self.space.elements() {
+            let Some(output) = self.window_output(window) else {
+                continue;
+            };
+            let zone = self.output_zone(output);
+            let toplevel = window.toplevel().unwrap();
+            let max_size = Rectangle::from_loc_and_size(
+                (0, 0),
+                with_states(toplevel.wl_surface(), |states| {
+                    states
+                        .data_map
+                        .get::<XdgToplevelSurfaceData>()
+                        .map(|_attrs| states.cached_state.get::<SurfaceCachedState>().current().max_size)
+                })
+                    .unwrap_or(zone.size),
+            );
+
+            let new_size = max_size
+                .intersection(Rectangle::from_loc_and_size((0, 0), zone.size))
+                .map(|rect| rect.size);
+            toplevel.with_pending_state(|state| state.size = new_size);
+            toplevel.send_configure();
+        }
+
+        // windows keep their position, unless they ended up off their output (e.g. after it was removed)
+        let windows = self.space.elements().cloned().collect::<Vec<_>>();
+        for window in windows {
+            let Some(zone) = self.window_output(&window).map(|output| self.output_zone(output)) else {
+                continue;
+            };
+            let on_output = self
+                .space
+                .element_geometry(&window)
+                .is_some_and(|geometry| geometry.overlaps(zone));
+            if !on_output {
+                self.center_window(&window, zone);
+            }
+        }
+    }
+
+    /// Centers a window in a zone, at the size it was last configured with.
+    pub fn center_window(&mut self, window: &Window, zone: Rectangle<i32, Logical>) {
+        let size = window.toplevel().unwrap().with_pending_state(|state| state.size).unwrap_or_default();
+        let loc = (
+            zone.loc.x + (zone.size.w / 2) - (size.w / 2),
+            zone.loc.y + (zone.size.h / 2) - (size.h / 2),
+        );
+        self.space.map_element(window.clone(), loc, false);
+    }
 }
 
+pub fn set_preferred_scale(states: &SurfaceData, scale: f64) {
+    with_fractional_scale(states, |fractional_scale| {
+        fractional_scale.set_preferred_scale(scale);
+    });
+}
+
+/// Integer scales are advertised as they are, fractional ones get rounded up for `wl_output`.
+fn output_scale(scale: f64) -> Scale {
+    if scale.fract() == 0.0 {
+        Scale::Integer(scale as i32)
+    } else {
+        Scale::Fractional(scale)
+    }
+}
+
+fn init_renderer(render_target: &RenderTarget) -> Result<RenderBackend, DisplayError> {
+    if let RenderTarget::Pixman = render_target {
+        return Ok(RenderBackend::Pixman(PixmanRenderer::new()?));
+    }
+    let render_node: Option<DrmNode> = render_target.clone().into();
+
+    let mut displays = EGL_DISPLAYS.lock().unwrap();
+    let maybe_display = displays
+        .get(&render_node)
+        .and_then(|weak_display| weak_display.upgrade());
+
+    let egl = match maybe_display {
+        Some(display) => display,
+        None => {
+            let device = match render_node.as_ref() {
+                Some(render_node) => get_egl_device_for_node(render_node)?,
+                None => EGLDevice::enumerate()?
+                    .find(|device| {
+                        device
+                            .extensions()
+                            .iter()
+                            .any(|e| e == "EGL_MESA_device_software")
+                    })
+                    .ok_or(DisplayError::NoEglDevice)?,
+            };
+            let egl = unsafe { EGLDisplay::new(device)? };
+            let display = Arc::new(egl);
+            displays.insert(render_node, Arc::downgrade(&display));
+            display
+        }
+    };
+    let context = EGLContext::new(&egl)?;
+    let renderer = unsafe { GlesRenderer::new(context) }?;
+    Ok(RenderBackend::Gles {
+        renderer,
+        egl_display_ref: egl,
+    })
+}
+
+/// Creates the zwp_linux_dmabuf_v1 and wl_drm globals for a hardware render target.
+fn create_dmabuf_globals(
+    dh: &DisplayHandle,
+    dmabuf_state: &mut DmabufState,
+    renderer: &RenderBackend,
+    render_target: &RenderTarget,
+) -> Result<Option<(DmabufGlobal, GlobalId)>, DisplayError> {
+    let RenderTarget::Hardware(node) = render_target else {
+        return Ok(None);
+    };
+    let formats = renderer.dmabuf_formats();
+    let dev_path = node
+        .dev_path()
+        .ok_or(DisplayError::RenderNode(CreateDrmNodeError::NotDrmNode))?;
+
+    // dma buffer
+    let dmabuf_global = dmabuf_state.create_global_with_filter::<State, _>(dh, formats.clone(), |client| {
+        can_view_global(client, "zwp_linux_dmabuf_v1")
+    });
+    // wl_drm (mesa protocol, so we don't need EGL_WL_bind_display)
+    let wl_drm_global = create_drm_global_with_filter::<State, _>(
+        dh,
+        dev_path,
+        formats.clone(),
+        &dmabuf_global,
+        |client| can_view_global(client, "wl_drm"),
+    );
+
+    Ok(Some((dmabuf_global, wl_drm_global)))
+}
+
+/// Sets up the compositor, the returned event loop is driven by [`run`].
 pub(crate) fn init(
     command_src: Channel<Command>,
     render: impl Into<RenderTarget>,
+    listener: Listener,
     devices_tx: Sender<Vec<CString>>,
     envs_tx: Sender<Vec<CString>>,
-) {
+) -> Result<(EventLoop<'static, State>, State), DisplayError> {
     let clock = Clock::new();
-    let display = Display::<State>::new().unwrap();
+    let display = Display::<State>::new()?;
     let dh = display.handle();
 
     // init state
     let compositor_state = CompositorState::new::<State>(&dh);
     let data_device_state = DataDeviceState::new::<State>(&dh);
     let mut dmabuf_state = DmabufState::new();
+    let layer_shell_state =
+        WlrLayerShellState::new_with_filter::<State, _>(&dh, |client| can_view_global(client, "zwlr_layer_shell_v1"));
+    let security_context_state = SecurityContextState::new::<State, _>(&dh, |client| {
+        // nested security contexts are not allowed
+        can_view_global(client, "wp_security_context_manager_v1")
+            && client
+                .get_data::<ClientState>()
+                .map_or(false, |data| data.security_context.is_none())
+    });
     let output_state = OutputManagerState::new_with_xdg_output::<State>(&dh);
     let presentation_state = PresentationState::new::<State>(&dh, clock.id() as _);
     let relative_ptr_state = RelativePointerManagerState::new::<State>(&dh);
@@ -160,65 +489,53 @@ pub(crate) fn init(
     let mut seat_state = SeatState::new();
     let shell_state = XdgShellState::new::<State>(&dh);
     let viewporter_state = ViewporterState::new::<State>(&dh);
+    let fractional_scale_state = FractionalScaleManagerState::new::<State>(&dh);
+    let color_management_state =
+        ColorManagementState::new::<State, _>(&dh, |client| can_view_global(client, "wp_color_manager_v1"));
+    let output_management_state =
+        OutputManagementState::new::<State, _>(&dh, |client| can_view_global(client, "zwlr_output_manager_v1"));
 
-    let render_target = render.into();
-    let render_node: Option<DrmNode> = render_target.clone().into();
-
-    // init render backend
-    let (egl_display_ref, context) = {
-        let mut displays = EGL_DISPLAYS.lock().unwrap();
-        let maybe_display = displays
-            .get(&render_node)
-            .and_then(|weak_display| weak_display.upgrade());
-
-        let egl = match maybe_display {
-            Some(display) => display,
-            None => {
-                let device = match render_node.as_ref() {
-                    Some(render_node) => get_egl_device_for_node(render_node),
-                    None => EGLDevice::enumerate()
-                        .expect("Failed to enumerate EGLDevices")
-                        .find(|device| {
-                            device
-                                .extensions()
-                                .iter()
-                                .any(|e| e == "EGL_MESA_device_software")
-                        })
-                        .expect("Failed to find software device"),
-                };
-                let egl = unsafe { EGLDisplay::new(device).expect("Failed to create EGLDisplay") };
-                let display = Arc::new(egl);
-                displays.insert(render_node, Arc::downgrade(&display));
-                display
+    // init render backend, trying every candidate of an `auto` target until one works
+    let target = render.into();
+    let mut tried = Vec::new();
+    let mut error = DisplayError::NoEglDevice;
+    let mut initialized = None;
+    for candidate in target.candidates() {
+        match init_renderer(&candidate) {
+            Ok(renderer) => {
+                initialized = Some((candidate, renderer));
+                break;
             }
-        };
-        let context = EGLContext::new(&egl).expect("Failed to initialize EGL context");
-        (egl, context)
+            Err(err) => {
+                tracing::warn!(?err, render_target = %candidate, "Failed to initialize renderer.");
+                tried.push(candidate.to_string());
+                error = err;
+            }
+        }
+    }
+    let (render_target, renderer) = match initialized {
+        Some(initialized) => initialized,
+        None if matches!(target, RenderTarget::Auto(_)) => {
+            return Err(DisplayError::NoRenderTarget {
+                tried,
+                error: Box::new(error),
+            })
+        }
+        None => return Err(error),
     };
-    let renderer = unsafe { GlesRenderer::new(context) }.expect("Failed to initialize renderer");
+    tracing::info!(%render_target, "Initialized renderer.");
     let _ = devices_tx.send(render_target.as_devices());
 
-    let shm_state = ShmState::new::<State>(&dh, vec![]);
-    let dmabuf_global = if let RenderTarget::Hardware(node) = render_target {
-        let formats = Bind::<Dmabuf>::supported_formats(&renderer)
-            .expect("Failed to query formats")
-            .into_iter()
-            .collect::<Vec<_>>();
-
-        // dma buffer
-        let dmabuf_global = dmabuf_state.create_global::<State>(&dh, formats.clone());
-        // wl_drm (mesa protocol, so we don't need EGL_WL_bind_display)
-        let wl_drm_global = create_drm_global::<State>(
-            &dh,
-            node.dev_path().expect("Failed to determine DrmNode path?"),
-            formats.clone(),
-            &dmabuf_global,
-        );
-
-        Some((dmabuf_global, wl_drm_global))
-    } else {
-        None
-    };
+    // argb8888 and xrgb8888 are always advertised
+    let shm_formats = renderer
+        .shm_formats()
+        .into_iter()
+        .filter(|format| !matches!(format, Fourcc::Argb8888 | Fourcc::Xrgb8888))
+        .filter_map(fourcc_to_shm_format)
+        .collect::<Vec<_>>();
+    tracing::debug!(?shm_formats, "Advertising shm formats.");
+    let shm_state = ShmState::new::<State>(&dh, shm_formats);
+    let dmabuf_global = create_dmabuf_globals(&dh, &mut dmabuf_state, &renderer, &render_target)?;
 
     let cursor_element =
         MemoryRenderBuffer::from_memory(MemoryBuffer::from_slice(
@@ -234,30 +551,49 @@ pub(crate) fn init(
     let space = Space::default();
 
     let mut seat = seat_state.new_wl_seat(&dh, "seat-0");
-    seat.add_keyboard(XkbConfig::default(), 200, 25)
-        .expect("Failed to add keyboard to seat");
+    seat.add_keyboard(XkbConfig::default(), 200, 25)?;
     seat.add_pointer();
 
-    let mut event_loop =
-        EventLoop::<State>::try_new().expect("Unable to create event_loop");
+    let event_loop = EventLoop::<State>::try_new()?;
+    // downstream frees passthrough frames on its own threads, the buffers are released on ours
+    let (buffer_release, buffer_release_source) = make_ping().map_err(calloop::Error::IoError)?;
+    event_loop
+        .handle()
+        .insert_source(buffer_release_source, |_, _, state| state.release_buffers())
+        .map_err(|err| err.error)?;
 
-    let mut state = State {
+    let state = State {
         handle: event_loop.handle(),
         should_quit: false,
         clock,
+        reaping_children: false,
 
         renderer,
-        egl_display_ref,
-        dtr: None,
-        renderbuffer: None,
+        render_target,
+        software_fallback: false,
+        renderer_lost: false,
         dmabuf_global,
-        video_info: None,
-        last_render: None,
 
         space,
         popups: PopupManager::default(),
         seat,
         output: None,
+        outputs: Vec::new(),
+        output_infos: HashMap::from([(PRIMARY_OUTPUT, OutputInfo::default())]),
+        next_output_id: PRIMARY_OUTPUT + 1,
+        output_scale: 1.0,
+        output_transform: Transform::Normal,
+        render_size: None,
+        frame_crop: FrameCrop::None,
+        letterbox: false,
+        background_color: [0.0, 0.0, 0.0, 1.0],
+        wallpaper: None,
+        wallpaper_mode: WallpaperMode::default(),
+        trusted_globals: GlobalFilter::default(),
+        restricted_globals: GlobalFilter::restricted(),
+        overlays: Vec::new(),
+        window_captures: HashMap::new(),
+        output_modes: HashMap::new(),
         pointer_location: (0., 0.).into(),
         last_pointer_movement: Instant::now(),
         cursor_element,
@@ -265,12 +601,24 @@ pub(crate) fn init(
         cursor_event_count: 0,
         surpressed_keys: HashSet::new(),
         pending_windows: Vec::new(),
+        pending_layers: Vec::new(),
+        leased_buffers: Vec::new(),
+        buffer_release,
         input_context,
+        event_callback: None,
+        clients: HashMap::new(),
+        disconnected_clients: DisconnectedClients::default(),
+        eos_on_last_client: false,
+        limits: ClientLimits::default(),
+        client_surfaces: HashMap::new(),
+        eos: false,
 
         dh: display.handle(),
         compositor_state,
         data_device_state,
         dmabuf_state,
+        layer_shell_state,
+        security_context_state,
         output_state,
         presentation_state,
         relative_ptr_state,
@@ -279,6 +627,9 @@ pub(crate) fn init(
         shell_state,
         shm_state,
         viewporter_state,
+        fractional_scale_state,
+        color_management_state,
+        output_management_state,
     };
 
     // init event loop
@@ -287,86 +638,147 @@ pub(crate) fn init(
         .insert_source(libinput_backend, move |event, _, state| {
             state.process_input_event(event)
         })
-        .unwrap();
+        .map_err(|err| err.error)?;
 
     event_loop
         .handle()
         .insert_source(command_src, move |event, _, state| {
             match event {
-                Event::Msg(Command::VideoInfo(info)) => {
+                Event::Msg(Command::VideoInfo(id, info)) => {
                     debug!("Requested video format: {} .to_fourcc() = {}", info.format(), info.format().to_fourcc());
-                    let size: Size<i32, Physical> =
-                        (info.width() as i32, info.height() as i32).into();
-                    let framerate = info.fps();
-                    let duration = Duration::from_secs_f64(
-                        framerate.numer() as f64 / framerate.denom() as f64,
-                    );
-
-                    // init wayland objects
-                    let output = state.output.get_or_insert_with(|| {
-                        let output = Output::new(
-                            "HEADLESS-1".into(),
-                            PhysicalProperties {
-                                make: "Virtual".into(),
-                                model: "Wolf".into(),
-                                size: (0, 0).into(),
-                                subpixel: Subpixel::Unknown,
-                            },
-                        );
-                        output.create_global::<State>(&state.dh);
-                        output
-                    });
-                    let mode = OutputMode {
-                        size: size.into(),
-                        refresh: (duration.as_secs_f64() * 1000.0).round() as i32,
-                    };
-                    output.change_current_state(Some(mode), None, None, None);
-                    output.set_preferred(mode);
-                    let dtr = OutputDamageTracker::from_output(&output);
-
-                    state.space.map_output(&output, (0, 0));
-                    state.dtr = Some(dtr);
-                    state.pointer_location = (size.w as f64 / 2.0, size.h as f64 / 2.0).into();
-                    state.renderbuffer = Some(
-                        state
-                            .renderer
-                            .create_buffer(Fourcc::try_from(info.format().to_fourcc()).unwrap_or(Fourcc::Abgr8888),
-                                           (info.width() as i32, info.height() as i32).into())
-                            .expect("Failed to create renderbuffer"),
+                    state.set_output_video_info(id, info);
+                }
+                Event::Msg(Command::EventCallback(callback)) => {
+                    state.event_callback = Some(callback);
+                }
+                Event::Msg(Command::EosOnLastClient(enabled)) => {
+                    state.eos_on_last_client = enabled;
+                }
+                Event::Msg(Command::ClientLimits(limits)) => {
+                    state.limits = limits;
+                }
+                Event::Msg(Command::SoftwareFallback(enabled)) => {
+                    state.software_fallback = enabled;
+                }
+                Event::Msg(Command::OutputScale(scale)) => {
+                    state.output_scale = scale;
+                    state.update_output_state();
+                }
+                Event::Msg(Command::RenderSize(size)) => {
+                    state.render_size = size;
+                    if let Some(info) = state
+                        .virtual_output(PRIMARY_OUTPUT)
+                        .map(|output| output.video_info.clone())
+                    {
+                        state.set_output_video_info(PRIMARY_OUTPUT, info);
+                    }
+                }
+                Event::Msg(Command::FrameCrop(crop)) => {
+                    state.frame_crop = crop;
+                }
+                Event::Msg(Command::Letterbox(enabled)) => {
+                    state.letterbox = enabled;
+                }
+                Event::Msg(Command::BackgroundColor(color)) => {
+                    state.background_color = color;
+                }
+                Event::Msg(Command::Wallpaper(image)) => {
+                    state.set_wallpaper(image);
+                }
+                Event::Msg(Command::WallpaperMode(mode)) => {
+                    state.wallpaper_mode = mode;
+                }
+                Event::Msg(Command::GlobalFilter(restricted, filter)) => {
+                    if restricted {
+                        state.restricted_globals = filter;
+                    } else {
+                        state.trusted_globals = filter;
+                    }
+                }
+                Event::Msg(Command::AddOverlay(id, overlay)) => {
+                    state.add_overlay(id, overlay);
+                }
+                Event::Msg(Command::UpdateOverlay(id, overlay)) => {
+                    state.update_overlay(id, overlay);
+                }
+                Event::Msg(Command::RemoveOverlay(id)) => {
+                    state.remove_overlay(id);
+                }
+                Event::Msg(Command::OutputTransform(transform)) => {
+                    state.output_transform = transform.into();
+                    state.update_output_state();
+                }
+                Event::Msg(Command::OutputInfo(info)) => {
+                    if state.output.is_some() {
+                        tracing::warn!("Output already created, ignoring new output info.");
+                    } else {
+                        state.output_infos.insert(PRIMARY_OUTPUT, info);
+                    }
+                }
+                Event::Msg(Command::AddOutput(info, id_sender)) => {
+                    let _ = id_sender.send(state.add_output(info));
+                }
+                Event::Msg(Command::RemoveOutput(id)) => {
+                    state.remove_output(id);
+                }
+                Event::Msg(Command::MoveWindow(id, output_id)) => match state.find_window(id) {
+                    Some(window) => state.move_window_to_output(&window, output_id),
+                    None => tracing::warn!(id, "No such window."),
+                },
+                Event::Msg(Command::OutputModes(id, modes)) => {
+                    state.output_modes.insert(id, modes);
+                    state.update_output_modes();
+                }
+                Event::Msg(Command::ListWindows(windows_sender)) => {
+                    let _ = windows_sender.send(state.windows());
+                }
+                Event::Msg(Command::WindowAction(id, action)) => {
+                    state.window_action(id, action);
+                }
+                Event::Msg(Command::InsertClient(stream, restricted, globals, client_sender)) => {
+                    let _ = client_sender.send(state.insert_client(stream, restricted, globals, None));
+                }
+                Event::Msg(Command::ChildSpawned(children)) => {
+                    if state.reaping_children {
+                        return;
+                    }
+                    // a single timer checks on all applications, as long as any are running
+                    let res = state.handle.insert_source(
+                        Timer::from_duration(CHILD_POLL_INTERVAL),
+                        move |_, _, state| {
+                            if state.reap_children(&children) {
+                                TimeoutAction::ToDuration(CHILD_POLL_INTERVAL)
+                            } else {
+                                state.reaping_children = false;
+                                TimeoutAction::Drop
+                            }
+                        },
                     );
-                    state.video_info = Some(info);
-
-                    let new_size = size
-                        .to_f64()
-                        .to_logical(output.current_scale().fractional_scale())
-                        .to_i32_round();
-                    for window in state.space.elements() {
-                        let toplevel = window.toplevel().unwrap();
-                        let max_size = Rectangle::from_loc_and_size(
-                            (0, 0),
-                            with_states(toplevel.wl_surface(), |states| {
-                                states
-                                    .data_map
-                                    .get::<XdgToplevelSurfaceData>()
-                                    .map(|_attrs| states.cached_state.get::<SurfaceCachedState>().current().max_size)
-                            })
-                                .unwrap_or(new_size),
-                        );
-
-                        let new_size = max_size
-                            .intersection(Rectangle::from_loc_and_size((0, 0), new_size))
-                            .map(|rect| rect.size);
-                        toplevel.with_pending_state(|state| state.size = new_size);
-                        toplevel.send_configure();
+                    match res {
+                        Ok(_) => state.reaping_children = true,
+                        Err(err) => tracing::error!(?err, "Failed to watch applications."),
                     }
                 }
                 Event::Msg(Command::InputDevice(path)) => {
                     tracing::info!(path, "Adding input device.");
                     state.input_context.path_add_device(&path);
                 }
-                Event::Msg(Command::Buffer(buffer_sender, tracer)) => {
-                    let wait = if let Some(last_render) = state.last_render {
-                        let framerate = state.video_info.as_ref().unwrap().fps();
+                Event::Msg(Command::GlHandles(handles_sender)) => {
+                    let _ = handles_sender.send(state.renderer.gl_handles());
+                }
+                Event::Msg(Command::Buffer(id, target, buffer_sender, tracer)) => {
+                    if state.eos {
+                        let _ = buffer_sender.send(Err(FrameError::Eos));
+                        return;
+                    }
+                    let Some(virtual_output) = state.virtual_output(id) else {
+                        tracing::warn!(id, "No such output.");
+                        let _ = buffer_sender.send(Err(FrameError::Render));
+                        return;
+                    };
+
+                    let wait = if let Some(last_render) = virtual_output.last_render {
+                        let framerate = virtual_output.video_info.fps();
                         let duration = Duration::from_secs_f64(
                             framerate.denom() as f64 / framerate.numer() as f64,
                         );
@@ -385,17 +797,19 @@ pub(crate) fn init(
                             Some(ref tracer) => Some(tracer.trace("render")),
                             None => None
                         };
-                        if let Err(_) = match state.create_frame() {
+                        if let Err(_) = match state.render_frame(id, &target) {
                             Ok((buf, render_result)) => {
                                 render_result.sync.wait().expect("Error during render_result.sync"); // we need to wait before giving a hardware buffer to gstreamer or we might not be done writing to it
+                                state.update_hdr_metadata(id);
                                 let res = buffer_sender.send(Ok(buf));
                                 let rendered_states = &render_result.states;
                                 let rendered_damage = render_result.damage.is_some();
 
-                                if let Some(output) = state.output.as_ref() {
+                                if let Some(virtual_output) = state.virtual_output(id) {
+                                    let output = &virtual_output.output;
                                     let mut output_presentation_feedback =
                                         OutputPresentationFeedback::new(output);
-                                    for window in state.space.elements() {
+                                    for window in state.space.elements_for_output(output) {
                                         window.with_surfaces(|surface, states| {
                                             update_surface_primary_scanout_output(
                                                 surface,
@@ -409,7 +823,7 @@ pub(crate) fn init(
                                             output,
                                             state.clock.now(),
                                             Some(Duration::ZERO),
-                                            |_, _| Some(output.clone()),
+                                            surface_primary_scanout_output,
                                         );
                                         window.take_presentation_feedback(
                                             &mut output_presentation_feedback,
@@ -422,6 +836,33 @@ pub(crate) fn init(
                                             },
                                         );
                                     }
+                                    for layer in layer_map_for_output(output).layers() {
+                                        layer.with_surfaces(|surface, states| {
+                                            update_surface_primary_scanout_output(
+                                                surface,
+                                                output,
+                                                states,
+                                                rendered_states,
+                                                |next_output, _, _, _| next_output,
+                                            );
+                                        });
+                                        layer.send_frame(
+                                            output,
+                                            state.clock.now(),
+                                            Some(Duration::ZERO),
+                                            surface_primary_scanout_output,
+                                        );
+                                        layer.take_presentation_feedback(
+                                            &mut output_presentation_feedback,
+                                            surface_primary_scanout_output,
+                                            |surface, _| {
+                                                surface_presentation_feedback_flags_from_states(
+                                                    surface,
+                                                    rendered_states,
+                                                )
+                                            },
+                                        );
+                                    }
                                     if rendered_damage {
                                         output_presentation_feedback.presented(
                                             state.clock.now(),
@@ -436,6 +877,7 @@ pub(crate) fn init(
                                     if let CursorImageStatus::Surface(wl_surface) =
                                         &state.cursor_state
                                     {
+
                                         send_frames_surface_tree(
                                             wl_surface,
                                             output,
@@ -446,16 +888,14 @@ pub(crate) fn init(
                                     }
                                 }
 
-                                state.last_render = Some(now);
+                                if let Some(virtual_output) =
+                                    state.outputs.iter_mut().find(|output| output.id == id)
+                                {
+                                    virtual_output.last_render = Some(now);
+                                }
                                 res
                             }
-                            Err(err) => {
-                                tracing::error!(?err, "Rendering failed.");
-                                buffer_sender.send(Err(match err {
-                                    DTRError::OutputNoMode(_) => unreachable!(),
-                                    DTRError::Rendering(err) => err.into(),
-                                }))
-                            }
+                            Err(err) => buffer_sender.send(Err(err)),
                         } {
                             state.should_quit = true;
                         }
@@ -477,6 +917,30 @@ pub(crate) fn init(
                         None => render(state, Instant::now()),
                     };
                 }
+                Event::Msg(Command::WindowFrame(id, buffer_sender)) => {
+                    if state.eos {
+                        let _ = buffer_sender.send(Err(FrameError::Eos));
+                        return;
+                    }
+                    let render = move |state: &mut State, now: Instant| {
+                        let _ = buffer_sender.send(state.render_window_frame(id, now));
+                    };
+                    match state.window_frame_delay(id) {
+                        Some(duration) => {
+                            if let Err(err) = state.handle.insert_source(
+                                Timer::from_duration(duration),
+                                move |now, _, data| {
+                                    render(data, now);
+                                    TimeoutAction::Drop
+                                },
+                            ) {
+                                tracing::error!(?err, "Event loop error.");
+                                state.should_quit = true;
+                            };
+                        }
+                        None => render(state, Instant::now()),
+                    };
+                }
                 Event::Msg(Command::Quit) | Event::Closed => {
                     state.should_quit = true;
                 }
@@ -488,9 +952,12 @@ pub(crate) fn init(
                     let time: Duration = state.clock.now().into();
                     state.pointer_motion(time.as_nanos() as u64, position, position);
                 }
-                Event::Msg(Command::PointerMotionAbsolute(position)) => {
+                Event::Msg(Command::PointerMotionAbsolute(output, position)) => {
                     let time: Duration = state.clock.now().into();
-                    state.pointer_motion_absolute(time.as_nanos() as u64, position);
+                    match state.frame_to_logical(output, position) {
+                        Some(position) => state.pointer_motion_absolute(time.as_nanos() as u64, position),
+                        None => tracing::warn!(output, "No such output."),
+                    }
                 }
                 Event::Msg(Command::PointerButton(btn_code, btn_state)) => {
                     let time: Duration = state.clock.now().into();
@@ -502,22 +969,69 @@ pub(crate) fn init(
                 }
             };
         })
-        .unwrap();
+        .map_err(|err| err.error)?;
 
-    let source = ListeningSocketSource::new_auto().unwrap();
-    let socket_name = source.socket_name().to_string_lossy().into_owned();
+    let socket_name = listener.socket_name();
     tracing::info!(?socket_name, "Listening on wayland socket.");
-    event_loop
-        .handle()
-        .insert_source(source, |client_stream, _, state| {
-            if let Err(err) = state
-                .dh
-                .insert_client(client_stream, Arc::new(ClientState::default()))
-            {
-                tracing::error!(?err, "Error adding wayland client.");
-            };
-        })
-        .expect("Failed to init wayland socket source");
+    match listener {
+        Listener::Source(source) => {
+            event_loop
+                .handle()
+                .insert_source(source, |client_stream, _, state| {
+                    if let Err(err) = state.insert_client(client_stream, false, None, None) {
+                        tracing::error!(?err, "Error adding wayland client.");
+                    };
+                })
+                .map_err(|err| err.error)?;
+        }
+        Listener::Unix(listener, lock) => {
+            let listener_token = Rc::new(Cell::new(None));
+            let token = event_loop
+                .handle()
+                .insert_source(Generic::new(listener, Interest::READ, Mode::Level), {
+                    let listener_token = listener_token.clone();
+                    move |_, listener, state| {
+                        // the socket stays locked for as long as the source exists
+                        let _lock = &lock;
+                        loop {
+                            match listener.accept() {
+                                Ok((client_stream, _)) => {
+                                    if let Err(err) = state.insert_client(client_stream, false, None, None) {
+                                        tracing::error!(?err, "Error adding wayland client.");
+                                    };
+                                }
+                                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => break,
+                                // e.g. an aborted connection or too many open files, the socket stays readable,
+                                // so stop polling it for a moment instead of spinning on the error
+                                Err(err) => {
+                                    tracing::warn!(?err, "Failed to accept wayland client, retrying shortly.");
+                                    let listener_token = listener_token.clone();
+                                    let res = state.handle.insert_source(
+                                        Timer::from_duration(ACCEPT_BACKOFF),
+                                        move |_, _, state| {
+                                            if let Some(token) = listener_token.get() {
+                                                if let Err(err) = state.handle.enable(&token) {
+                                                    tracing::error!(?err, "Failed to resume wayland socket.");
+                                                }
+                                            }
+                                            TimeoutAction::Drop
+                                        },
+                                    );
+                                    if let Err(err) = res {
+                                        tracing::error!(?err, "Event loop error.");
+                                        break;
+                                    }
+                                    return Ok(PostAction::Disable);
+                                }
+                            }
+                        }
+                        Ok(PostAction::Continue)
+                    }
+                })
+                .map_err(|err| err.error)?;
+            listener_token.set(Some(token));
+        }
+    };
 
     event_loop.
         handle()
@@ -526,25 +1040,63 @@ pub(crate) fn init(
             |_, display, state| {
                 // Safety: we don't drop the display
                 unsafe {
-                    display.get_mut().dispatch_clients(state).unwrap();
+                    display.get_mut().dispatch_clients(state)?;
                 }
                 Ok(PostAction::Continue)
             },
         )
-        .unwrap();
+        .map_err(|err| err.error)?;
 
-    let env_vars = vec![CString::new(format!("WAYLAND_DISPLAY={}", socket_name)).unwrap()];
+    let env_vars = socket_name
+        .into_iter()
+        .map(|socket_name| CString::new(format!("WAYLAND_DISPLAY={}", socket_name)).unwrap())
+        .collect();
     if let Err(err) = envs_tx.send(env_vars) {
         tracing::warn!(?err, "Failed to post environment to application.");
     }
 
+    Ok((event_loop, state))
+}
+
+impl State {
+    /// Reports and removes applications that exited, returns whether any are still running.
+    fn reap_children(&mut self, children: &Mutex<Vec<Child>>) -> bool {
+        let mut exited = Vec::new();
+        let running = {
+            let mut children = children.lock().unwrap();
+            children.retain_mut(|child| match child.try_wait() {
+                Ok(None) => true,
+                Ok(Some(status)) => {
+                    exited.push((child.id(), status.code()));
+                    false
+                }
+                Err(err) => {
+                    tracing::warn!(?err, pid = child.id(), "Failed to wait for application.");
+                    exited.push((child.id(), None));
+                    false
+                }
+            });
+            !children.is_empty()
+        };
+        // callbacks may lock anything, so the list isn't held while they run
+        for (pid, exit_code) in exited {
+            tracing::info!(pid, ?exit_code, "Application exited.");
+            self.emit(DisplayEvent::ProcessExited { pid, exit_code });
+        }
+        running
+    }
+}
+
+/// Runs the compositor until it is asked to quit.
+pub(crate) fn run(mut event_loop: EventLoop<'static, State>, mut state: State) {
     let signal = event_loop.get_signal();
     if let Err(err) = event_loop.run(None, &mut state, |state| {
-        state.dh
-            .flush_clients()
-            .expect("Failed to flush clients");
+        if let Err(err) = state.dh.flush_clients() {
+            tracing::warn!(?err, "Failed to flush clients.");
+        }
         state.space.refresh();
         state.popups.cleanup();
+        state.handle_disconnected_clients();
 
         if state.should_quit {
             signal.stop();
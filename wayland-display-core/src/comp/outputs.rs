@@ -0,0 +1,240 @@
+use std::{
+    cell::Cell,
+    time::{Duration, Instant},
+};
+
+use gst_video::VideoInfo;
+use smithay::{
+    backend::renderer::{
+        damage::OutputDamageTracker, element::solid::SolidColorBuffer, gles::GlesRenderbuffer,
+    },
+    desktop::{layer_map_for_output, Window},
+    output::{Mode as OutputMode, Output},
+    reexports::{
+        calloop::timer::{TimeoutAction, Timer},
+        wayland_server::backend::GlobalId,
+    },
+    utils::{Logical, Rectangle},
+};
+
+use super::{output_scale, State};
+use crate::{
+    wayland::protocols::color_management::{ColorManagementState, ImageDescription},
+    DisplayMode, HdrMetadata, OutputInfo,
+};
+
+/// How long the global of a removed output stays disabled, before it gets destroyed.
+///
+/// Clients that got the global announced may still bind it in the meantime, without being disconnected.
+const GLOBAL_REMOVAL_DELAY: Duration = Duration::from_secs(5);
+
+/// Id of the output driven by [`crate::WaylandDisplay::set_video_info`] and [`crate::WaylandDisplay::frame`].
+pub const PRIMARY_OUTPUT: u32 = 0;
+
+/// A virtual monitor with its own video stream.
+pub struct VirtualOutput {
+    pub id: u32,
+    pub output: Output,
+    pub video_info: VideoInfo,
+    global: GlobalId,
+    pub(super) dtr: OutputDamageTracker,
+    /// Tracks damage in the frames while they show a [`super::FrameView`], created on demand.
+    pub(super) view_dtr: Option<OutputDamageTracker>,
+    pub(super) renderbuffer: Option<GlesRenderbuffer>,
+    pub(super) background: SolidColorBuffer,
+    pub(super) last_render: Option<Instant>,
+    pub(super) hdr_metadata: Option<HdrMetadata>,
+}
+
+/// The output a window was put on, windows start out on the primary output.
+struct WindowOutput(Cell<u32>);
+
+pub fn window_output_id(window: &Window) -> u32 {
+    window
+        .user_data()
+        .get::<WindowOutput>()
+        .map_or(PRIMARY_OUTPUT, |output| output.0.get())
+}
+
+impl State {
+    pub fn virtual_output(&self, id: u32) -> Option<&VirtualOutput> {
+        self.outputs.iter().find(|output| output.id == id)
+    }
+
+    /// Registers a new output, it shows up for clients once its video info is set.
+    pub fn add_output(&mut self, info: OutputInfo) -> u32 {
+        let id = self.next_output_id;
+        self.next_output_id += 1;
+        self.output_infos.insert(id, info);
+        id
+    }
+
+    /// Creates or reconfigures an output for frames of the given format, size and framerate.
+    pub fn set_output_video_info(&mut self, id: u32, info: VideoInfo) {
+        let mut display_mode = DisplayMode::from_video_info(&info);
+        if let (PRIMARY_OUTPUT, Some((width, height))) = (id, self.render_size) {
+            display_mode.width = width;
+            display_mode.height = height;
+        }
+        let mode = OutputMode::from(display_mode);
+        let renderbuffer = self.renderer.create_renderbuffer(&info).unwrap_or_else(|err| {
+            tracing::error!(?err, "Failed to create renderbuffer.");
+            None
+        });
+
+        let output = match self.outputs.iter_mut().find(|output| output.id == id) {
+            Some(virtual_output) => {
+                virtual_output.output.change_current_state(Some(mode), None, None, None);
+                virtual_output.output.set_preferred(mode);
+                virtual_output.dtr = OutputDamageTracker::from_output(&virtual_output.output);
+                virtual_output.view_dtr = None;
+                virtual_output.renderbuffer = renderbuffer;
+                virtual_output.video_info = info.clone();
+                virtual_output.output.clone()
+            }
+            None => {
+                let Some(output_info) = self.output_infos.get(&id) else {
+                    tracing::warn!(id, "No such output.");
+                    return;
+                };
+                let output = Output::new(output_info.name.clone(), output_info.physical_properties());
+                let global = output.create_global::<State>(&self.dh);
+                output.change_current_state(
+                    Some(mode),
+                    Some(self.output_transform),
+                    Some(output_scale(self.output_scale)),
+                    None,
+                );
+                output.set_preferred(mode);
+                self.outputs.push(VirtualOutput {
+                    id,
+                    output: output.clone(),
+                    video_info: info.clone(),
+                    global,
+                    dtr: OutputDamageTracker::from_output(&output),
+                    view_dtr: None,
+                    renderbuffer,
+                    background: SolidColorBuffer::new((0, 0), [0.0, 0.0, 0.0, 1.0]),
+                    last_render: None,
+                    hdr_metadata: None,
+                });
+                self.outputs.sort_by_key(|output| output.id);
+                if id == PRIMARY_OUTPUT {
+                    self.output = Some(output.clone());
+                }
+                output
+            }
+        };
+        self.arrange_outputs();
+
+        if id == PRIMARY_OUTPUT {
+            if let Some(output_geo) = self.space.output_geometry(&output) {
+                self.pointer_location =
+                    (output_geo.size.w as f64 / 2.0, output_geo.size.h as f64 / 2.0).into();
+            }
+            self.map_pending_layers();
+        }
+        // clients are asked to render in the color space of the stream
+        ColorManagementState::set_output_description(
+            self,
+            &output,
+            ImageDescription::from_colorimetry(&info.colorimetry()),
+        );
+        self.update_output_modes();
+        self.configure_toplevels();
+    }
+
+    /// Removes an output other than the primary one, its windows move to the primary output.
+    pub fn remove_output(&mut self, id: u32) {
+        if id == PRIMARY_OUTPUT {
+            tracing::warn!("The primary output can't be removed.");
+            return;
+        }
+        self.output_infos.remove(&id);
+        self.output_modes.remove(&id);
+        let Some(idx) = self.outputs.iter().position(|output| output.id == id) else {
+            return;
+        };
+
+        let virtual_output = self.outputs.remove(idx);
+        for layer in layer_map_for_output(&virtual_output.output).layers() {
+            layer.layer_surface().send_close();
+        }
+        self.space.unmap_output(&virtual_output.output);
+        self.color_management_state.remove_output(&virtual_output.output);
+        self.remove_global_later(virtual_output.global);
+        self.output_management_state.remove(&virtual_output.output.name());
+        for window in self.space.elements() {
+            if window_output_id(window) == id {
+                set_window_output_id(window, PRIMARY_OUTPUT);
+            }
+        }
+
+        self.arrange_outputs();
+        self.pointer_location = self.clamp_coords(self.pointer_location);
+        self.update_output_modes();
+        self.configure_toplevels();
+    }
+
+    /// Disables a global right away, but only destroys it after [`GLOBAL_REMOVAL_DELAY`].
+    fn remove_global_later(&mut self, global: GlobalId) {
+        self.dh.disable_global::<State>(global.clone());
+        let result = self.handle.insert_source(
+            Timer::from_duration(GLOBAL_REMOVAL_DELAY),
+            move |_, _, state| {
+                state.dh.remove_global::<State>(global.clone());
+                TimeoutAction::Drop
+            },
+        );
+        if let Err(err) = result {
+            tracing::error!(?err, "Failed to delay removing a global.");
+        }
+    }
+
+    /// Places the outputs next to each other, from left to right in the order of their ids.
+    pub fn arrange_outputs(&mut self) {
+        let mut x = 0;
+        for virtual_output in &self.outputs {
+            self.space.map_output(&virtual_output.output, (x, 0));
+            x += self
+                .space
+                .output_geometry(&virtual_output.output)
+                .map_or(0, |geometry| geometry.size.w);
+        }
+    }
+
+    /// Moves a window onto another output, maximizing it there.
+    pub fn move_window_to_output(&mut self, window: &Window, id: u32) {
+        if self.virtual_output(id).is_none() {
+            tracing::warn!(id, "No such output.");
+            return;
+        }
+        set_window_output_id(window, id);
+        self.configure_toplevels();
+        if let Some(zone) = self.window_output(window).map(|output| self.output_zone(output)) {
+            self.center_window(window, zone);
+        }
+    }
+
+    /// The output a window is shown on, the primary one unless it was moved.
+    pub fn window_output(&self, window: &Window) -> Option<&Output> {
+        self.virtual_output(window_output_id(window))
+            .map(|virtual_output| &virtual_output.output)
+            .or(self.output.as_ref())
+    }
+
+    /// The area of an output not reserved by layer surfaces, in desktop coordinates.
+    pub fn output_zone(&self, output: &Output) -> Rectangle<i32, Logical> {
+        let mut zone = layer_map_for_output(output).non_exclusive_zone();
+        if let Some(output_geo) = self.space.output_geometry(output) {
+            zone.loc += output_geo.loc;
+        }
+        zone
+    }
+}
+
+fn set_window_output_id(window: &Window, id: u32) {
+    let user_data = window.user_data();
+    user_data.insert_if_missing(|| WindowOutput(Cell::new(PRIMARY_OUTPUT)));
+    user_data.get::<WindowOutput>().unwrap().0.set(id);
+}
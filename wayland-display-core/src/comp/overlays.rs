@@ -0,0 +1,100 @@
+use std::cmp::Reverse;
+
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            element::{
+                memory::{MemoryBuffer, MemoryRenderBuffer, MemoryRenderBufferRenderElement},
+                Kind,
+            },
+            ImportAll, ImportMem, Renderer, Texture,
+        },
+    },
+    utils::{Physical, Point, Size, Transform},
+};
+
+use super::State;
+use crate::Overlay;
+
+/// An [`Overlay`] uploaded for rendering.
+pub struct OverlayBuffer {
+    id: u64,
+    buffer: MemoryRenderBuffer,
+    location: Point<i32, Physical>,
+    size: Size<i32, Physical>,
+    z: i32,
+}
+
+impl OverlayBuffer {
+    fn new(id: u64, overlay: &Overlay) -> Self {
+        let image = &overlay.image;
+        // RGBA bytes are ABGR in little endian words
+        let buffer = MemoryBuffer::from_slice(
+            &image.premultiplied_data(),
+            Fourcc::Abgr8888,
+            (image.width(), image.height()),
+        );
+
+        OverlayBuffer {
+            id,
+            buffer: MemoryRenderBuffer::from_memory(buffer, 1, Transform::Normal, None),
+            location: (overlay.x, overlay.y).into(),
+            size: (image.width(), image.height()).into(),
+            z: overlay.z,
+        }
+    }
+}
+
+/// Elements for the overlays, in frame coordinates and from top to bottom.
+///
+/// Overlays keep their size in pixels, independent of the output scale.
+pub fn overlay_elements<R>(
+    renderer: &mut R,
+    overlays: &[OverlayBuffer],
+    scale: f64,
+) -> Result<Vec<MemoryRenderBufferRenderElement<R>>, R::Error>
+where
+    R: Renderer + ImportAll + ImportMem,
+    R::TextureId: Texture + Clone + 'static,
+{
+    // overlays are kept in the order they were added, newer ones go on top of older ones
+    let mut overlays = overlays.iter().rev().collect::<Vec<_>>();
+    overlays.sort_by_key(|overlay| Reverse(overlay.z));
+
+    overlays
+        .into_iter()
+        .map(|overlay| {
+            MemoryRenderBufferRenderElement::from_buffer(
+                renderer,
+                overlay.location.to_f64(),
+                &overlay.buffer,
+                None,
+                None,
+                Some(overlay.size.to_f64().to_logical(scale).to_i32_round()),
+                Kind::Unspecified,
+            )
+        })
+        .collect()
+}
+
+impl State {
+    pub fn add_overlay(&mut self, id: u64, overlay: Overlay) {
+        self.overlays.push(OverlayBuffer::new(id, &overlay));
+    }
+
+    pub fn update_overlay(&mut self, id: u64, overlay: Overlay) {
+        match self.overlays.iter_mut().find(|buffer| buffer.id == id) {
+            Some(buffer) => *buffer = OverlayBuffer::new(id, &overlay),
+            None => tracing::warn!(id, "No such overlay."),
+        }
+    }
+
+    pub fn remove_overlay(&mut self, id: u64) {
+        let count = self.overlays.len();
+        self.overlays.retain(|buffer| buffer.id != id);
+        if self.overlays.len() == count {
+            tracing::warn!(id, "No such overlay.");
+        }
+    }
+}
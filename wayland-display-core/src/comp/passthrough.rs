@@ -0,0 +1,217 @@
+use std::{
+    collections::HashMap,
+    os::fd::BorrowedFd,
+    sync::Arc,
+};
+
+use gst_video::VideoInfo;
+use smithay::{
+    backend::{
+        allocator::{dmabuf::Dmabuf, Buffer as _, Fourcc, Modifier},
+        renderer::{
+            buffer_dimensions,
+            damage::RenderOutputResult,
+            element::{Id, RenderElementPresentationState, RenderElementState, RenderElementStates},
+            sync::SyncPoint,
+            utils::{Buffer, RendererSurfaceStateUserData},
+        },
+    },
+    desktop::{layer_map_for_output, PopupManager},
+    input::pointer::CursorImageStatus,
+    reexports::{
+        calloop::{ping::Ping, Interest},
+        wayland_server::protocol::wl_output,
+    },
+    utils::{Point, Rectangle, Transform},
+    wayland::{
+        compositor::{get_children, with_states, SurfaceAttributes},
+        dmabuf::get_dmabuf,
+        shm::{shm_format_to_fourcc, with_buffer_contents},
+    },
+};
+
+use super::{drm_format, State, PRIMARY_OUTPUT};
+
+/// Keeps a client's dmabuf from being released, while downstream still reads from it.
+///
+/// The buffer itself stays with the compositor, dropping the lease on any thread only wakes the compositor,
+/// which then releases it.
+struct BufferLease {
+    token: Option<Arc<()>>,
+    release: Ping,
+}
+
+impl AsRef<[u8]> for BufferLease {
+    fn as_ref(&self) -> &[u8] {
+        &[]
+    }
+}
+
+impl Drop for BufferLease {
+    fn drop(&mut self) {
+        // the token has to be gone, before the compositor looks for released buffers
+        self.token.take();
+        self.release.ping();
+    }
+}
+
+impl State {
+    /// Hands out the buffer of a single fullscreen client, if nothing else would have
+    /// to be composited on top of it and it exactly matches the negotiated size and format.
+    ///
+    /// Only linear dmabufs are handed downstream as they are, shm buffers are copied once,
+    /// as the client's pool may be remapped while downstream still reads from it.
+    pub fn passthrough_frame(
+        &mut self,
+        index: usize,
+        show_cursor: bool,
+    ) -> Option<(gst::Buffer, RenderOutputResult)> {
+        let output = self.outputs.get(index)?.output.clone();
+        let video_info = self.outputs.get(index)?.video_info.clone();
+        let output_geo = self.space.output_geometry(&output)?;
+        let format = drm_format(video_info.format())?;
+
+        if show_cursor && !matches!(self.cursor_state, CursorImageStatus::Hidden) {
+            return None;
+        }
+        if self.outputs[index].id == PRIMARY_OUTPUT && !self.overlays.is_empty() {
+            return None;
+        }
+        if output.current_transform() != Transform::Normal
+            || layer_map_for_output(&output).layers().next().is_some()
+        {
+            return None;
+        }
+
+        let mut windows = self.space.elements_for_output(&output);
+        let window = windows.next()?;
+        if windows.next().is_some() {
+            return None;
+        }
+        if self.space.element_location(window)? != output_geo.loc
+            || window.geometry().loc != Point::from((0, 0))
+        {
+            return None;
+        }
+
+        let surface = window.wl_surface()?.into_owned();
+        if PopupManager::popups_for_surface(&surface).next().is_some()
+            || !get_children(&surface).is_empty()
+        {
+            return None;
+        }
+
+        let size = (video_info.width() as i32, video_info.height() as i32);
+        let buffer = with_states(&surface, |states| {
+            let mut attributes = states.cached_state.get::<SurfaceAttributes>();
+            let attributes = attributes.current();
+            if attributes.buffer_scale != 1 || attributes.buffer_transform != wl_output::Transform::Normal {
+                return None;
+            }
+            let data = states.data_map.get::<RendererSurfaceStateUserData>()?.borrow();
+            // catches viewports, the buffer has to be shown unscaled and uncropped
+            let view = data.view()?;
+            if view.dst != size.into()
+                || view.src != Rectangle::from_loc_and_size((0., 0.), (size.0 as f64, size.1 as f64))
+            {
+                return None;
+            }
+            data.buffer().cloned()
+        })?;
+        if buffer_dimensions(&buffer)? != size.into() {
+            return None;
+        }
+
+        let gst_buffer = match get_dmabuf(&buffer).cloned() {
+            Ok(dmabuf) => {
+                let mut gst_buffer = dmabuf_frame(dmabuf, &video_info, format)?;
+                let lease = self.lease_buffer(buffer);
+                gst::meta::ParentBufferMeta::add(gst_buffer.get_mut().unwrap(), &gst::Buffer::from_slice(lease));
+                gst_buffer
+            }
+            Err(_) => shm_frame(buffer, &video_info, format)?,
+        };
+        tracing::trace!(surface = ?surface, "Passing through client buffer.");
+
+        let mut states = RenderElementStates {
+            states: HashMap::new(),
+        };
+        states.states.insert(
+            Id::from_wayland_resource(&surface),
+            RenderElementState {
+                visible_area: (size.0 * size.1) as usize,
+                presentation_state: RenderElementPresentationState::ZeroCopy,
+            },
+        );
+        Some((
+            gst_buffer,
+            RenderOutputResult {
+                sync: SyncPoint::signaled(),
+                damage: Some(vec![Rectangle::from_loc_and_size((0, 0), size)]),
+                states,
+            },
+        ))
+    }
+}
+
+impl State {
+    /// Keeps a client buffer until downstream dropped the lease.
+    fn lease_buffer(&mut self, buffer: Buffer) -> BufferLease {
+        let token = Arc::new(());
+        self.leased_buffers.push((Arc::downgrade(&token), buffer));
+        BufferLease {
+            token: Some(token),
+            release: self.buffer_release.clone(),
+        }
+    }
+
+    /// Releases the client buffers downstream is done with.
+    pub fn release_buffers(&mut self) {
+        self.leased_buffers.retain(|(token, _)| token.strong_count() > 0);
+    }
+}
+
+/// Copies a client's shm buffer, skipping the compositing.
+///
+/// The pool gets remapped on `wl_shm_pool.resize` and the client may keep drawing into it,
+/// so the contents can't be handed downstream without a copy.
+fn shm_frame(buffer: Buffer, video_info: &VideoInfo, format: Fourcc) -> Option<gst::Buffer> {
+    with_buffer_contents(&buffer, |ptr, len, data| {
+        let size = data.stride as usize * data.height as usize;
+        if shm_format_to_fourcc(data.format) != Some(format)
+            || data.stride != video_info.stride()[0]
+            || data.offset as usize + size > len
+        {
+            return None;
+        }
+        let contents = unsafe { std::slice::from_raw_parts(ptr.add(data.offset as usize), size) };
+        Some(gst::Buffer::from_slice(contents.to_vec()))
+    })
+    .ok()?
+}
+
+fn dmabuf_frame(dmabuf: Dmabuf, video_info: &VideoInfo, format: Fourcc) -> Option<gst::Buffer> {
+    // downstream expects system memory layout, so only mappable, tightly packed dmabufs qualify
+    if dmabuf.format().code != format
+        || dmabuf.format().modifier != Modifier::Linear
+        || dmabuf.num_planes() != 1
+        || dmabuf.offsets().next() != Some(0)
+        || dmabuf.strides().next() != Some(video_info.stride()[0] as u32)
+    {
+        return None;
+    }
+    // downstream doesn't wait on the implicit fences, buffers still being drawn to get composited
+    if dmabuf.generate_blocker(Interest::READ).is_ok() {
+        return None;
+    }
+
+    let fd: BorrowedFd<'_> = dmabuf.handles().next()?;
+    let fd = fd.try_clone_to_owned().ok()?;
+    let memory = unsafe { gst_allocators::DmaBufAllocator::new().alloc(fd, video_info.size()) }
+        .map_err(|err| tracing::warn!(?err, "Failed to wrap client dmabuf."))
+        .ok()?;
+
+    let mut gst_buffer = gst::Buffer::new();
+    gst_buffer.get_mut().unwrap().append_memory(memory);
+    Some(gst_buffer)
+}
@@ -1,23 +1,47 @@
-use std::time::{Duration, Instant};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use gst_video::{VideoFormat, VideoInfo};
 use smithay::{
-    desktop::space::render_output,
-    backend::allocator::Fourcc,
+    desktop::{
+        space::{space_render_elements, SpaceRenderElements},
+        Space, Window,
+    },
+    backend::allocator::{dmabuf::Dmabuf, Format, Fourcc},
+    backend::drm::DrmNode,
+    backend::egl::EGLDisplay,
     backend::renderer::{
-        damage::Error as DTRError,
-        damage::RenderOutputResult,
+        damage::{Error as DTRError, OutputDamageTracker, RenderOutputResult},
         element::{
-            memory::MemoryRenderBufferRenderElement, surface::WaylandSurfaceRenderElement, Kind,
+            memory::{MemoryRenderBuffer, MemoryRenderBufferRenderElement},
+            solid::SolidColorRenderElement,
+            surface::WaylandSurfaceRenderElement,
+            utils::{CropRenderElement, RelocateRenderElement, RescaleRenderElement, Relocate},
+            Kind,
         },
-        gles::GlesRenderer,
-        Bind, ExportMem, ImportAll, ImportMem, Renderer, Unbind,
+        gles::{ffi, GlesError, GlesRenderbuffer, GlesRenderer},
+        pixman::PixmanRenderer,
+        Bind, ExportMem, ImportAll, ImportDma, ImportMem, Offscreen, Renderer, Texture, Unbind,
     },
+    backend::SwapBuffersError,
     input::pointer::CursorImageStatus,
+    output::Output,
+    reexports::pixman,
     render_elements,
-    utils::{Rectangle},
+    utils::{Logical, Point, Rectangle},
 };
 
-use super::State;
+use super::{
+    background_elements, create_dmabuf_globals, init_renderer, overlay_elements, FrameView, State,
+    window_output_id, EGL_DISPLAYS, PRIMARY_OUTPUT,
+};
+use crate::{
+    utils::RenderTarget,
+    wayland::protocols::color_management::surface_image_description,
+    DisplayEvent, FrameError, GlHandles, GlSync, HdrMetadata,
+};
 
 pub const CURSOR_DATA_BYTES: &[u8] = include_bytes!("../../resources/cursor.rgba");
 
@@ -27,99 +51,655 @@ render_elements! {
     Memory=MemoryRenderBufferRenderElement<R>
 }
 
+/// Where a rendered frame ends up.
+pub enum FrameTarget {
+    /// A new system memory [`gst::Buffer`].
+    Memory,
+    /// An existing 2D texture in a GL context shared with the renderer's.
+    Texture(u32),
+}
+
+/// What a frame got rendered into, depending on its [`FrameTarget`].
+pub enum RenderedFrame {
+    Buffer(gst::Buffer),
+    /// The texture is written once the sync object is signalled.
+    Texture(GlSync),
+}
+
+/// The DRM format with the same memory layout as a video format.
+pub fn drm_format(format: VideoFormat) -> Option<Fourcc> {
+    match format {
+        VideoFormat::Rgbx => Some(Fourcc::Xbgr8888),
+        VideoFormat::Rgba => Some(Fourcc::Abgr8888),
+        VideoFormat::Bgrx => Some(Fourcc::Xrgb8888),
+        VideoFormat::Bgra => Some(Fourcc::Argb8888),
+        VideoFormat::Rgb10a2Le => Some(Fourcc::Abgr2101010),
+        VideoFormat::Bgr10a2Le => Some(Fourcc::Argb2101010),
+        _ => None,
+    }
+}
+
+/// The format to render and read back in with GL, and whether red and blue have to be swapped
+/// afterwards. GLES can't read back 10-bit formats with blue in the low bits.
+fn gl_format(format: Fourcc) -> (Fourcc, bool) {
+    match format {
+        Fourcc::Argb2101010 => (Fourcc::Abgr2101010, true),
+        Fourcc::Xrgb2101010 => (Fourcc::Xbgr2101010, true),
+        format => (format, false),
+    }
+}
+
+/// Copies 2:10:10:10 pixels, swapping the red and blue channels.
+fn copy_swap_rb_2101010(dst: &mut [u8], src: &[u8]) {
+    for (dst, src) in dst.chunks_exact_mut(4).zip(src.chunks_exact(4)) {
+        let pixel = u32::from_le_bytes(src.try_into().unwrap());
+        let pixel = (pixel & 0xc00f_fc00) | ((pixel & 0x3ff) << 20) | ((pixel >> 20) & 0x3ff);
+        dst.copy_from_slice(&pixel.to_le_bytes());
+    }
+}
+
+/// The renderer in use, GL on a (possibly software) EGL device or pixman on the CPU.
+pub enum RenderBackend {
+    Gles {
+        renderer: GlesRenderer,
+        egl_display_ref: Arc<EGLDisplay>,
+    },
+    Pixman(PixmanRenderer),
+}
+
+impl RenderBackend {
+    /// Formats clients may use for dmabufs, empty if dmabufs aren't supported.
+    pub fn dmabuf_formats(&self) -> Vec<Format> {
+        match self {
+            RenderBackend::Gles { renderer, .. } => {
+                ImportDma::dmabuf_formats(renderer).into_iter().collect()
+            }
+            RenderBackend::Pixman(_) => Vec::new(),
+        }
+    }
+
+    /// Formats clients may use for shm buffers.
+    pub fn shm_formats(&self) -> Vec<Fourcc> {
+        match self {
+            RenderBackend::Gles { renderer, .. } => renderer.mem_formats().collect(),
+            RenderBackend::Pixman(renderer) => renderer.mem_formats().collect(),
+        }
+    }
+
+    pub fn gl_handles(&self) -> Option<GlHandles> {
+        match self {
+            RenderBackend::Gles {
+                renderer,
+                egl_display_ref,
+                ..
+            } => Some(GlHandles {
+                egl_display: egl_display_ref.get_display_handle().handle as usize,
+                egl_context: renderer.egl_context().get_context_handle() as usize,
+            }),
+            RenderBackend::Pixman(_) => None,
+        }
+    }
+
+    pub fn import_dmabuf(&mut self, dmabuf: &Dmabuf) -> bool {
+        match self {
+            RenderBackend::Gles { renderer, .. } => renderer.import_dmabuf(dmabuf, None).is_ok(),
+            RenderBackend::Pixman(_) => false,
+        }
+    }
+
+    /// Creates a render target for frames of the given format and size, pixman renders straight
+    /// into the frame memory and needs none.
+    pub fn create_renderbuffer(&mut self, info: &VideoInfo) -> Result<Option<GlesRenderbuffer>, GlesError> {
+        match self {
+            RenderBackend::Gles { renderer, .. } => renderer
+                .create_buffer(
+                    gl_format(drm_format(info.format()).unwrap_or(Fourcc::Abgr8888)).0,
+                    (info.width() as i32, info.height() as i32).into(),
+                )
+                .map(Some),
+            RenderBackend::Pixman(_) => Ok(None),
+        }
+    }
+}
+
+fn cursor_elements<R>(
+    renderer: &mut R,
+    cursor_state: &CursorImageStatus,
+    cursor_element: &MemoryRenderBuffer,
+    location: Point<f64, Logical>,
+    scale: f64,
+) -> Result<Vec<CursorElement<R>>, R::Error>
+where
+    R: Renderer + ImportAll + ImportMem,
+    R::TextureId: Texture + Clone + 'static,
+{
+    Ok(match cursor_state {
+        CursorImageStatus::Named(_cursor_icon) => vec![CursorElement::Memory(
+            // TODO: icon?
+            MemoryRenderBufferRenderElement::from_buffer(
+                renderer,
+                location.to_physical_precise_round(scale),
+                cursor_element,
+                None,
+                None,
+                None,
+                Kind::Cursor,
+            )?,
+        )],
+        CursorImageStatus::Surface(wl_surface) => {
+            smithay::backend::renderer::element::surface::render_elements_from_surface_tree(
+                renderer,
+                wl_surface,
+                location.to_physical_precise_round(scale),
+                scale,
+                1.,
+                Kind::Cursor,
+            )
+        }
+        CursorImageStatus::Hidden => vec![],
+    })
+}
+
+render_elements! {
+    ViewElement<R> where R: Renderer + ImportAll + ImportMem;
+    Space=SpaceRenderElements<R, WaylandSurfaceRenderElement<R>>,
+    Cursor=CursorElement<R>,
+    Memory=MemoryRenderBufferRenderElement<R>,
+    Color=SolidColorRenderElement
+}
+
+render_elements! {
+    FrameElement<R> where R: Renderer + ImportAll + ImportMem;
+    View=RelocateRenderElement<RescaleRenderElement<CropRenderElement<ViewElement<R>>>>,
+    Overlay=MemoryRenderBufferRenderElement<R>
+}
+
+fn render<R>(
+    renderer: &mut R,
+    output: &Output,
+    space: &Space<Window>,
+    overlays: Vec<MemoryRenderBufferRenderElement<R>>,
+    elements: Vec<CursorElement<R>>,
+    background: Vec<ViewElement<R>>,
+    view: Option<&FrameView>,
+    dtr: &mut OutputDamageTracker,
+) -> Result<RenderOutputResult, FrameError>
+where
+    R: Renderer + ImportAll + ImportMem,
+    R::TextureId: Texture + Clone + 'static,
+    R::Error: Into<SwapBuffersError>,
+{
+    let scale = output.current_scale().fractional_scale();
+    let space_elements = space_render_elements(renderer, [space], output, 1.0).map_err(|err| {
+        tracing::error!(?err, "Rendering failed.");
+        FrameError::Render
+    })?;
+    // from top to bottom, like `render_output` plus the background
+    let elements = elements
+        .into_iter()
+        .map(ViewElement::from)
+        .chain(space_elements.into_iter().map(ViewElement::from))
+        .chain(background);
+    let Some(view) = view else {
+        // overlays are placed in frame coordinates, which match the output's here
+        let elements = overlays
+            .into_iter()
+            .map(ViewElement::from)
+            .chain(elements)
+            .collect::<Vec<_>>();
+        return dtr
+            .render_output(renderer, 0, &elements, [0.0, 0.0, 0.0, 1.0])
+            .map_err(frame_error);
+    };
+
+    // crop to the shown area, then scale and move it into place, overlays stay untouched on top
+    let elements = elements
+        .filter_map(|element| CropRenderElement::from_element(element, scale, view.src))
+        .map(|element| RescaleRenderElement::from_element(element, view.src.loc, view.zoom))
+        .map(|element| {
+            RelocateRenderElement::from_element(element, view.offset - view.src.loc, Relocate::Relative)
+        })
+        .map(FrameElement::from);
+    let elements = overlays
+        .into_iter()
+        .map(FrameElement::from)
+        .chain(elements)
+        .collect::<Vec<_>>();
+
+    // a moving view moves every element, which damages the whole frame
+    dtr.render_output(renderer, 0, &elements, [0.0, 0.0, 0.0, 1.0])
+        .map_err(frame_error)
+}
+
+pub(super) fn frame_error<R>(err: DTRError<R>) -> FrameError
+where
+    R: Renderer,
+    R::Error: Into<SwapBuffersError>,
+{
+    tracing::error!(?err, "Rendering failed.");
+    match err {
+        DTRError::Rendering(err) => swap_buffers_error(err),
+        DTRError::OutputNoMode(_) => FrameError::Render,
+    }
+}
+
+pub(super) fn swap_buffers_error(err: impl Into<SwapBuffersError>) -> FrameError {
+    match err.into() {
+        SwapBuffersError::ContextLost(_) => FrameError::ContextLost,
+        SwapBuffersError::AlreadySwapped | SwapBuffersError::TemporaryFailure(_) => {
+            FrameError::Render
+        }
+    }
+}
+
 impl State {
+    /// Renders a frame of an output, rebuilding the renderer once if the rendering context got lost.
+    pub fn render_frame(
+        &mut self,
+        id: u32,
+        target: &FrameTarget,
+    ) -> Result<(RenderedFrame, RenderOutputResult), FrameError> {
+        if self.renderer_lost {
+            return Err(FrameError::ContextLost);
+        }
+        let Some(index) = self.outputs.iter().position(|output| output.id == id) else {
+            return Err(FrameError::Render);
+        };
+        match self.create_frame(index, target) {
+            Err(FrameError::ContextLost) if self.recover_renderer() => self.create_frame(index, target),
+            result => result,
+        }
+    }
+
+    /// Replaces a renderer, that lost its context, with a new one on the same target
+    /// (or the software renderer, if enabled). Client buffers get re-imported on the next frame.
+    ///
+    /// If no renderer can be created, all further frames fail with [`FrameError::ContextLost`].
+    pub(super) fn recover_renderer(&mut self) -> bool {
+        tracing::warn!(render_target = %self.render_target, "Rendering context lost, recreating renderer.");
+
+        // clients must not import buffers into the lost device anymore
+        if let Some((dmabuf_global, wl_drm_global)) = self.dmabuf_global.take() {
+            self.dmabuf_state.destroy_global::<State>(&self.dh, dmabuf_global);
+            self.dh.remove_global::<State>(wl_drm_global);
+        }
+
+        // the old EGL display has to go first, the new one may end up with the same native handle
+        self.renderer = match PixmanRenderer::new() {
+            Ok(renderer) => RenderBackend::Pixman(renderer),
+            Err(err) => {
+                tracing::error!(?err, "Failed to create placeholder renderer.");
+                self.renderer_lost = true;
+                return false;
+            }
+        };
+        // a cached display of the lost device would be reused otherwise
+        let lost_node: Option<DrmNode> = self.render_target.clone().into();
+        EGL_DISPLAYS.lock().unwrap().remove(&lost_node);
+
+        let mut candidates = vec![self.render_target.clone()];
+        if self.software_fallback {
+            for fallback in [RenderTarget::Software, RenderTarget::Pixman] {
+                if !candidates.contains(&fallback) {
+                    candidates.push(fallback);
+                }
+            }
+        }
+        let recovered = candidates.into_iter().find_map(|candidate| {
+            match init_renderer(&candidate) {
+                Ok(renderer) => Some((candidate, renderer)),
+                Err(err) => {
+                    tracing::warn!(?err, render_target = %candidate, "Failed to recreate renderer.");
+                    None
+                }
+            }
+        });
+
+        let recovered = match recovered {
+            Some((render_target, renderer)) => {
+                self.render_target = render_target;
+                self.renderer = renderer;
+                self.dmabuf_global =
+                    create_dmabuf_globals(&self.dh, &mut self.dmabuf_state, &self.renderer, &self.render_target)
+                        .unwrap_or_else(|err| {
+                            tracing::error!(?err, "Failed to recreate dmabuf globals.");
+                            None
+                        });
+                for virtual_output in &mut self.outputs {
+                    virtual_output.renderbuffer = self
+                        .renderer
+                        .create_renderbuffer(&virtual_output.video_info)
+                        .unwrap_or_else(|err| {
+                            tracing::error!(?err, "Failed to create renderbuffer.");
+                            None
+                        });
+                    // everything has to be redrawn with the new renderer
+                    virtual_output.dtr = OutputDamageTracker::from_output(&virtual_output.output);
+                    virtual_output.view_dtr = None;
+                }
+                // captured windows get new targets on their next frame
+                self.window_captures.clear();
+                tracing::info!(render_target = %self.render_target, "Recreated renderer.");
+                true
+            }
+            None => {
+                tracing::error!("Failed to recreate renderer, giving up.");
+                self.renderer_lost = true;
+                false
+            }
+        };
+
+        self.emit(DisplayEvent::ContextLost {
+            recovered,
+            render_node: self.render_target.to_string(),
+        });
+        recovered
+    }
+
+    /// Picks up the HDR metadata of the topmost window on an output that has any, notifying about changes.
+    pub fn update_hdr_metadata(&mut self, id: u32) {
+        let Some(virtual_output) = self.virtual_output(id) else {
+            return;
+        };
+        let output_description = self
+            .color_management_state
+            .output_description(&virtual_output.output);
+        let metadata = self
+            .space
+            .elements()
+            .rev()
+            .filter(|window| window_output_id(window) == id)
+            .filter_map(|window| surface_image_description(&*window.wl_surface()?))
+            // descriptions from before an output change may not match anymore
+            .filter(|description| output_description.same_color_space(description))
+            .find(|description| description.has_hdr_metadata())
+            .map(|description| HdrMetadata::from(&*description));
+
+        let Some(virtual_output) = self.outputs.iter_mut().find(|output| output.id == id) else {
+            return;
+        };
+        if metadata != virtual_output.hdr_metadata {
+            virtual_output.hdr_metadata = metadata.clone();
+            self.emit(DisplayEvent::HdrMetadataChanged { output: id, metadata });
+        }
+    }
+
+    /// Renders a frame of `self.outputs[index]`.
     pub fn create_frame(
         &mut self,
-    ) -> Result<
-        (
-            gst::Buffer,
-            RenderOutputResult,
-        ),
-        DTRError<GlesRenderer>,
-    > {
-        assert!(self.output.is_some());
-        assert!(self.dtr.is_some());
-        assert!(self.video_info.is_some());
-        assert!(self.renderbuffer.is_some());
-
-        let elements =
-            if Instant::now().duration_since(self.last_pointer_movement) < Duration::from_secs(5) {
-                match &self.cursor_state {
-                    CursorImageStatus::Named(_cursor_icon) => vec![CursorElement::Memory(
-                        // TODO: icon?
-                        MemoryRenderBufferRenderElement::from_buffer(
-                            &mut self.renderer,
-                            self.pointer_location.to_physical_precise_round(1),
-                            &self.cursor_element,
-                            None,
-                            None,
-                            None,
-                            Kind::Cursor,
-                        )
-                            .map_err(DTRError::Rendering)?,
-                    )],
-                    CursorImageStatus::Surface(wl_surface) => {
-                        smithay::backend::renderer::element::surface::render_elements_from_surface_tree(
-                            &mut self.renderer,
-                            wl_surface,
-                            self.pointer_location.to_physical_precise_round(1),
-                            1.,
-                            1.,
-                            Kind::Cursor,
-                        )
+        index: usize,
+        target: &FrameTarget,
+    ) -> Result<(RenderedFrame, RenderOutputResult), FrameError> {
+        let show_cursor =
+            Instant::now().duration_since(self.last_pointer_movement) < Duration::from_secs(5);
+        let view = self.frame_view(self.outputs[index].id);
+        if let (FrameTarget::Memory, None) = (target, view) {
+            if let Some((buffer, render_output_result)) = self.passthrough_frame(index, show_cursor) {
+                // the next composited frame can't rely on the damage tracker's last state
+                let virtual_output = &mut self.outputs[index];
+                virtual_output.dtr = OutputDamageTracker::from_output(&virtual_output.output);
+                return Ok((RenderedFrame::Buffer(buffer), render_output_result));
+            }
+        }
+        let virtual_output = &mut self.outputs[index];
+        let output_geo = self
+            .space
+            .output_geometry(&virtual_output.output)
+            .ok_or(FrameError::Render)?;
+        virtual_output.background.update(output_geo.size, self.background_color);
+        let output = &virtual_output.output;
+        let video_info = &virtual_output.video_info;
+        let background = &virtual_output.background;
+        let overlays = match virtual_output.id {
+            PRIMARY_OUTPUT => &self.overlays[..],
+            _ => &[],
+        };
+        let scale = output.current_scale().fractional_scale();
+        let dtr = match view {
+            // the frames may differ from the output in size, the view gets a tracker of their own
+            Some(_) => virtual_output.view_dtr.get_or_insert_with(|| {
+                OutputDamageTracker::new(
+                    (video_info.width() as i32, video_info.height() as i32),
+                    scale,
+                    output.current_transform(),
+                )
+            }),
+            None => &mut virtual_output.dtr,
+        };
+        // the cursor is drawn relative to the output
+        let cursor_location = self.pointer_location - output_geo.loc.to_f64();
+
+        match &mut self.renderer {
+            RenderBackend::Gles { renderer, .. } => {
+                let elements = if show_cursor {
+                    cursor_elements(
+                        renderer,
+                        &self.cursor_state,
+                        &self.cursor_element,
+                        cursor_location,
+                        scale,
+                    )
+                    .map_err(swap_buffers_error)?
+                } else {
+                    vec![]
+                };
+                let background = background_elements(
+                    renderer,
+                    background,
+                    self.wallpaper.as_ref(),
+                    self.wallpaper_mode,
+                    output_geo.size,
+                    scale,
+                )
+                .map_err(swap_buffers_error)?;
+
+                let overlays = overlay_elements(renderer, overlays, scale).map_err(swap_buffers_error)?;
+
+                let Some(renderbuffer) = virtual_output.renderbuffer.clone() else {
+                    return Err(FrameError::Render);
+                };
+                renderer.bind(renderbuffer).map_err(swap_buffers_error)?;
+                let render_output_result = render(
+                    renderer,
+                    output,
+                    &self.space,
+                    overlays,
+                    elements,
+                    background,
+                    view.as_ref(),
+                    dtr,
+                )?;
+
+                if let FrameTarget::Texture(texture) = *target {
+                    // copy on the gpu, the texture lives in a context sharing objects with ours
+                    let sync = renderer
+                        .with_context(|gl| unsafe {
+                            gl.BindTexture(ffi::TEXTURE_2D, texture);
+                            gl.CopyTexSubImage2D(
+                                ffi::TEXTURE_2D,
+                                0,
+                                0,
+                                0,
+                                0,
+                                0,
+                                video_info.width() as i32,
+                                video_info.height() as i32,
+                            );
+                            gl.BindTexture(ffi::TEXTURE_2D, 0);
+                            // the other context waits for the copy on this, it has to reach the gpu first
+                            let sync = gl.FenceSync(ffi::SYNC_GPU_COMMANDS_COMPLETE, 0);
+                            gl.Flush();
+                            sync
+                        })
+                        .map_err(swap_buffers_error)?;
+                    renderer.unbind().map_err(swap_buffers_error)?;
+                    if sync.is_null() {
+                        return Err(FrameError::Render);
                     }
-                    CursorImageStatus::Hidden => vec![],
+                    let sync = GlSync { sync: sync as usize };
+                    return Ok((RenderedFrame::Texture(sync), render_output_result));
                 }
-            } else {
-                vec![]
-            };
-
-        self.renderer
-            .bind(self.renderbuffer.clone().unwrap())
-            .map_err(DTRError::Rendering)?;
-        let render_output_result = render_output(
-            self.output.as_ref().unwrap(),
-            &mut self.renderer,
-            1.0,
-            0,
-            [&self.space],
-            &*elements,
-            self.dtr.as_mut().unwrap(),
-            [0.0, 0.0, 0.0, 1.0],
-        )?;
-
-        let mapping = self
-            .renderer
-            .copy_framebuffer(Rectangle::from_loc_and_size(
-                (0, 0),
-                (
-                    self.video_info.as_ref().unwrap().width() as i32,
-                    self.video_info.as_ref().unwrap().height() as i32,
-                ),
-            ), Fourcc::try_from(self.video_info.as_ref().unwrap().format().to_fourcc()).unwrap_or(Fourcc::Abgr8888))
-            .expect("Failed to export framebuffer");
-        let map = self
-            .renderer
-            .map_texture(&mapping)
-            .expect("Failed to download framebuffer");
-
-        let buffer = {
-            let mut buffer = gst::Buffer::with_size(map.len()).expect("failed to create buffer");
-            {
-                let buffer = buffer.get_mut().unwrap();
-
-                let mut vframe = gst_video::VideoFrameRef::from_buffer_ref_writable(
-                    buffer,
-                    self.video_info.as_ref().unwrap(),
+
+                let buffer = download_frame(renderer, video_info);
+                renderer.unbind().map_err(swap_buffers_error)?;
+                Ok((RenderedFrame::Buffer(buffer?), render_output_result))
+            }
+            RenderBackend::Pixman(renderer) => {
+                if let FrameTarget::Texture(_) = target {
+                    tracing::error!("Rendering into textures requires a GL renderer.");
+                    return Err(FrameError::Render);
+                }
+
+                let elements = if show_cursor {
+                    cursor_elements(
+                        renderer,
+                        &self.cursor_state,
+                        &self.cursor_element,
+                        cursor_location,
+                        scale,
+                    )
+                    .map_err(swap_buffers_error)?
+                } else {
+                    vec![]
+                };
+                let background = background_elements(
+                    renderer,
+                    background,
+                    self.wallpaper.as_ref(),
+                    self.wallpaper_mode,
+                    output_geo.size,
+                    scale,
                 )
-                    .unwrap();
-                let plane_data = vframe.plane_data_mut(0).unwrap();
-                plane_data.clone_from_slice(map);
+                .map_err(swap_buffers_error)?;
+
+                let overlays = overlay_elements(renderer, overlays, scale).map_err(swap_buffers_error)?;
+
+                let (buffer, render_output_result) = pixman_frame(renderer, video_info, |renderer| {
+                    render(
+                        renderer,
+                        output,
+                        &self.space,
+                        overlays,
+                        elements,
+                        background,
+                        view.as_ref(),
+                        dtr,
+                    )
+                })?;
+                Ok((RenderedFrame::Buffer(buffer), render_output_result))
             }
+        }
+    }
+}
 
-            buffer
-        };
-        self.renderer.unbind().map_err(DTRError::Rendering)?;
-        Ok((buffer, render_output_result))
+/// Reads the bound framebuffer back into a new buffer of `video_info`'s size and format.
+pub(super) fn download_frame(
+    renderer: &mut GlesRenderer,
+    video_info: &VideoInfo,
+) -> Result<gst::Buffer, FrameError> {
+    let (format, swap_rb) = gl_format(drm_format(video_info.format()).unwrap_or(Fourcc::Abgr8888));
+    let mapping = renderer
+        .copy_framebuffer(Rectangle::from_loc_and_size(
+            (0, 0),
+            (
+                video_info.width() as i32,
+                video_info.height() as i32,
+            ),
+        ), format)
+        .map_err(swap_buffers_error)?;
+    let map = renderer
+        .map_texture(&mapping)
+        .map_err(swap_buffers_error)?;
+
+    let mut buffer = gst::Buffer::with_size(video_info.size()).expect("failed to create buffer");
+    {
+        let buffer = buffer.get_mut().unwrap();
+
+        let mut vframe = gst_video::VideoFrameRef::from_buffer_ref_writable(
+            buffer,
+            video_info,
+        )
+            .unwrap();
+        // the mapping has tightly packed rows of 4 byte pixels, the frame's rows may be padded
+        let row = video_info.width() as usize * 4;
+        let stride = vframe.plane_stride()[0] as usize;
+        let plane_data = vframe.plane_data_mut(0).unwrap();
+        for (dst, src) in plane_data
+            .chunks_mut(stride)
+            .zip(map.chunks_exact(row))
+            .take(video_info.height() as usize)
+        {
+            if swap_rb {
+                copy_swap_rb_2101010(&mut dst[..row], src);
+            } else {
+                dst[..row].copy_from_slice(src);
+            }
+        }
+    }
+
+    Ok(buffer)
+}
+
+/// Composites with pixman straight into the memory of a new buffer of `video_info`'s size and format.
+pub(super) fn pixman_frame(
+    renderer: &mut PixmanRenderer,
+    video_info: &VideoInfo,
+    render: impl FnOnce(&mut PixmanRenderer) -> Result<RenderOutputResult, FrameError>,
+) -> Result<(gst::Buffer, RenderOutputResult), FrameError> {
+    let format = drm_format(video_info.format())
+        .and_then(|format| pixman::FormatCode::try_from(format).ok())
+        .ok_or(FrameError::Render)?;
+    let mut buffer = gst::Buffer::with_size(video_info.size()).expect("failed to create buffer");
+    let render_output_result = {
+        let buffer = buffer.get_mut().unwrap();
+        let mut vframe =
+            gst_video::VideoFrameRef::from_buffer_ref_writable(buffer, video_info)
+                .unwrap();
+        let stride = vframe.plane_stride()[0] as usize;
+        let plane_data = vframe.plane_data_mut(0).unwrap();
+
+        // the image is dropped by `unbind` while the frame is still mapped
+        let image = unsafe {
+            pixman::Image::from_raw_mut(
+                format,
+                video_info.width() as usize,
+                video_info.height() as usize,
+                plane_data.as_mut_ptr() as *mut u32,
+                stride,
+                false,
+            )
+        }
+        .map_err(|_| FrameError::Render)?;
+        renderer.bind(image).map_err(swap_buffers_error)?;
+        let result = render(renderer);
+        renderer.unbind().map_err(swap_buffers_error)?;
+        result?
+    };
+
+    Ok((buffer, render_output_result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::copy_swap_rb_2101010;
+
+    fn pixel(alpha: u32, red: u32, green: u32, blue: u32) -> [u8; 4] {
+        ((alpha << 30) | (red << 20) | (green << 10) | blue).to_le_bytes()
+    }
+
+    #[test]
+    fn swap_red_and_blue_2101010() {
+        let src = [pixel(3, 0x3ff, 0x155, 0x001), pixel(1, 0x000, 0x3ff, 0x2aa)].concat();
+        let mut dst = vec![0; src.len()];
+        copy_swap_rb_2101010(&mut dst, &src);
+        assert_eq!(
+            dst,
+            [pixel(3, 0x001, 0x155, 0x3ff), pixel(1, 0x2aa, 0x3ff, 0x000)].concat()
+        );
+
+        // swapping twice restores the pixels
+        let mut restored = vec![0; dst.len()];
+        copy_swap_rb_2101010(&mut restored, &dst);
+        assert_eq!(restored, src);
     }
 }
@@ -0,0 +1,212 @@
+use smithay::{
+    output::Output,
+    reexports::wayland_protocols::xdg::shell::server::xdg_toplevel::State as XdgState,
+    utils::{Logical, Physical, Point, Rectangle, Size},
+};
+
+use super::{FocusTarget, State, PRIMARY_OUTPUT};
+use crate::FrameCrop;
+
+/// Maps the part of an output shown in the video frames onto them.
+///
+/// Everything is in physical output coordinates in the orientation of the desktop, the space render elements
+/// are placed in. The output transform only gets applied when drawing into the frames.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameView {
+    /// The shown area, relative to the output.
+    pub src: Rectangle<i32, Physical>,
+    /// Scale from the shown area to the frames.
+    pub zoom: f64,
+    /// Where the shown area starts in the frames, it is centered if the aspect ratios differ.
+    pub offset: Point<i32, Physical>,
+}
+
+impl FrameView {
+    fn new(src: Rectangle<i32, Physical>, frame_size: Size<i32, Physical>) -> Self {
+        let zoom = f64::min(
+            frame_size.w as f64 / src.size.w as f64,
+            frame_size.h as f64 / src.size.h as f64,
+        );
+        let offset = (
+            ((frame_size.w as f64 - src.size.w as f64 * zoom) / 2.0).round() as i32,
+            ((frame_size.h as f64 - src.size.h as f64 * zoom) / 2.0).round() as i32,
+        );
+
+        FrameView {
+            src,
+            zoom,
+            offset: offset.into(),
+        }
+    }
+
+    /// Shows `area` of an output, `None` if the frames show all of it as is.
+    ///
+    /// `area` is in logical coordinates relative to the output, `frame_size` is already turned into the orientation
+    /// of the desktop. Areas outside of the output show the whole output.
+    fn for_area(
+        area: Option<Rectangle<i32, Logical>>,
+        output_size: Size<i32, Logical>,
+        scale: f64,
+        frame_size: Size<i32, Physical>,
+    ) -> Option<Self> {
+        let output_area = Rectangle::from_loc_and_size((0, 0), output_size.to_physical_precise_round(scale));
+        let src = area
+            .and_then(|area| area.to_physical_precise_round(scale).intersection(output_area))
+            .filter(|src| !src.is_empty())
+            .unwrap_or(output_area);
+
+        (src != Rectangle::from_loc_and_size((0, 0), frame_size)).then(|| FrameView::new(src, frame_size))
+    }
+
+    /// Maps a point in the frames to the output.
+    pub fn frame_to_output(&self, position: Point<f64, Physical>) -> Point<f64, Physical> {
+        let offset = self.offset.to_f64();
+        let src = self.src.loc.to_f64();
+        (
+            (position.x - offset.x) / self.zoom + src.x,
+            (position.y - offset.y) / self.zoom + src.y,
+        )
+            .into()
+    }
+}
+
+impl State {
+    /// How an output is shown in its frames, `None` if they show it as is.
+    ///
+    /// Only the primary output can be rendered at a different size or cropped.
+    pub fn frame_view(&self, id: u32) -> Option<FrameView> {
+        if id != PRIMARY_OUTPUT {
+            return None;
+        }
+        let virtual_output = self.virtual_output(id)?;
+        let output = &virtual_output.output;
+        let output_geo = self.space.output_geometry(output)?;
+        let scale = output.current_scale().fractional_scale();
+        // the frames in the orientation of the desktop
+        let frame_size = output.current_transform().transform_size(Size::from((
+            virtual_output.video_info.width() as i32,
+            virtual_output.video_info.height() as i32,
+        )));
+
+        if let (FrameCrop::None, Some(window_geo)) = (self.frame_crop, self.letterboxed_window(output)) {
+            // the client may be larger than the output, so it isn't cut off at the output edges
+            let mut src = window_geo.to_physical_precise_round(scale);
+            src.loc -= output_geo.loc.to_physical_precise_round(scale);
+            return Some(FrameView::new(src, frame_size));
+        }
+
+        let area: Option<Rectangle<i32, Logical>> = match self.frame_crop {
+            FrameCrop::None => None,
+            FrameCrop::Region {
+                x,
+                y,
+                width,
+                height,
+            } => Some(Rectangle::from_loc_and_size((x, y), (width, height))),
+            FrameCrop::FocusedWindow => self
+                .seat
+                .get_keyboard()
+                .and_then(|keyboard| keyboard.current_focus())
+                .and_then(|focus| match focus {
+                    FocusTarget::Wayland(window) => self.space.element_geometry(&window),
+                    _ => None,
+                })
+                .map(|mut geometry| {
+                    geometry.loc -= output_geo.loc;
+                    geometry
+                }),
+        };
+        FrameView::for_area(area, output_geo.size, scale, frame_size)
+    }
+
+    /// The geometry of the topmost window on an output, if it is fullscreen but didn't take the size of the output.
+    fn letterboxed_window(&self, output: &Output) -> Option<Rectangle<i32, Logical>> {
+        if !self.letterbox {
+            return None;
+        }
+        let output_geo = self.space.output_geometry(output)?;
+        let window = self.space.elements_for_output(output).last()?;
+        let fullscreen = window
+            .toplevel()
+            .is_some_and(|toplevel| toplevel.current_state().states.contains(XdgState::Fullscreen));
+        let window_geo = self.space.element_geometry(window)?;
+
+        (fullscreen && !window_geo.is_empty() && window_geo.size != output_geo.size).then_some(window_geo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use smithay::utils::{Physical, Point, Rectangle, Size, Transform};
+
+    use super::FrameView;
+
+    #[test]
+    fn whole_output_at_frame_size() {
+        assert_eq!(FrameView::for_area(None, (1920, 1080).into(), 1.0, (1920, 1080).into()), None);
+    }
+
+    #[test]
+    fn render_size_is_scaled_down() {
+        let view = FrameView::for_area(None, (3840, 2160).into(), 1.0, (1920, 1080).into()).unwrap();
+        assert_eq!(view.src, Rectangle::from_loc_and_size((0, 0), (3840, 2160)));
+        assert_eq!(view.zoom, 0.5);
+        assert_eq!(view.offset, Point::from((0, 0)));
+        assert_eq!(
+            view.frame_to_output((960.0, 540.0).into()),
+            Point::<f64, Physical>::from((1920.0, 1080.0))
+        );
+    }
+
+    #[test]
+    fn crop_is_centered() {
+        let area = Rectangle::from_loc_and_size((100, 50), (400, 400));
+        let view = FrameView::for_area(Some(area), (1920, 1080).into(), 1.0, (1920, 1080).into()).unwrap();
+        assert_eq!(view.zoom, 2.7);
+        assert_eq!(view.offset, Point::from((420, 0)));
+        assert_eq!(
+            view.frame_to_output((420.0, 0.0).into()),
+            Point::<f64, Physical>::from((100.0, 50.0))
+        );
+    }
+
+    #[test]
+    fn crop_with_output_scale() {
+        let area = Rectangle::from_loc_and_size((0, 0), (640, 360));
+        let view = FrameView::for_area(Some(area), (1280, 720).into(), 1.5, (1920, 1080).into()).unwrap();
+        assert_eq!(view.src, Rectangle::from_loc_and_size((0, 0), (960, 540)));
+        assert_eq!(view.zoom, 2.0);
+    }
+
+    #[test]
+    fn crop_is_clipped_to_the_output() {
+        let outside = Rectangle::from_loc_and_size((4000, 4000), (100, 100));
+        assert_eq!(FrameView::for_area(Some(outside), (1920, 1080).into(), 1.0, (1920, 1080).into()), None);
+
+        let partial = Rectangle::from_loc_and_size((1820, 980), (200, 200));
+        let view = FrameView::for_area(Some(partial), (1920, 1080).into(), 1.0, (1920, 1080).into()).unwrap();
+        assert_eq!(view.src, Rectangle::from_loc_and_size((1820, 980), (100, 100)));
+    }
+
+    #[test]
+    fn crop_with_rotated_output() {
+        // 1920x1080 frames of an output turned by 90° show a 1080x1920 desktop
+        let frame_size = Transform::_90.transform_size(Size::<i32, Physical>::from((1920, 1080)));
+        assert_eq!(frame_size, Size::from((1080, 1920)));
+
+        // the crop is given in the orientation of the desktop, as is the view
+        let area = Rectangle::from_loc_and_size((0, 960), (540, 960));
+        let view = FrameView::for_area(Some(area), (1080, 1920).into(), 1.0, frame_size).unwrap();
+        assert_eq!(view.src, Rectangle::from_loc_and_size((0, 960), (540, 960)));
+        assert_eq!(view.zoom, 2.0);
+        assert_eq!(view.offset, Point::from((0, 0)));
+        assert_eq!(
+            view.frame_to_output((1080.0, 1920.0).into()),
+            Point::<f64, Physical>::from((540.0, 1920.0))
+        );
+
+        // the same crop on an unrotated output is cut off at its bottom edge
+        let unrotated = FrameView::for_area(Some(area), (1920, 1080).into(), 1.0, (1920, 1080).into()).unwrap();
+        assert_eq!(unrotated.src, Rectangle::from_loc_and_size((0, 960), (540, 120)));
+    }
+}
@@ -0,0 +1,106 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use smithay::{
+    desktop::Window,
+    reexports::wayland_server::{backend::DisconnectReason, Resource},
+    utils::SERIAL_COUNTER,
+    wayland::{compositor::with_states, shell::xdg::XdgToplevelSurfaceData},
+};
+
+use super::{ClientState, FocusTarget, State};
+use crate::{DisplayEvent, WindowAction, WindowInfo};
+
+static NEXT_WINDOW_ID: AtomicU64 = AtomicU64::new(1);
+
+struct WindowId(u64);
+
+/// Returns the stable id of a window, ids are never reused and start at 1.
+pub fn window_id(window: &Window) -> u64 {
+    let user_data = window.user_data();
+    user_data.insert_if_missing(|| WindowId(NEXT_WINDOW_ID.fetch_add(1, Ordering::SeqCst)));
+    user_data.get::<WindowId>().unwrap().0
+}
+
+impl State {
+    pub fn emit(&self, event: DisplayEvent) {
+        if let Some(callback) = self.event_callback.as_ref() {
+            callback(event);
+        }
+    }
+
+    pub fn window_info(&self, window: &Window) -> WindowInfo {
+        let toplevel = window.toplevel().unwrap();
+        let (title, app_id) = with_states(toplevel.wl_surface(), |states| {
+            states
+                .data_map
+                .get::<XdgToplevelSurfaceData>()
+                .map(|attrs| {
+                    let attrs = attrs.lock().unwrap();
+                    (attrs.title.clone(), attrs.app_id.clone())
+                })
+                .unwrap_or_default()
+        });
+        let client = toplevel.wl_surface().client();
+        let client_id = client
+            .as_ref()
+            .and_then(|client| client.get_data::<ClientState>())
+            .map(|data| data.id);
+        let pid = client
+            .and_then(|client| client.get_credentials(&self.dh).ok())
+            .map(|credentials| credentials.pid);
+        let geometry = self.space.element_geometry(window).unwrap_or_default();
+
+        WindowInfo {
+            id: window_id(window),
+            client_id,
+            title,
+            app_id,
+            pid,
+            x: geometry.loc.x,
+            y: geometry.loc.y,
+            width: geometry.size.w,
+            height: geometry.size.h,
+        }
+    }
+
+    pub fn windows(&self) -> Vec<WindowInfo> {
+        self.space
+            .elements()
+            .map(|window| self.window_info(window))
+            .collect()
+    }
+
+    pub fn find_window(&self, id: u64) -> Option<Window> {
+        self.space
+            .elements()
+            .find(|window| window_id(window) == id)
+            .cloned()
+    }
+
+    pub fn window_action(&mut self, id: u64, action: WindowAction) {
+        let Some(window) = self.find_window(id) else {
+            tracing::warn!(id, ?action, "No such window.");
+            return;
+        };
+
+        match action {
+            WindowAction::Focus => {
+                self.space.raise_element(&window, true);
+                self.seat.get_keyboard().unwrap().set_focus(
+                    self,
+                    Some(FocusTarget::from(window)),
+                    SERIAL_COUNTER.next_serial(),
+                );
+            }
+            WindowAction::Raise => self.space.raise_element(&window, false),
+            WindowAction::Close => window.toplevel().unwrap().send_close(),
+            WindowAction::Kill => {
+                if let Some(client) = window.toplevel().unwrap().wl_surface().client() {
+                    self.dh
+                        .backend_handle()
+                        .kill_client(client.id(), DisconnectReason::ConnectionClosed);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::fmt;
+
+use smithay::{
+    backend::{
+        drm::CreateDrmNodeError,
+        egl::Error as EGLError,
+        renderer::{gles::GlesError, pixman::PixmanError},
+    },
+    input::keyboard::Error as KeyboardError,
+    reexports::{
+        calloop::Error as EventLoopError,
+        wayland_server::{backend::InitError, BindError},
+    },
+};
+
+/// Errors preventing the creation of a [`crate::WaylandDisplay`].
+#[derive(Debug)]
+pub enum DisplayError {
+    /// The requested render node couldn't be opened.
+    RenderNode(CreateDrmNodeError),
+    /// No EGL device matches the requested render node (or no software device is available).
+    NoEglDevice,
+    /// None of the render targets an `auto` target picked could be initialized.
+    NoRenderTarget {
+        /// The targets in the order they were tried.
+        tried: Vec<String>,
+        /// Why the last one failed.
+        error: Box<DisplayError>,
+    },
+    /// Creating the EGL display or context failed.
+    Egl(EGLError),
+    /// The renderer couldn't be initialized on the EGL context.
+    Renderer(GlesError),
+    /// The pixman software renderer couldn't be initialized.
+    Pixman(PixmanError),
+    /// The wayland display couldn't be created.
+    Wayland(InitError),
+    /// The wayland socket couldn't be created.
+    Socket(BindError),
+    /// The keyboard (and its keymap) couldn't be set up.
+    Keyboard(KeyboardError),
+    /// The compositor event loop couldn't be set up.
+    EventLoop(EventLoopError),
+    /// The compositor thread died before it finished initializing.
+    CompositorDied,
+}
+
+impl fmt::Display for DisplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisplayError::RenderNode(err) => write!(f, "Failed to open render node: {}", err),
+            DisplayError::NoEglDevice => write!(f, "Unable to find an EGL device for the render node"),
+            DisplayError::NoRenderTarget { tried, error } => {
+                write!(f, "No usable render target, tried {}: {}", tried.join(", "), error)
+            }
+            DisplayError::Egl(err) => write!(f, "Failed to initialize EGL: {}", err),
+            DisplayError::Renderer(err) => write!(f, "Failed to initialize renderer: {}", err),
+            DisplayError::Pixman(err) => write!(f, "Failed to initialize pixman renderer: {}", err),
+            DisplayError::Wayland(err) => write!(f, "Failed to create wayland display: {}", err),
+            DisplayError::Socket(err) => write!(f, "Failed to create wayland socket: {}", err),
+            DisplayError::Keyboard(err) => write!(f, "Failed to set up keyboard: {}", err),
+            DisplayError::EventLoop(err) => write!(f, "Failed to set up event loop: {}", err),
+            DisplayError::CompositorDied => write!(f, "Compositor thread died during initialization"),
+        }
+    }
+}
+
+impl std::error::Error for DisplayError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DisplayError::RenderNode(err) => Some(err),
+            DisplayError::Egl(err) => Some(err),
+            DisplayError::Renderer(err) => Some(err),
+            DisplayError::Pixman(err) => Some(err),
+            DisplayError::Wayland(err) => Some(err),
+            DisplayError::Socket(err) => Some(err),
+            DisplayError::Keyboard(err) => Some(err),
+            DisplayError::EventLoop(err) => Some(err),
+            DisplayError::NoRenderTarget { error, .. } => Some(error.as_ref()),
+            DisplayError::NoEglDevice | DisplayError::CompositorDied => None,
+        }
+    }
+}
+
+impl From<CreateDrmNodeError> for DisplayError {
+    fn from(err: CreateDrmNodeError) -> Self {
+        DisplayError::RenderNode(err)
+    }
+}
+
+impl From<EGLError> for DisplayError {
+    fn from(err: EGLError) -> Self {
+        DisplayError::Egl(err)
+    }
+}
+
+impl From<GlesError> for DisplayError {
+    fn from(err: GlesError) -> Self {
+        DisplayError::Renderer(err)
+    }
+}
+
+impl From<PixmanError> for DisplayError {
+    fn from(err: PixmanError) -> Self {
+        DisplayError::Pixman(err)
+    }
+}
+
+impl From<InitError> for DisplayError {
+    fn from(err: InitError) -> Self {
+        DisplayError::Wayland(err)
+    }
+}
+
+impl From<BindError> for DisplayError {
+    fn from(err: BindError) -> Self {
+        DisplayError::Socket(err)
+    }
+}
+
+impl From<KeyboardError> for DisplayError {
+    fn from(err: KeyboardError) -> Self {
+        DisplayError::Keyboard(err)
+    }
+}
+
+impl From<EventLoopError> for DisplayError {
+    fn from(err: EventLoopError) -> Self {
+        DisplayError::EventLoop(err)
+    }
+}
+
+/// Errors returned by [`crate::WaylandDisplay::frame`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameError {
+    /// The stream ended, e.g. because the last client disconnected.
+    Eos,
+    /// Rendering this frame failed, the next one might succeed.
+    Render,
+    /// The rendering context was lost.
+    ContextLost,
+    /// The compositor thread is gone, the display has to be recreated.
+    CompositorDied,
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::Eos => write!(f, "End of stream"),
+            FrameError::Render => write!(f, "Rendering failed"),
+            FrameError::ContextLost => write!(f, "Rendering context lost"),
+            FrameError::CompositorDied => write!(f, "Compositor thread died"),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+impl From<FrameError> for gst::FlowError {
+    fn from(err: FrameError) -> Self {
+        match err {
+            FrameError::Eos | FrameError::ContextLost => gst::FlowError::Eos,
+            FrameError::Render | FrameError::CompositorDied => gst::FlowError::Error,
+        }
+    }
+}
+
+/// A mode that isn't in the `WIDTHxHEIGHT@REFRESH` format, see [`crate::DisplayMode`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidModeError(pub String);
+
+impl fmt::Display for InvalidModeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid mode {:?}, expected WIDTHxHEIGHT@REFRESH", self.0)
+    }
+}
+
+impl std::error::Error for InvalidModeError {}
+
+/// A crop that isn't `none`, `focused-window` or in the `WIDTHxHEIGHT+X+Y` format, see [`crate::FrameCrop`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidCropError(pub String);
+
+impl fmt::Display for InvalidCropError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid crop {:?}, expected none, focused-window or WIDTHxHEIGHT+X+Y", self.0)
+    }
+}
+
+impl std::error::Error for InvalidCropError {}
+
+/// An image that couldn't be loaded, see [`crate::RgbaImage::load`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageError(pub String);
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to load image: {}", self.0)
+    }
+}
+
+impl std::error::Error for ImageError {}
@@ -0,0 +1,219 @@
+use gst_video::{
+    VideoContentLightLevel, VideoMasteringDisplayInfo, VideoMasteringDisplayInfoCoordinates,
+};
+
+use crate::DisplayMode;
+
+/// Geometry and metadata of a toplevel window inside the compositor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowInfo {
+    pub id: u64,
+    /// Id of the owning client, as reported by [`DisplayEvent::ClientConnected`].
+    pub client_id: Option<u64>,
+    pub title: Option<String>,
+    pub app_id: Option<String>,
+    pub pid: Option<i32>,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl WindowInfo {
+    pub fn to_structure(&self) -> gst::Structure {
+        gst::Structure::builder("wayland.window")
+            .field("id", self.id)
+            .field("client-id", self.client_id.unwrap_or(0))
+            .field("title", self.title.as_deref().unwrap_or_default())
+            .field("app-id", self.app_id.as_deref().unwrap_or_default())
+            .field("pid", self.pid.unwrap_or(-1))
+            .field("x", self.x)
+            .field("y", self.y)
+            .field("width", self.width)
+            .field("height", self.height)
+            .build()
+    }
+}
+
+/// HDR metadata of the composited content of an output, taken from the image description
+/// (`wp_color_management_surface_v1`) of the topmost window on it carrying any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HdrMetadata {
+    /// Red, green and blue primaries and the white point of the mastering display, as CIE 1931 xy coordinates.
+    pub mastering_primaries: Option<[(f32, f32); 4]>,
+    /// Minimum and maximum luminance of the mastering display in cd/m².
+    pub mastering_luminance: Option<(f32, f32)>,
+    /// Maximum content light level in cd/m².
+    pub max_cll: Option<u32>,
+    /// Maximum frame-average light level in cd/m².
+    pub max_fall: Option<u32>,
+}
+
+impl HdrMetadata {
+    /// The mastering display info for the caps, if both primaries and luminance are known.
+    pub fn mastering_display_info(&self) -> Option<VideoMasteringDisplayInfo> {
+        let [red, green, blue, white] = self.mastering_primaries?;
+        let (min, max) = self.mastering_luminance?;
+        let coordinates = |(x, y)| VideoMasteringDisplayInfoCoordinates::new(x, y);
+        // in units of 0.0001 cd/m²
+        Some(VideoMasteringDisplayInfo::new(
+            [coordinates(red), coordinates(green), coordinates(blue)],
+            coordinates(white),
+            (max * 10000.) as u32,
+            (min * 10000.) as u32,
+        ))
+    }
+
+    /// The content light level for the caps, unknown values are 0.
+    pub fn content_light_level(&self) -> Option<VideoContentLightLevel> {
+        if self.max_cll.is_none() && self.max_fall.is_none() {
+            return None;
+        }
+        Some(VideoContentLightLevel::new(
+            self.max_cll.unwrap_or(0).min(u16::MAX as u32) as u16,
+            self.max_fall.unwrap_or(0).min(u16::MAX as u32) as u16,
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowAction {
+    Focus,
+    Raise,
+    Close,
+    Kill,
+}
+
+/// Notifications emitted by the compositor thread.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DisplayEvent {
+    WindowCreated(WindowInfo),
+    WindowDestroyed(u64),
+    WindowTitleChanged(u64, String),
+    WindowFocusChanged(Option<u64>),
+    ClientConnected {
+        id: u64,
+        pid: Option<i32>,
+        uid: Option<u32>,
+        restricted: bool,
+        /// Set for clients connecting through a `wp_security_context_v1`.
+        sandbox_engine: Option<String>,
+        app_id: Option<String>,
+    },
+    ClientDisconnected { id: u64, pid: Option<i32>, uid: Option<u32>, reason: String },
+    /// A client was disconnected for going over one of its [`crate::ClientLimits`].
+    QuotaExceeded { client_id: u64, quota: &'static str, limit: u64, value: u64 },
+    /// The rendering context got lost (e.g. by a GPU reset), `render_node` is the target in use afterwards.
+    ContextLost { recovered: bool, render_node: String },
+    /// The HDR metadata of the content of an output changed, `None` if there is none (anymore).
+    HdrMetadataChanged { output: u32, metadata: Option<HdrMetadata> },
+    /// A client asked to switch an output to one of the modes passed to [`crate::WaylandDisplay::set_output_modes`].
+    ModeRequested { output: u32, mode: DisplayMode },
+    /// A process started by [`crate::WaylandDisplay::spawn`] exited, `exit_code` is `None` if it was killed by a signal.
+    ProcessExited { pid: u32, exit_code: Option<i32> },
+}
+
+impl DisplayEvent {
+    /// Serializes the event, e.g. to be posted as an element message on the bus.
+    pub fn to_structure(&self) -> gst::Structure {
+        match self {
+            DisplayEvent::WindowCreated(info) => {
+                let mut structure = info.to_structure();
+                structure.set_name("wayland.window-created");
+                structure
+            }
+            DisplayEvent::WindowDestroyed(id) => gst::Structure::builder("wayland.window-destroyed")
+                .field("id", id)
+                .build(),
+            DisplayEvent::WindowTitleChanged(id, title) => {
+                gst::Structure::builder("wayland.window-title-changed")
+                    .field("id", id)
+                    .field("title", title)
+                    .build()
+            }
+            DisplayEvent::WindowFocusChanged(id) => {
+                gst::Structure::builder("wayland.window-focus-changed")
+                    .field("id", id.unwrap_or(0))
+                    .build()
+            }
+            DisplayEvent::ClientConnected {
+                id,
+                pid,
+                uid,
+                restricted,
+                sandbox_engine,
+                app_id,
+            } => {
+                let mut structure = gst::Structure::builder("wayland.client-connected")
+                    .field("id", id)
+                    .field("restricted", restricted)
+                    .build();
+                set_credentials(&mut structure, *pid, *uid);
+                if let Some(sandbox_engine) = sandbox_engine {
+                    structure.set("sandbox-engine", sandbox_engine);
+                }
+                if let Some(app_id) = app_id {
+                    structure.set("app-id", app_id);
+                }
+                structure
+            }
+            DisplayEvent::ClientDisconnected { id, pid, uid, reason } => {
+                let mut structure = gst::Structure::builder("wayland.client-disconnected")
+                    .field("id", id)
+                    .field("reason", reason)
+                    .build();
+                set_credentials(&mut structure, *pid, *uid);
+                structure
+            }
+            DisplayEvent::QuotaExceeded { client_id, quota, limit, value } => {
+                gst::Structure::builder("wayland.quota-exceeded")
+                    .field("client-id", client_id)
+                    .field("quota", quota)
+                    .field("limit", limit)
+                    .field("value", value)
+                    .build()
+            }
+            DisplayEvent::ContextLost { recovered, render_node } => {
+                gst::Structure::builder("wayland.context-lost")
+                    .field("recovered", recovered)
+                    .field("render-node", render_node)
+                    .build()
+            }
+            DisplayEvent::HdrMetadataChanged { output, metadata } => {
+                let mut structure = gst::Structure::builder("wayland.hdr-metadata-changed")
+                    .field("output", output)
+                    .build();
+                if let Some(info) = metadata.as_ref().and_then(HdrMetadata::mastering_display_info) {
+                    structure.set("mastering-display-info", info.to_string());
+                }
+                if let Some(level) = metadata.as_ref().and_then(HdrMetadata::content_light_level) {
+                    structure.set("content-light-level", level.to_string());
+                }
+                structure
+            }
+            DisplayEvent::ModeRequested { output, mode } => gst::Structure::builder("wayland.mode-requested")
+                .field("output", output)
+                .field("width", mode.width)
+                .field("height", mode.height)
+                .field("framerate", mode.framerate())
+                .build(),
+            DisplayEvent::ProcessExited { pid, exit_code } => {
+                gst::Structure::builder("wayland.process-exited")
+                    .field("pid", pid)
+                    .field("exit-code", exit_code.unwrap_or(-1))
+                    .build()
+            }
+        }
+    }
+}
+
+fn set_credentials(structure: &mut gst::Structure, pid: Option<i32>, uid: Option<u32>) {
+    if let Some(pid) = pid {
+        structure.set("pid", pid);
+    }
+    if let Some(uid) = uid {
+        structure.set("uid", uid);
+    }
+}
+
+pub type EventCallback = Box<dyn Fn(DisplayEvent) + Send>;
@@ -0,0 +1,119 @@
+use std::sync::mpsc;
+
+use smithay::reexports::calloop::channel::Sender;
+
+use crate::{
+    comp, Command, DisplayMode, FrameCrop, OutputTransform, Overlays, RgbaImage, WallpaperMode, WindowAction,
+    WindowInfo, MAX_OUTPUT_SCALE, MIN_OUTPUT_SCALE,
+};
+
+/// Changes a [`crate::WaylandDisplay`] from anywhere, e.g. another thread or an event callback,
+/// without access to the display itself. Commands are queued for the compositor and take effect
+/// with the next frame, they are ignored once the display got dropped.
+///
+/// Apart from [`DisplayHandle::windows`], nothing waits for the compositor.
+#[derive(Clone)]
+pub struct DisplayHandle {
+    command_tx: Sender<Command>,
+}
+
+impl DisplayHandle {
+    pub(crate) fn new(command_tx: Sender<Command>) -> Self {
+        DisplayHandle { command_tx }
+    }
+
+    /// See [`crate::WaylandDisplay::set_output_scale`].
+    pub fn set_output_scale(&self, scale: f64) {
+        if !scale.is_finite() {
+            tracing::warn!(scale, "Ignoring invalid output scale.");
+            return;
+        }
+        let clamped = scale.clamp(MIN_OUTPUT_SCALE, MAX_OUTPUT_SCALE);
+        if clamped != scale {
+            tracing::warn!(scale, clamped, "Output scale out of range, clamping.");
+        }
+        let _ = self.command_tx.send(Command::OutputScale(clamped));
+    }
+
+    /// See [`crate::WaylandDisplay::set_output_transform`].
+    pub fn set_output_transform(&self, transform: OutputTransform) {
+        let _ = self.command_tx.send(Command::OutputTransform(transform));
+    }
+
+    /// See [`crate::WaylandDisplay::set_render_size`].
+    pub fn set_render_size(&self, size: Option<(i32, i32)>) {
+        if let Some((width, height)) = size {
+            if width <= 0 || height <= 0 {
+                tracing::warn!(width, height, "Ignoring invalid render size.");
+                return;
+            }
+        }
+        let _ = self.command_tx.send(Command::RenderSize(size));
+    }
+
+    /// See [`crate::WaylandDisplay::set_frame_crop`].
+    pub fn set_frame_crop(&self, crop: FrameCrop) {
+        let _ = self.command_tx.send(Command::FrameCrop(crop));
+    }
+
+    /// See [`crate::WaylandDisplay::set_letterbox`].
+    pub fn set_letterbox(&self, enabled: bool) {
+        let _ = self.command_tx.send(Command::Letterbox(enabled));
+    }
+
+    /// See [`crate::WaylandDisplay::set_background_color`].
+    pub fn set_background_color(&self, color: [f32; 4]) {
+        let _ = self.command_tx.send(Command::BackgroundColor(color));
+    }
+
+    /// See [`crate::WaylandDisplay::set_wallpaper`].
+    pub fn set_wallpaper(&self, image: Option<RgbaImage>) {
+        let _ = self.command_tx.send(Command::Wallpaper(image));
+    }
+
+    pub fn set_wallpaper_mode(&self, mode: WallpaperMode) {
+        let _ = self.command_tx.send(Command::WallpaperMode(mode));
+    }
+
+    /// See [`crate::WaylandDisplay::set_modes`].
+    pub fn set_modes(&self, modes: Vec<DisplayMode>) {
+        self.set_output_modes(comp::PRIMARY_OUTPUT, modes);
+    }
+
+    /// See [`crate::WaylandDisplay::set_output_modes`].
+    pub fn set_output_modes(&self, output: u32, modes: Vec<DisplayMode>) {
+        let _ = self.command_tx.send(Command::OutputModes(output, modes));
+    }
+
+    /// Changes the overlays, see [`Overlays`].
+    pub fn overlays(&self) -> Overlays {
+        Overlays::new(self.command_tx.clone())
+    }
+
+    /// Lists the toplevel windows. This waits for the compositor's answer, so it must not be called
+    /// on the compositor thread, e.g. from an event callback.
+    pub fn windows(&self) -> Vec<WindowInfo> {
+        let (windows_tx, windows_rx) = mpsc::sync_channel(1);
+        if let Err(err) = self.command_tx.send(Command::ListWindows(windows_tx)) {
+            tracing::warn!(?err, "Failed to send list windows command.");
+            return Vec::new();
+        }
+        windows_rx.recv().unwrap_or_default()
+    }
+
+    pub fn focus_window(&self, id: u64) {
+        let _ = self.command_tx.send(Command::WindowAction(id, WindowAction::Focus));
+    }
+
+    pub fn raise_window(&self, id: u64) {
+        let _ = self.command_tx.send(Command::WindowAction(id, WindowAction::Raise));
+    }
+
+    pub fn close_window(&self, id: u64) {
+        let _ = self.command_tx.send(Command::WindowAction(id, WindowAction::Close));
+    }
+
+    pub fn kill_window(&self, id: u64) {
+        let _ = self.command_tx.send(Command::WindowAction(id, WindowAction::Kill));
+    }
+}
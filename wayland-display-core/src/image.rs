@@ -0,0 +1,214 @@
+use std::{fmt, path::Path};
+
+use gst::prelude::*;
+use gst_video::{VideoFrameRef, VideoInfo};
+#[cfg(feature = "text")]
+use pangocairo::{cairo, pango};
+
+use crate::ImageError;
+
+/// Upper bound for either side of rendered text.
+#[cfg(feature = "text")]
+const MAX_TEXT_SIZE: u32 = 4096;
+
+/// An image with 4 bytes per pixel in RGBA order, rows without padding.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    width: i32,
+    height: i32,
+    data: Vec<u8>,
+}
+
+impl fmt::Debug for RgbaImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RgbaImage")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RgbaImage {
+    /// Wraps RGBA pixels, `None` if `data` doesn't have the size of a `width`x`height` image.
+    pub fn new(width: i32, height: i32, data: Vec<u8>) -> Option<Self> {
+        (width > 0 && height > 0 && data.len() == width as usize * height as usize * 4).then_some(RgbaImage {
+            width,
+            height,
+            data,
+        })
+    }
+
+    /// Decodes an image file, e.g. a PNG or JPEG, with the installed GStreamer plugins.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ImageError> {
+        let error = |err: &dyn fmt::Display| ImageError(err.to_string());
+        gst::init().map_err(|err| error(&err))?;
+
+        let pipeline = gst::parse_launch(
+            "filesrc name=src ! decodebin ! videoconvert ! video/x-raw,format=RGBA ! \
+             fakesink name=sink enable-last-sample=true sync=false",
+        )
+        .map_err(|err| error(&err))?
+        .downcast::<gst::Pipeline>()
+        .map_err(|_| error(&"not a pipeline"))?;
+        pipeline
+            .by_name("src")
+            .unwrap()
+            .set_property("location", path.as_ref().to_string_lossy().as_ref());
+
+        let decoded = decode(&pipeline);
+        let _ = pipeline.set_state(gst::State::Null);
+        let sample = decoded.map_err(|err| error(&err))?;
+
+        Self::from_sample(&sample).map_err(|err| error(&err))
+    }
+
+    /// Renders a line or more of plain text in a single color on a transparent background with pango.
+    ///
+    /// `font` is a pango font description without size (e.g. `Sans Bold`), `size` the font size in
+    /// pixels and `color` is RGBA from 0.0 to 1.0. The image is cut to the drawn text.
+    ///
+    /// Only available with the `text` feature.
+    #[cfg(feature = "text")]
+    pub fn from_text(text: &str, font: &str, size: u32, color: [f32; 4]) -> Result<Self, ImageError> {
+        let error = |err: &dyn fmt::Display| ImageError(err.to_string());
+        if text.trim().is_empty() || size == 0 || size > MAX_TEXT_SIZE {
+            return Err(error(&"no text"));
+        }
+
+        let mut description = pango::FontDescription::from_string(font);
+        description.set_absolute_size(size as f64 * pango::SCALE as f64);
+        let layout = |cr: &cairo::Context| {
+            let layout = pangocairo::functions::create_layout(cr);
+            layout.set_font_description(Some(&description));
+            layout.set_text(text);
+            layout
+        };
+
+        // measure on a scratch surface, the ink rectangle is what actually gets drawn
+        let scratch = cairo::ImageSurface::create(cairo::Format::ARgb32, 1, 1).map_err(|err| error(&err))?;
+        let (ink, _) = layout(&cairo::Context::new(&scratch).map_err(|err| error(&err))?).pixel_extents();
+        if ink.width() <= 0 || ink.height() <= 0 {
+            return Err(error(&"no text drawn"));
+        }
+        let width = ink.width().min(MAX_TEXT_SIZE as i32);
+        let height = ink.height().min(MAX_TEXT_SIZE as i32);
+
+        // white text, the alpha of the premultiplied pixels is the coverage
+        let mut surface =
+            cairo::ImageSurface::create(cairo::Format::ARgb32, width, height).map_err(|err| error(&err))?;
+        {
+            let cr = cairo::Context::new(&surface).map_err(|err| error(&err))?;
+            let layout = layout(&cr);
+            cr.translate(-ink.x() as f64, -ink.y() as f64);
+            cr.set_source_rgba(1.0, 1.0, 1.0, 1.0);
+            pangocairo::functions::update_layout(&cr, &layout);
+            pangocairo::functions::show_layout(&cr, &layout);
+        }
+        surface.flush();
+
+        let stride = surface.stride() as usize;
+        let [r, g, b, a] = color.map(|channel| channel.clamp(0.0, 1.0));
+        let rgb = [r, g, b].map(|channel| (channel * 255.0).round() as u8);
+        let pixels = surface.data().map_err(|err| error(&err))?;
+        let data = pixels
+            .chunks(stride)
+            .take(height as usize)
+            .flat_map(|line| line[..width as usize * 4].chunks_exact(4))
+            .flat_map(|pixel| {
+                let coverage = u32::from_ne_bytes(pixel.try_into().unwrap()) >> 24;
+                [rgb[0], rgb[1], rgb[2], (coverage as f32 * a).round() as u8]
+            })
+            .collect();
+
+        Ok(RgbaImage { width, height, data })
+    }
+
+    /// Copies the pixels of an RGBA sample, dropping the row padding.
+    fn from_sample(sample: &gst::Sample) -> Result<Self, String> {
+        let buffer = sample.buffer().ok_or("no image data")?;
+        let caps = sample.caps().ok_or("no image format")?;
+        let info = VideoInfo::from_caps(caps).map_err(|err| err.to_string())?;
+        let frame = VideoFrameRef::from_buffer_ref_readable(buffer, &info).map_err(|err| err.to_string())?;
+
+        let row = info.width() as usize * 4;
+        let stride = frame.plane_stride()[0] as usize;
+        let plane = frame.plane_data(0).map_err(|err| err.to_string())?;
+        let data = plane
+            .chunks(stride)
+            .take(info.height() as usize)
+            .flat_map(|line| &line[..row])
+            .copied()
+            .collect();
+
+        Ok(RgbaImage {
+            width: info.width() as i32,
+            height: info.height() as i32,
+            data,
+        })
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The pixels with the color channels multiplied by alpha, as the renderers expect them.
+    pub(crate) fn premultiplied_data(&self) -> Vec<u8> {
+        self.data
+            .chunks_exact(4)
+            .flat_map(|pixel| {
+                let alpha = pixel[3] as u32;
+                let premultiply = |channel: u8| ((channel as u32 * alpha + 127) / 255) as u8;
+                [premultiply(pixel[0]), premultiply(pixel[1]), premultiply(pixel[2]), pixel[3]]
+            })
+            .collect()
+    }
+}
+
+/// Runs the pipeline until the image was decoded.
+fn decode(pipeline: &gst::Pipeline) -> Result<gst::Sample, String> {
+    pipeline.set_state(gst::State::Playing).map_err(|err| err.to_string())?;
+
+    let bus = pipeline.bus().unwrap();
+    let msg = bus.timed_pop_filtered(
+        gst::ClockTime::from_seconds(10),
+        &[gst::MessageType::Eos, gst::MessageType::Error],
+    );
+    match msg.as_ref().map(|msg| msg.view()) {
+        Some(gst::MessageView::Eos(_)) => {}
+        Some(gst::MessageView::Error(err)) => return Err(err.error().to_string()),
+        _ => return Err(String::from("decoding timed out")),
+    }
+
+    pipeline
+        .by_name("sink")
+        .unwrap()
+        .property::<Option<gst::Sample>>("last-sample")
+        .ok_or_else(|| String::from("no image decoded"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RgbaImage;
+
+    #[test]
+    fn premultiply_alpha() {
+        let image = RgbaImage::new(
+            3,
+            1,
+            vec![255, 128, 0, 255, 255, 128, 0, 128, 255, 128, 0, 0],
+        )
+        .unwrap();
+        assert_eq!(
+            image.premultiplied_data(),
+            [255, 128, 0, 255, 128, 64, 0, 128, 0, 0, 0, 0]
+        );
+    }
+}
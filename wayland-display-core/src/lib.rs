@@ -1,33 +1,102 @@
 use gst_video::VideoInfo;
 
-use smithay::backend::drm::CreateDrmNodeError;
-use smithay::backend::SwapBuffersError;
 use smithay::reexports::calloop::channel::Sender;
 
 use std::ffi::{c_char, c_void, CString};
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::{io::OwnedFd, net::UnixStream};
+use std::process::{Child, Stdio};
 use std::str::FromStr;
 use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
 use smithay::backend::input::{ButtonState, KeyState};
-use smithay::utils::{Logical, Point};
+use smithay::utils::{Logical, Physical, Point};
 use utils::RenderTarget;
 
 pub(crate) mod comp;
+mod error;
+mod events;
+mod handle;
+mod image;
+mod limits;
+mod output;
+mod overlay;
 pub(crate) mod utils;
 pub(crate) mod wayland;
 
+pub use error::*;
+pub use events::*;
+pub use handle::*;
+pub use image::*;
+pub use limits::*;
+pub use output::*;
+pub use overlay::*;
+pub use comp::PRIMARY_OUTPUT;
+pub use utils::WaylandSocket;
+
+/// Smallest output scale, smaller ones are clamped.
+pub const MIN_OUTPUT_SCALE: f64 = 0.25;
+/// Largest output scale, larger ones are clamped.
+pub const MAX_OUTPUT_SCALE: f64 = 8.0;
+
 pub(crate) enum Command {
     InputDevice(String),
-    VideoInfo(VideoInfo),
-    Buffer(SyncSender<Result<gst::Buffer, SwapBuffersError>>, Option<Tracer>),
+    VideoInfo(u32, VideoInfo),
+    Buffer(u32, comp::FrameTarget, SyncSender<Result<comp::RenderedFrame, FrameError>>, Option<Tracer>),
+    WindowFrame(u64, SyncSender<Result<(gst::Buffer, VideoInfo), FrameError>>),
+    GlHandles(SyncSender<Option<GlHandles>>),
     KeyboardInput(u32, KeyState),
     PointerMotion(Point<f64, Logical>),
-    PointerMotionAbsolute(Point<f64, Logical>),
+    PointerMotionAbsolute(u32, Point<f64, Physical>),
     PointerButton(u32, ButtonState),
     PointerAxis(f64, f64),
+    EventCallback(EventCallback),
+    EosOnLastClient(bool),
+    ClientLimits(ClientLimits),
+    SoftwareFallback(bool),
+    OutputScale(f64),
+    OutputTransform(OutputTransform),
+    RenderSize(Option<(i32, i32)>),
+    FrameCrop(FrameCrop),
+    Letterbox(bool),
+    BackgroundColor([f32; 4]),
+    Wallpaper(Option<RgbaImage>),
+    WallpaperMode(WallpaperMode),
+    AddOverlay(u64, Overlay),
+    UpdateOverlay(u64, Overlay),
+    RemoveOverlay(u64),
+    OutputModes(u32, Vec<DisplayMode>),
+    OutputInfo(OutputInfo),
+    AddOutput(OutputInfo, SyncSender<u32>),
+    RemoveOutput(u32),
+    MoveWindow(u64, u32),
+    ListWindows(SyncSender<Vec<WindowInfo>>),
+    WindowAction(u64, WindowAction),
+    ChildSpawned(Children),
+    InsertClient(UnixStream, bool, Option<GlobalFilter>, SyncSender<std::io::Result<u64>>),
+    GlobalFilter(bool, GlobalFilter),
     Quit,
 }
 
+/// Raw EGL handles of the compositor's GL renderer, to create contexts sharing its objects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlHandles {
+    /// `EGLDisplay`
+    pub egl_display: usize,
+    /// `EGLContext`
+    pub egl_context: usize,
+}
+
+/// A GL sync object in the compositor's context, signalled once a texture was written.
+///
+/// It is shared with contexts created from [`GlHandles`], the receiver waits on and deletes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlSync {
+    /// `GLsync`
+    pub sync: usize,
+}
+
 #[derive(Clone)]
 pub struct Tracer {
     start_fn: extern "C" fn(*const c_char) -> *mut c_void,
@@ -66,9 +135,15 @@ impl Drop for Trace {
     }
 }
 
+/// Started applications, shared with the compositor, which removes them once they exited.
+pub(crate) type Children = Arc<Mutex<Vec<Child>>>;
+
 pub struct WaylandDisplay {
     thread_handle: Option<JoinHandle<()>>,
     command_tx: Sender<Command>,
+    render_node: String,
+    /// Applications started with [`WaylandDisplay::spawn`] that are still running, terminated with the display.
+    children: Children,
 
     pub tracer: Option<Tracer>,
     pub devices: MaybeRecv<Vec<CString>>,
@@ -94,35 +169,61 @@ impl<T: Clone> MaybeRecv<T> {
 }
 
 impl WaylandDisplay {
-    pub fn new(render_node: Option<String>) -> Result<WaylandDisplay, CreateDrmNodeError> {
-        let (channel_tx, channel_rx) = std::sync::mpsc::sync_channel(0);
+    /// `render_node` is either a device path, `software` or `auto` (the default),
+    /// optionally followed by preferred drivers, PCI ids or paths, e.g. `auto:amdgpu,10de:*`.
+    pub fn new(render_node: Option<String>) -> Result<WaylandDisplay, DisplayError> {
+        Self::new_with_socket(render_node, WaylandSocket::Auto)
+    }
+
+    pub fn new_with_socket(
+        render_node: Option<String>,
+        socket: WaylandSocket,
+    ) -> Result<WaylandDisplay, DisplayError> {
+        let (init_tx, init_rx) = std::sync::mpsc::sync_channel(1);
         let (devices_tx, devices_rx) = std::sync::mpsc::channel();
         let (envs_tx, envs_rx) = std::sync::mpsc::channel();
         let render_target = RenderTarget::from_str(
-            &render_node.unwrap_or_else(|| String::from("/dev/dri/renderD128")),
+            &render_node.unwrap_or_else(|| String::from("auto")),
         )?;
+        // bind the socket upfront, so the name is known and errors are reported to the caller
+        let listener = socket.bind()?;
 
         let thread_handle = std::thread::spawn(move || {
             if let Err(err) = std::panic::catch_unwind(|| {
                 // calloops channel is not "UnwindSafe", but the std channel is... *sigh* lets workaround it creatively
                 let (command_tx, command_src) = smithay::reexports::calloop::channel::channel();
-                channel_tx.send(command_tx).unwrap();
-                comp::init(command_src, render_target, devices_tx, envs_tx);
+                match comp::init(command_src, render_target, listener, devices_tx, envs_tx) {
+                    Ok((event_loop, state)) => {
+                        let _ = init_tx.send(Ok((command_tx, state.render_target.to_string())));
+                        comp::run(event_loop, state);
+                    }
+                    Err(err) => {
+                        let _ = init_tx.send(Err(err));
+                    }
+                }
             }) {
                 tracing::error!(?err, "Compositor thread panic'ed!");
             }
         });
-        let command_tx = channel_rx.recv().unwrap();
+        // a panic drops the sender without reporting anything
+        let (command_tx, render_node) = init_rx.recv().map_err(|_| DisplayError::CompositorDied)??;
 
         Ok(WaylandDisplay {
             thread_handle: Some(thread_handle),
             command_tx,
+            render_node,
+            children: Children::default(),
             tracer: None,
             devices: MaybeRecv::Rx(devices_rx),
             envs: MaybeRecv::Rx(envs_rx),
         })
     }
 
+    /// The render node actually in use, or `software`.
+    pub fn render_node(&self) -> &str {
+        &self.render_node
+    }
+
     pub fn devices(&mut self) -> impl Iterator<Item=&str> {
         self.devices
             .get()
@@ -141,8 +242,150 @@ impl WaylandDisplay {
         let _ = self.command_tx.send(Command::InputDevice(path.into()));
     }
 
+    /// Makes [`WaylandDisplay::frame`] return [`FrameError::Eos`] once the last client disconnected.
+    pub fn set_eos_on_last_client(&self, enabled: bool) {
+        let _ = self.command_tx.send(Command::EosOnLastClient(enabled));
+    }
+
+    /// Sets the quotas enforced for every client, already connected clients are only checked on their next request.
+    pub fn set_client_limits(&self, limits: ClientLimits) {
+        let _ = self.command_tx.send(Command::ClientLimits(limits));
+    }
+
+    /// Lets the compositor switch to software rendering, if the renderer can't be recreated after a context loss.
+    pub fn set_software_fallback(&self, enabled: bool) {
+        let _ = self.command_tx.send(Command::SoftwareFallback(enabled));
+    }
+
+    /// Sets the scale of the output, e.g. `1.5` to render the UI at 150%. Clients get the
+    /// integer scale rounded up through `wl_output`, or the exact one with `wp_fractional_scale_v1`.
+    ///
+    /// The scale is clamped to [`MIN_OUTPUT_SCALE`]..=[`MAX_OUTPUT_SCALE`], non-finite ones are ignored.
+    pub fn set_output_scale(&self, scale: f64) {
+        self.handle().set_output_scale(scale)
+    }
+
+    /// Rotates and/or flips the desktop inside the video frames, e.g. for clients in portrait orientation.
+    pub fn set_output_transform(&self, transform: OutputTransform) {
+        self.handle().set_output_transform(transform)
+    }
+
+    /// Renders the desktop at a size other than the one of the video frames, e.g. 3840x2160 for 1920x1080
+    /// frames. It is scaled to fit the frames, keeping the aspect ratio. `None` renders at the frame size.
+    ///
+    /// Sizes that aren't positive are ignored.
+    pub fn set_render_size(&self, size: Option<(i32, i32)>) {
+        self.handle().set_render_size(size)
+    }
+
+    /// Shows only part of the desktop in the video frames, scaled to fit them, e.g. to zoom in for accessibility.
+    ///
+    /// Absolute pointer input is mapped back onto the shown part.
+    pub fn set_frame_crop(&self, crop: FrameCrop) {
+        self.handle().set_frame_crop(crop)
+    }
+
+    /// Scales a fullscreen client, that doesn't take the size of the output (e.g. a game with a fixed resolution),
+    /// to fit the frames while keeping its aspect ratio. The area around it stays black.
+    ///
+    /// Absolute pointer input is mapped into the client's coordinates.
+    pub fn set_letterbox(&self, enabled: bool) {
+        self.handle().set_letterbox(enabled)
+    }
+
+    /// Sets the color (RGBA, 0.0 to 1.0) shown behind the windows, black by default.
+    pub fn set_background_color(&self, color: [f32; 4]) {
+        self.handle().set_background_color(color)
+    }
+
+    /// Shows an image behind the windows, on top of the background color. `None` removes it.
+    pub fn set_wallpaper(&self, image: Option<RgbaImage>) {
+        self.handle().set_wallpaper(image)
+    }
+
+    pub fn set_wallpaper_mode(&self, mode: WallpaperMode) {
+        self.handle().set_wallpaper_mode(mode)
+    }
+
+    /// Sets the globals clients connecting from now on can see, for restricted clients (including those
+    /// of `wp_security_context_v1`) or all others. Restricted clients default to [`GlobalFilter::restricted`].
+    pub fn set_global_filter(&self, restricted: bool, filter: GlobalFilter) {
+        let _ = self.command_tx.send(Command::GlobalFilter(restricted, filter));
+    }
+
+    /// A handle changing the overlays independently of this display, e.g. from another thread.
+    pub fn overlays(&self) -> Overlays {
+        self.handle().overlays()
+    }
+
+    /// A handle changing this display without access to it, e.g. from another thread.
+    pub fn handle(&self) -> DisplayHandle {
+        DisplayHandle::new(self.command_tx.clone())
+    }
+
+    /// Adds an overlay to the frames of the primary output, returns its id or `None` if the compositor is gone.
+    pub fn add_overlay(&self, overlay: Overlay) -> Option<u64> {
+        self.overlays().add(overlay)
+    }
+
+    /// Replaces the image, position and z-order of an overlay.
+    pub fn update_overlay(&self, id: u64, overlay: Overlay) {
+        self.overlays().update(id, overlay)
+    }
+
+    pub fn remove_overlay(&self, id: u64) {
+        self.overlays().remove(id)
+    }
+
+    /// Sets name, make, model and physical properties of the output.
+    ///
+    /// Clients can't be told about a new identity, so this only takes effect if called before the first
+    /// [`WaylandDisplay::set_video_info`].
+    pub fn set_output_info(&self, info: OutputInfo) {
+        let _ = self.command_tx.send(Command::OutputInfo(info));
+    }
+
+    /// Sets the modes clients may request for the primary output, see [`WaylandDisplay::set_output_modes`].
+    pub fn set_modes(&self, modes: Vec<DisplayMode>) {
+        self.handle().set_modes(modes)
+    }
+
+    /// Sets the modes clients may request through `wlr-output-management` for an output, besides the current one.
+    ///
+    /// Requests are reported as [`DisplayEvent::ModeRequested`], the mode only changes once
+    /// the embedder renegotiates and calls [`WaylandDisplay::set_output_video_info`].
+    pub fn set_output_modes(&self, output: u32, modes: Vec<DisplayMode>) {
+        self.handle().set_output_modes(output, modes)
+    }
+
     pub fn set_video_info(&self, info: VideoInfo) {
-        let _ = self.command_tx.send(Command::VideoInfo(info));
+        self.set_output_video_info(comp::PRIMARY_OUTPUT, info);
+    }
+
+    /// Adds another virtual output and returns its id.
+    ///
+    /// Outputs are placed next to each other from left to right. The new output shows up for clients
+    /// once [`WaylandDisplay::set_output_video_info`] was called, its frames are rendered by
+    /// [`WaylandDisplay::output_frame`].
+    pub fn add_output(&self, info: OutputInfo) -> Option<u32> {
+        let (id_tx, id_rx) = mpsc::sync_channel(1);
+        self.command_tx.send(Command::AddOutput(info, id_tx)).ok()?;
+        id_rx.recv().ok()
+    }
+
+    /// Sets the format, size and framerate of an output's frames, the primary output has id `0`.
+    pub fn set_output_video_info(&self, output: u32, info: VideoInfo) {
+        let _ = self.command_tx.send(Command::VideoInfo(output, info));
+    }
+
+    /// Removes an output added with [`WaylandDisplay::add_output`], its windows move to the primary output.
+    pub fn remove_output(&self, output: u32) {
+        let _ = self.command_tx.send(Command::RemoveOutput(output));
+    }
+
+    /// Moves a window onto another output, where it is maximized.
+    pub fn move_window_to_output(&self, window: u64, output: u32) {
+        let _ = self.command_tx.send(Command::MoveWindow(window, output));
     }
 
     pub fn keyboard_input(&self, key: u32, pressed: bool) {
@@ -154,8 +397,16 @@ impl WaylandDisplay {
         let _ = self.command_tx.send(Command::PointerMotion((x, y).into()));
     }
 
+    /// Moves the pointer to a position in pixels of the video frames.
     pub fn pointer_motion_absolute(&self, x: f64, y: f64) {
-        let _ = self.command_tx.send(Command::PointerMotionAbsolute((x, y).into()));
+        self.pointer_motion_absolute_on_output(PRIMARY_OUTPUT, x, y);
+    }
+
+    /// Moves the pointer to a position in pixels of the video frames of an output added with [`WaylandDisplay::add_output`].
+    pub fn pointer_motion_absolute_on_output(&self, output: u32, x: f64, y: f64) {
+        let _ = self
+            .command_tx
+            .send(Command::PointerMotionAbsolute(output, (x, y).into()));
     }
 
     pub fn pointer_button(&self, button: u32, pressed: bool) {
@@ -167,30 +418,202 @@ impl WaylandDisplay {
         let _ = self.command_tx.send(Command::PointerAxis(x, y));
     }
 
-    pub fn frame(&self) -> Result<gst::Buffer, gst::FlowError> {
+    /// Registers a callback, that is invoked on the compositor thread for every [`DisplayEvent`].
+    pub fn set_event_callback(&self, callback: impl Fn(DisplayEvent) + Send + 'static) {
+        let _ = self.command_tx.send(Command::EventCallback(Box::new(callback)));
+    }
+
+    pub fn windows(&self) -> Vec<WindowInfo> {
+        self.handle().windows()
+    }
+
+    pub fn focus_window(&self, id: u64) {
+        self.handle().focus_window(id)
+    }
+
+    pub fn raise_window(&self, id: u64) {
+        self.handle().raise_window(id)
+    }
+
+    pub fn close_window(&self, id: u64) {
+        self.handle().close_window(id)
+    }
+
+    pub fn kill_window(&self, id: u64) {
+        self.handle().kill_window(id)
+    }
+
+    /// Starts `command` connected to this display and returns its pid.
+    ///
+    /// The process output is forwarded to the current tracing subscriber, its exit is reported as [`DisplayEvent::ProcessExited`].
+    pub fn spawn<'a>(
+        &mut self,
+        command: &str,
+        args: impl IntoIterator<Item=&'a str>,
+        envs: impl IntoIterator<Item=(&'a str, &'a str)>,
+    ) -> std::io::Result<u32> {
+        let display_envs = self
+            .env_vars()
+            .flat_map(|var| var.split_once('='))
+            .map(|(key, val)| (key.to_owned(), val.to_owned()))
+            .collect::<Vec<_>>();
+        let mut child = std::process::Command::new(command)
+            .args(args)
+            .envs(display_envs)
+            .envs(envs)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let pid = child.id();
+        tracing::info!(command, pid, "Started application.");
+
+        let dispatch = tracing::dispatcher::get_default(|dispatch| dispatch.clone());
+        if let Some(stdout) = child.stdout.take() {
+            forward_output(stdout, pid, "stdout", dispatch.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward_output(stderr, pid, "stderr", dispatch.clone());
+        }
+
+        // the compositor reaps it, the display can still terminate it until then
+        self.children.lock().unwrap().push(child);
+        let _ = self.command_tx.send(Command::ChildSpawned(self.children.clone()));
+
+        Ok(pid)
+    }
+
+    /// Adds a client on an already connected stream socket, e.g. one end of a `socketpair`,
+    /// and returns the client id used in [`DisplayEvent`]s and [`WindowInfo`].
+    ///
+    /// `restricted` clients (e.g. sandboxed apps) can't see privileged protocols like layer-shell.
+    pub fn insert_client_fd(&self, fd: OwnedFd, restricted: bool) -> std::io::Result<u64> {
+        self.insert_client(fd, restricted, None)
+    }
+
+    /// Like [`WaylandDisplay::insert_client_fd`] for a restricted client, that sees the globals `filter` allows.
+    pub fn insert_client_fd_with_filter(&self, fd: OwnedFd, filter: GlobalFilter) -> std::io::Result<u64> {
+        self.insert_client(fd, true, Some(filter))
+    }
+
+    fn insert_client(&self, fd: OwnedFd, restricted: bool, filter: Option<GlobalFilter>) -> std::io::Result<u64> {
+        let (client_tx, client_rx) = mpsc::sync_channel(1);
+        self.command_tx
+            .send(Command::InsertClient(UnixStream::from(fd), restricted, filter, client_tx))
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "compositor thread is gone"))?;
+        client_rx
+            .recv()
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "compositor thread is gone"))?
+    }
+
+    /// Renders the next frame.
+    ///
+    /// Returns [`FrameError::CompositorDied`] once the compositor thread is gone,
+    /// in which case the display has to be dropped and created again.
+    pub fn frame(&self) -> Result<gst::Buffer, FrameError> {
+        self.output_frame(comp::PRIMARY_OUTPUT)
+    }
+
+    /// Renders the next frame into `texture`, a 2D texture of the video size in a GL context
+    /// sharing objects with the one in [`WaylandDisplay::gl_handles`].
+    ///
+    /// The copy into the texture may still be running on return, the texture must only be used
+    /// after waiting on the returned [`GlSync`].
+    pub fn frame_into_texture(&self, texture: u32) -> Result<GlSync, FrameError> {
+        self.output_frame_into_texture(comp::PRIMARY_OUTPUT, texture)
+    }
+
+    /// Renders the next frame of an output, see [`WaylandDisplay::frame`].
+    pub fn output_frame(&self, output: u32) -> Result<gst::Buffer, FrameError> {
+        match self.render(output, comp::FrameTarget::Memory)? {
+            comp::RenderedFrame::Buffer(buffer) => Ok(buffer),
+            comp::RenderedFrame::Texture(_) => Err(FrameError::Render),
+        }
+    }
+
+    /// Renders the next frame of an output into `texture`, see [`WaylandDisplay::frame_into_texture`].
+    pub fn output_frame_into_texture(&self, output: u32, texture: u32) -> Result<GlSync, FrameError> {
+        match self.render(output, comp::FrameTarget::Texture(texture))? {
+            comp::RenderedFrame::Texture(sync) => Ok(sync),
+            comp::RenderedFrame::Buffer(_) => Err(FrameError::Render),
+        }
+    }
+
+    /// Renders a single window with its popups and subsurfaces, independent of the outputs.
+    ///
+    /// The frame has the size of the window, so it changes whenever the window is resized,
+    /// the returned [`VideoInfo`] describes the frame. Like [`WaylandDisplay::frame`], frames are paced
+    /// at the framerate of the primary output.
+    pub fn window_frame(&self, window: u64) -> Result<(gst::Buffer, VideoInfo), FrameError> {
+        let (buffer_tx, buffer_rx) = mpsc::sync_channel(0);
+        if let Err(err) = self.command_tx.send(Command::WindowFrame(window, buffer_tx)) {
+            tracing::warn!(?err, "Failed to send window frame command.");
+            return Err(FrameError::CompositorDied);
+        }
+
+        buffer_rx.recv().unwrap_or_else(|err| {
+            tracing::warn!(?err, "Failed to recv window frame.");
+            Err(FrameError::CompositorDied)
+        })
+    }
+
+    fn render(&self, output: u32, target: comp::FrameTarget) -> Result<comp::RenderedFrame, FrameError> {
         let (buffer_tx, buffer_rx) = mpsc::sync_channel(0);
-        if let Err(err) = self.command_tx.send(Command::Buffer(buffer_tx, self.tracer.clone())) {
+        if let Err(err) = self.command_tx.send(Command::Buffer(output, target, buffer_tx, self.tracer.clone())) {
             tracing::warn!(?err, "Failed to send buffer command.");
-            return Err(gst::FlowError::Eos);
+            return Err(FrameError::CompositorDied);
         }
 
         match buffer_rx.recv() {
-            Ok(Ok(buffer)) => Ok(buffer),
-            Ok(Err(err)) => match err {
-                SwapBuffersError::AlreadySwapped => unreachable!(),
-                SwapBuffersError::ContextLost(_) => Err(gst::FlowError::Eos),
-                SwapBuffersError::TemporaryFailure(_) => Err(gst::FlowError::Error),
-            },
+            Ok(result) => result,
             Err(err) => {
                 tracing::warn!(?err, "Failed to recv buffer ack.");
-                Err(gst::FlowError::Error)
+                Err(FrameError::CompositorDied)
             }
         }
     }
+
+    /// Handles of the GL renderer, `None` when rendering with pixman.
+    ///
+    /// They change if the renderer is recreated after a context loss.
+    pub fn gl_handles(&self) -> Option<GlHandles> {
+        let (handles_tx, handles_rx) = mpsc::sync_channel(1);
+        self.command_tx.send(Command::GlHandles(handles_tx)).ok()?;
+        handles_rx.recv().ok().flatten()
+    }
+
+    /// Returns `false` once the compositor thread exited, e.g. after a panic.
+    pub fn is_alive(&self) -> bool {
+        self.thread_handle
+            .as_ref()
+            .map_or(false, |handle| !handle.is_finished())
+    }
+}
+
+fn forward_output(output: impl Read + Send + 'static, pid: u32, stream: &'static str, dispatch: tracing::Dispatch) {
+    std::thread::spawn(move || {
+        tracing::dispatcher::with_default(&dispatch, || {
+            for line in BufReader::new(output).lines() {
+                match line {
+                    Ok(line) => tracing::info!(pid, stream, "{}", line),
+                    Err(_) => break,
+                }
+            }
+        })
+    });
 }
 
 impl Drop for WaylandDisplay {
     fn drop(&mut self) {
+        for mut child in self.children.lock().unwrap().drain(..) {
+            if let Ok(None) = child.try_wait() {
+                tracing::info!(pid = child.id(), "Terminating application.");
+                if let Err(err) = child.kill().and_then(|_| child.wait()) {
+                    tracing::warn!(?err, pid = child.id(), "Failed to terminate application.");
+                }
+            }
+        }
+
         if let Err(err) = self.command_tx.send(Command::Quit) {
             tracing::warn!("Failed to send stop command: {}", err);
             return;
@@ -0,0 +1,229 @@
+/// Per-client quotas, `None` means unlimited.
+///
+/// Clients going over any of these are disconnected with a protocol error
+/// and reported as [`crate::DisplayEvent::QuotaExceeded`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ClientLimits {
+    /// Maximum number of live `wl_surface`s, including popups and subsurfaces.
+    pub max_surfaces: Option<usize>,
+    /// Maximum size in bytes of a single shm pool, checked when it is created or resized.
+    pub max_shm_pool_size: Option<usize>,
+    pub max_buffer_width: Option<i32>,
+    pub max_buffer_height: Option<i32>,
+    /// Maximum number of frame callbacks pending on all surfaces of a client.
+    pub max_frame_callbacks: Option<usize>,
+}
+
+/// What a client uses of the limited resources, `None` for what isn't checked at that point.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ClientUsage {
+    pub surfaces: Option<usize>,
+    pub shm_pool_size: Option<usize>,
+    pub frame_callbacks: Option<usize>,
+    pub buffer_size: Option<(i32, i32)>,
+}
+
+/// A quota a client went over, with its limit and the value exceeding it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct QuotaViolation {
+    pub quota: &'static str,
+    pub limit: u64,
+    pub value: u64,
+}
+
+impl QuotaViolation {
+    fn new(quota: &'static str, limit: impl TryInto<u64>, value: impl TryInto<u64>) -> Self {
+        QuotaViolation {
+            quota,
+            limit: limit.try_into().unwrap_or_default(),
+            value: value.try_into().unwrap_or_default(),
+        }
+    }
+}
+
+impl ClientLimits {
+    /// The first quota `usage` goes over, reaching a limit is still fine.
+    pub(crate) fn check(&self, usage: &ClientUsage) -> Option<QuotaViolation> {
+        fn exceeded<T: PartialOrd + Copy>(limit: Option<T>, value: Option<T>) -> Option<(T, T)> {
+            let (limit, value) = (limit?, value?);
+            (value > limit).then_some((limit, value))
+        }
+
+        if let Some((limit, value)) = exceeded(self.max_surfaces, usage.surfaces) {
+            return Some(QuotaViolation::new("surfaces", limit, value));
+        }
+        if let Some((limit, value)) = exceeded(self.max_shm_pool_size, usage.shm_pool_size) {
+            return Some(QuotaViolation::new("shm-pool-size", limit, value));
+        }
+        if let Some((limit, value)) = exceeded(self.max_frame_callbacks, usage.frame_callbacks) {
+            return Some(QuotaViolation::new("frame-callbacks", limit, value));
+        }
+        let (width, height) = usage.buffer_size.unzip();
+        if let Some((limit, value)) = exceeded(self.max_buffer_width, width) {
+            return Some(QuotaViolation::new("buffer-width", limit, value));
+        }
+        if let Some((limit, value)) = exceeded(self.max_buffer_height, height) {
+            return Some(QuotaViolation::new("buffer-height", limit, value));
+        }
+        None
+    }
+}
+
+/// Globals whose visibility a [`GlobalFilter`] controls, by interface name.
+///
+/// All others are always visible: `wl_compositor`, `wl_subcompositor`, `wl_shm`, `wl_seat`, `wl_output`,
+/// `zxdg_output_manager_v1`, `xdg_wm_base`, `wl_data_device_manager`, `wp_presentation`,
+/// `zwp_relative_pointer_manager_v1`, `zwp_pointer_constraints_v1`, `wp_viewporter` and
+/// `wp_fractional_scale_manager_v1`.
+pub const FILTERABLE_GLOBALS: &[&str] = &[
+    "zwlr_layer_shell_v1",
+    "wp_security_context_manager_v1",
+    "zwlr_output_manager_v1",
+    "wp_color_manager_v1",
+    "zwp_linux_dmabuf_v1",
+    "wl_drm",
+];
+
+/// Privileged protocols hidden from restricted clients by default.
+pub const PRIVILEGED_GLOBALS: &[&str] = &[
+    "zwlr_layer_shell_v1",
+    "wp_security_context_manager_v1",
+    "zwlr_output_manager_v1",
+];
+
+/// Which of the [`FILTERABLE_GLOBALS`] a client can see, by interface name.
+///
+/// Other interfaces aren't affected, allowing or denying them has no effect.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GlobalFilter {
+    /// Only these interfaces are visible, all of them if `None`.
+    pub allow: Option<Vec<String>>,
+    /// Interfaces that are never visible, even if allowed.
+    pub deny: Vec<String>,
+}
+
+impl GlobalFilter {
+    /// Hides the [`PRIVILEGED_GLOBALS`], the default for restricted clients.
+    pub fn restricted() -> Self {
+        GlobalFilter {
+            allow: None,
+            deny: PRIVILEGED_GLOBALS.iter().map(|interface| interface.to_string()).collect(),
+        }
+    }
+
+    pub fn allows(&self, interface: &str) -> bool {
+        self.allow
+            .as_ref()
+            .map_or(true, |allow| allow.iter().any(|allowed| allowed == interface))
+            && !self.deny.iter().any(|denied| denied == interface)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> ClientLimits {
+        ClientLimits {
+            max_surfaces: Some(10),
+            max_shm_pool_size: Some(1024),
+            max_buffer_width: Some(640),
+            max_buffer_height: Some(480),
+            max_frame_callbacks: Some(4),
+        }
+    }
+
+    #[test]
+    fn unlimited_by_default() {
+        let usage = ClientUsage {
+            surfaces: Some(usize::MAX),
+            shm_pool_size: Some(usize::MAX),
+            frame_callbacks: Some(usize::MAX),
+            buffer_size: Some((i32::MAX, i32::MAX)),
+        };
+        assert_eq!(ClientLimits::default().check(&usage), None);
+    }
+
+    #[test]
+    fn limits_may_be_reached() {
+        let usage = ClientUsage {
+            surfaces: Some(10),
+            shm_pool_size: Some(1024),
+            frame_callbacks: Some(4),
+            buffer_size: Some((640, 480)),
+        };
+        assert_eq!(limits().check(&usage), None);
+        assert_eq!(limits().check(&ClientUsage::default()), None);
+    }
+
+    #[test]
+    fn report_the_exceeded_quota() {
+        let check = |usage| {
+            limits()
+                .check(&usage)
+                .map(|violation| (violation.quota, violation.limit, violation.value))
+        };
+        assert_eq!(
+            check(ClientUsage {
+                surfaces: Some(11),
+                ..Default::default()
+            }),
+            Some(("surfaces", 10, 11))
+        );
+        assert_eq!(
+            check(ClientUsage {
+                shm_pool_size: Some(4096),
+                ..Default::default()
+            }),
+            Some(("shm-pool-size", 1024, 4096))
+        );
+        assert_eq!(
+            check(ClientUsage {
+                frame_callbacks: Some(5),
+                ..Default::default()
+            }),
+            Some(("frame-callbacks", 4, 5))
+        );
+        assert_eq!(
+            check(ClientUsage {
+                buffer_size: Some((641, 480)),
+                ..Default::default()
+            }),
+            Some(("buffer-width", 640, 641))
+        );
+        assert_eq!(
+            check(ClientUsage {
+                buffer_size: Some((640, 481)),
+                ..Default::default()
+            }),
+            Some(("buffer-height", 480, 481))
+        );
+    }
+
+    #[test]
+    fn only_checked_values_count() {
+        // a limit without a value to check against, e.g. no buffer attached on this commit
+        let limits = ClientLimits {
+            max_buffer_width: Some(1),
+            ..Default::default()
+        };
+        let usage = ClientUsage {
+            frame_callbacks: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(limits.check(&usage), None);
+    }
+
+    #[test]
+    fn first_exceeded_quota_wins() {
+        let usage = ClientUsage {
+            frame_callbacks: Some(5),
+            buffer_size: Some((1000, 1000)),
+            ..Default::default()
+        };
+        assert_eq!(
+            limits().check(&usage).map(|violation| violation.quota),
+            Some("frame-callbacks")
+        );
+    }
+}
@@ -0,0 +1,426 @@
+use std::{fmt, str::FromStr};
+
+#[cfg(feature = "glib")]
+use gst::glib;
+use gst_video::VideoInfo;
+use smithay::{
+    output::{Mode, PhysicalProperties, Subpixel},
+    utils::Transform,
+};
+
+use crate::{InvalidCropError, InvalidModeError};
+
+/// Rotation (counter-clockwise) and flipping of the output, with the values of `wl_output.transform`.
+///
+/// The video frames keep the negotiated size, rotating by 90° or 270° swaps the size of the desktop.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "glib", derive(glib::Enum), enum_type(name = "GstWaylandDisplayTransform"))]
+#[repr(u32)]
+pub enum OutputTransform {
+    #[default]
+    #[cfg_attr(feature = "glib", enum_value(name = "No rotation", nick = "normal"))]
+    Normal = 0,
+    #[cfg_attr(feature = "glib", enum_value(name = "Rotate 90 degrees counter-clockwise", nick = "90"))]
+    Rotate90 = 1,
+    #[cfg_attr(feature = "glib", enum_value(name = "Rotate 180 degrees", nick = "180"))]
+    Rotate180 = 2,
+    #[cfg_attr(feature = "glib", enum_value(name = "Rotate 270 degrees counter-clockwise", nick = "270"))]
+    Rotate270 = 3,
+    #[cfg_attr(feature = "glib", enum_value(name = "Flip horizontally", nick = "flipped"))]
+    Flipped = 4,
+    #[cfg_attr(feature = "glib", enum_value(name = "Flip horizontally and rotate 90 degrees", nick = "flipped-90"))]
+    Flipped90 = 5,
+    #[cfg_attr(feature = "glib", enum_value(name = "Flip horizontally and rotate 180 degrees", nick = "flipped-180"))]
+    Flipped180 = 6,
+    #[cfg_attr(feature = "glib", enum_value(name = "Flip horizontally and rotate 270 degrees", nick = "flipped-270"))]
+    Flipped270 = 7,
+}
+
+impl TryFrom<u32> for OutputTransform {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => OutputTransform::Normal,
+            1 => OutputTransform::Rotate90,
+            2 => OutputTransform::Rotate180,
+            3 => OutputTransform::Rotate270,
+            4 => OutputTransform::Flipped,
+            5 => OutputTransform::Flipped90,
+            6 => OutputTransform::Flipped180,
+            7 => OutputTransform::Flipped270,
+            value => return Err(value),
+        })
+    }
+}
+
+impl From<OutputTransform> for Transform {
+    fn from(transform: OutputTransform) -> Self {
+        match transform {
+            OutputTransform::Normal => Transform::Normal,
+            OutputTransform::Rotate90 => Transform::_90,
+            OutputTransform::Rotate180 => Transform::_180,
+            OutputTransform::Rotate270 => Transform::_270,
+            OutputTransform::Flipped => Transform::Flipped,
+            OutputTransform::Flipped90 => Transform::Flipped90,
+            OutputTransform::Flipped180 => Transform::Flipped180,
+            OutputTransform::Flipped270 => Transform::Flipped270,
+        }
+    }
+}
+
+/// Identity and physical properties of the output, published through `wl_output`, `xdg_output`
+/// and `wlr-output-management`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputInfo {
+    /// Connector name, e.g. `HDMI-A-1`, for configs keyed on it.
+    pub name: String,
+    pub make: String,
+    pub model: String,
+    pub serial: Option<String>,
+    /// Physical size in millimeters, `(0, 0)` if unknown. Toolkits derive the DPI from it.
+    pub physical_size: (i32, i32),
+    pub subpixel: OutputSubpixel,
+}
+
+impl Default for OutputInfo {
+    fn default() -> Self {
+        OutputInfo {
+            name: String::from("HEADLESS-1"),
+            make: String::from("Virtual"),
+            model: String::from("Wolf"),
+            serial: None,
+            physical_size: (0, 0),
+            subpixel: OutputSubpixel::Unknown,
+        }
+    }
+}
+
+impl OutputInfo {
+    pub(crate) fn physical_properties(&self) -> PhysicalProperties {
+        PhysicalProperties {
+            make: self.make.clone(),
+            model: self.model.clone(),
+            size: self.physical_size.into(),
+            subpixel: self.subpixel.into(),
+        }
+    }
+}
+
+/// Subpixel layout of the output, with the values of `wl_output.subpixel`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "glib", derive(glib::Enum), enum_type(name = "GstWaylandDisplaySubpixel"))]
+#[repr(u32)]
+pub enum OutputSubpixel {
+    #[default]
+    #[cfg_attr(feature = "glib", enum_value(name = "Unknown", nick = "unknown"))]
+    Unknown = 0,
+    #[cfg_attr(feature = "glib", enum_value(name = "No subpixels", nick = "none"))]
+    None = 1,
+    #[cfg_attr(feature = "glib", enum_value(name = "Horizontal RGB", nick = "horizontal-rgb"))]
+    HorizontalRgb = 2,
+    #[cfg_attr(feature = "glib", enum_value(name = "Horizontal BGR", nick = "horizontal-bgr"))]
+    HorizontalBgr = 3,
+    #[cfg_attr(feature = "glib", enum_value(name = "Vertical RGB", nick = "vertical-rgb"))]
+    VerticalRgb = 4,
+    #[cfg_attr(feature = "glib", enum_value(name = "Vertical BGR", nick = "vertical-bgr"))]
+    VerticalBgr = 5,
+}
+
+impl TryFrom<u32> for OutputSubpixel {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => OutputSubpixel::Unknown,
+            1 => OutputSubpixel::None,
+            2 => OutputSubpixel::HorizontalRgb,
+            3 => OutputSubpixel::HorizontalBgr,
+            4 => OutputSubpixel::VerticalRgb,
+            5 => OutputSubpixel::VerticalBgr,
+            value => return Err(value),
+        })
+    }
+}
+
+impl From<OutputSubpixel> for Subpixel {
+    fn from(subpixel: OutputSubpixel) -> Self {
+        match subpixel {
+            OutputSubpixel::Unknown => Subpixel::Unknown,
+            OutputSubpixel::None => Subpixel::None,
+            OutputSubpixel::HorizontalRgb => Subpixel::HorizontalRgb,
+            OutputSubpixel::HorizontalBgr => Subpixel::HorizontalBgr,
+            OutputSubpixel::VerticalRgb => Subpixel::VerticalRgb,
+            OutputSubpixel::VerticalBgr => Subpixel::VerticalBgr,
+        }
+    }
+}
+
+/// A video mode clients may ask the output to switch to, see [`crate::WaylandDisplay::set_modes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DisplayMode {
+    pub width: i32,
+    pub height: i32,
+    /// Refresh rate in mHz, like `wl_output`.
+    pub refresh: i32,
+}
+
+impl DisplayMode {
+    pub fn from_video_info(info: &VideoInfo) -> Self {
+        let fps = info.fps();
+        DisplayMode {
+            width: info.width() as i32,
+            height: info.height() as i32,
+            refresh: (fps.numer() as f64 * 1000.0 / fps.denom() as f64).round() as i32,
+        }
+    }
+
+    /// The refresh rate as a framerate for the caps.
+    pub fn framerate(&self) -> gst::Fraction {
+        gst::Fraction::new(self.refresh, 1000)
+    }
+}
+
+impl From<DisplayMode> for Mode {
+    fn from(mode: DisplayMode) -> Self {
+        Mode {
+            size: (mode.width, mode.height).into(),
+            refresh: mode.refresh,
+        }
+    }
+}
+
+impl From<Mode> for DisplayMode {
+    fn from(mode: Mode) -> Self {
+        DisplayMode {
+            width: mode.size.w,
+            height: mode.size.h,
+            refresh: mode.refresh,
+        }
+    }
+}
+
+impl fmt::Display for DisplayMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}@{}", self.width, self.height, self.refresh as f64 / 1000.0)
+    }
+}
+
+/// Parses `WIDTHxHEIGHT@REFRESH`, e.g. `1920x1080@60` or `2560x1440@59.94`.
+impl FromStr for DisplayMode {
+    type Err = InvalidModeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidModeError(s.to_owned());
+        let (size, refresh) = s.trim().split_once('@').ok_or_else(invalid)?;
+        let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+        let width = width.parse::<i32>().map_err(|_| invalid())?;
+        let height = height.parse::<i32>().map_err(|_| invalid())?;
+        let refresh = refresh.parse::<f64>().map_err(|_| invalid())?;
+        if width <= 0 || height <= 0 || refresh <= 0.0 {
+            return Err(invalid());
+        }
+
+        Ok(DisplayMode {
+            width,
+            height,
+            refresh: (refresh * 1000.0).round() as i32,
+        })
+    }
+}
+
+/// The part of the output shown in the video frames, it is scaled to fit and centered in them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FrameCrop {
+    /// The whole output.
+    #[default]
+    None,
+    /// A rectangle in logical output coordinates.
+    Region {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    },
+    /// The focused window, or the whole output while no window is focused.
+    FocusedWindow,
+}
+
+impl fmt::Display for FrameCrop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameCrop::None => write!(f, "none"),
+            FrameCrop::Region {
+                x,
+                y,
+                width,
+                height,
+            } => write!(f, "{}x{}+{}+{}", width, height, x, y),
+            FrameCrop::FocusedWindow => write!(f, "focused-window"),
+        }
+    }
+}
+
+/// Parses `none`, `focused-window` or a region as `WIDTHxHEIGHT+X+Y`, e.g. `1280x720+640+360`.
+impl FromStr for FrameCrop {
+    type Err = InvalidCropError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidCropError(s.to_owned());
+        match s.trim() {
+            "" | "none" => Ok(FrameCrop::None),
+            "focused-window" => Ok(FrameCrop::FocusedWindow),
+            region => {
+                let mut parts = region.split('+');
+                let size = parts.next().ok_or_else(invalid)?;
+                let x = parts.next().ok_or_else(invalid)?;
+                let y = parts.next().ok_or_else(invalid)?;
+                if parts.next().is_some() {
+                    return Err(invalid());
+                }
+                let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+                let width = width.parse::<i32>().map_err(|_| invalid())?;
+                let height = height.parse::<i32>().map_err(|_| invalid())?;
+                let x = x.parse::<i32>().map_err(|_| invalid())?;
+                let y = y.parse::<i32>().map_err(|_| invalid())?;
+                if width <= 0 || height <= 0 {
+                    return Err(invalid());
+                }
+
+                Ok(FrameCrop::Region {
+                    x,
+                    y,
+                    width,
+                    height,
+                })
+            }
+        }
+    }
+}
+
+/// How a wallpaper is fitted to the output.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "glib", derive(glib::Enum), enum_type(name = "GstWaylandDisplayWallpaperMode"))]
+#[repr(u32)]
+pub enum WallpaperMode {
+    /// Scaled to the output size, ignoring the aspect ratio.
+    #[cfg_attr(feature = "glib", enum_value(name = "Stretch to the output size", nick = "stretch"))]
+    Stretch = 0,
+    /// Scaled to fit inside the output, the rest shows the background color.
+    #[cfg_attr(feature = "glib", enum_value(name = "Fit inside the output", nick = "fit"))]
+    Fit = 1,
+    /// Scaled to cover the output, cropping what doesn't fit.
+    #[default]
+    #[cfg_attr(feature = "glib", enum_value(name = "Fill the output, cropping the image", nick = "fill"))]
+    Fill = 2,
+    /// Centered at its original size.
+    #[cfg_attr(feature = "glib", enum_value(name = "Center at the original size", nick = "center"))]
+    Center = 3,
+}
+
+impl TryFrom<u32> for WallpaperMode {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => WallpaperMode::Stretch,
+            1 => WallpaperMode::Fit,
+            2 => WallpaperMode::Fill,
+            3 => WallpaperMode::Center,
+            value => return Err(value),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_display_mode() {
+        assert_eq!(
+            "1920x1080@60".parse::<DisplayMode>().unwrap(),
+            DisplayMode {
+                width: 1920,
+                height: 1080,
+                refresh: 60000,
+            }
+        );
+        assert_eq!(" 2560x1440@59.94 ".parse::<DisplayMode>().unwrap().refresh, 59940);
+    }
+
+    #[test]
+    fn display_mode_round_trip() {
+        for mode in ["1920x1080@60", "2560x1440@59.94", "640x480@23.976"] {
+            assert_eq!(mode.parse::<DisplayMode>().unwrap().to_string(), mode);
+        }
+    }
+
+    #[test]
+    fn reject_invalid_display_modes() {
+        for mode in [
+            "",
+            "1920x1080",
+            "1920@60",
+            "1920x@60",
+            "x1080@60",
+            "0x1080@60",
+            "1920x-1@60",
+            "1920x1080@0",
+            "1920x1080@-60",
+            "1920x1080@fast",
+            "1920x1080x2@60",
+        ] {
+            assert!(mode.parse::<DisplayMode>().is_err(), "{mode} was accepted");
+        }
+    }
+
+    #[test]
+    fn parse_frame_crop() {
+        assert_eq!("none".parse::<FrameCrop>().unwrap(), FrameCrop::None);
+        assert_eq!("".parse::<FrameCrop>().unwrap(), FrameCrop::None);
+        assert_eq!("focused-window".parse::<FrameCrop>().unwrap(), FrameCrop::FocusedWindow);
+        assert_eq!(
+            "1280x720+640+360".parse::<FrameCrop>().unwrap(),
+            FrameCrop::Region {
+                x: 640,
+                y: 360,
+                width: 1280,
+                height: 720,
+            }
+        );
+        // regions may start left of or above the output, they get clipped to it
+        assert_eq!(
+            "100x100+-50+-10".parse::<FrameCrop>().unwrap(),
+            FrameCrop::Region {
+                x: -50,
+                y: -10,
+                width: 100,
+                height: 100,
+            }
+        );
+    }
+
+    #[test]
+    fn frame_crop_round_trip() {
+        for crop in ["none", "focused-window", "1280x720+640+360"] {
+            assert_eq!(crop.parse::<FrameCrop>().unwrap().to_string(), crop);
+        }
+    }
+
+    #[test]
+    fn reject_invalid_frame_crops() {
+        for crop in [
+            "focused",
+            "1280x720",
+            "1280x720+640",
+            "1280x720+640+360+1",
+            "1280+640+360",
+            "0x720+0+0",
+            "1280x-720+0+0",
+            "1280x720+x+0",
+        ] {
+            assert!(crop.parse::<FrameCrop>().is_err(), "{crop} was accepted");
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use smithay::reexports::calloop::channel::Sender;
+
+use crate::{Command, RgbaImage};
+
+static NEXT_OVERLAY_ID: AtomicU64 = AtomicU64::new(1);
+
+/// An image composited on top of the frames of the primary output, e.g. a banner, statistics or a
+/// watermark. Text can be turned into an image with `RgbaImage::from_text` (`text` feature).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overlay {
+    pub image: RgbaImage,
+    /// Position of the top left corner in pixels of the frames, in the orientation of the desktop.
+    pub x: i32,
+    pub y: i32,
+    /// Overlays with a higher z-order are drawn on top of those with a lower one,
+    /// newer overlays win ties. All overlays are drawn on top of the windows and the cursor.
+    pub z: i32,
+}
+
+/// Changes the overlays of a [`crate::WaylandDisplay`] without waiting for the compositor, so it can be used
+/// from any thread, even while a frame is being rendered. Changes show up with the next frame.
+#[derive(Clone)]
+pub struct Overlays {
+    command_tx: Sender<Command>,
+}
+
+impl Overlays {
+    pub(crate) fn new(command_tx: Sender<Command>) -> Self {
+        Overlays { command_tx }
+    }
+
+    /// Adds an overlay to the frames of the primary output, returns its id or `None` if the compositor is gone.
+    pub fn add(&self, overlay: Overlay) -> Option<u64> {
+        let id = NEXT_OVERLAY_ID.fetch_add(1, Ordering::SeqCst);
+        self.command_tx.send(Command::AddOverlay(id, overlay)).ok()?;
+        Some(id)
+    }
+
+    /// Replaces the image, position and z-order of an overlay.
+    pub fn update(&self, id: u64, overlay: Overlay) {
+        let _ = self.command_tx.send(Command::UpdateOverlay(id, overlay));
+    }
+
+    pub fn remove(&self, id: u64) {
+        let _ = self.command_tx.send(Command::RemoveOverlay(id));
+    }
+}
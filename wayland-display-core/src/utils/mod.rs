@@ -1,3 +1,5 @@
+mod socket;
 mod target;
 
+pub use self::socket::*;
 pub use self::target::*;
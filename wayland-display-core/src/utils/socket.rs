@@ -0,0 +1,115 @@
+use std::{
+    fs::{File, OpenOptions},
+    os::unix::{fs::OpenOptionsExt, io::OwnedFd, net::UnixListener},
+    path::PathBuf,
+};
+
+use smithay::{
+    reexports::{
+        rustix::fs::{flock, FlockOperation},
+        wayland_server::BindError,
+    },
+    wayland::socket::ListeningSocketSource,
+};
+
+/// Where the compositor should accept new wayland clients.
+#[derive(Debug, Default)]
+pub enum WaylandSocket {
+    /// Pick the first free `wayland-N` socket in `XDG_RUNTIME_DIR`.
+    #[default]
+    Auto,
+    /// A fixed socket name inside `XDG_RUNTIME_DIR`.
+    Name(String),
+    /// An absolute socket path, may be outside of `XDG_RUNTIME_DIR`.
+    Path(PathBuf),
+    /// An already bound and listening unix socket.
+    Fd(OwnedFd),
+}
+
+pub(crate) enum Listener {
+    Source(ListeningSocketSource),
+    /// A socket from [`WaylandSocket::Path`] holds the lock on it, an inherited one doesn't.
+    Unix(UnixListener, Option<SocketLock>),
+}
+
+/// Keeps other compositors off a socket path, like the `.lock` files of `ListeningSocketSource`.
+/// The socket and its lock file are removed when dropped.
+pub(crate) struct SocketLock {
+    socket_path: PathBuf,
+    lock_path: PathBuf,
+    _lock: File,
+}
+
+impl SocketLock {
+    /// Locks `<path>.lock` and removes a stale socket left behind by a previous, dead owner.
+    fn acquire(socket_path: PathBuf) -> Result<Self, BindError> {
+        let mut lock_path = socket_path.clone().into_os_string();
+        lock_path.push(".lock");
+        let lock_path = PathBuf::from(lock_path);
+
+        let lock = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .write(true)
+            .mode(0o660)
+            .open(&lock_path)
+            .map_err(BindError::Io)?;
+        if flock(&lock, FlockOperation::NonBlockingLockExclusive).is_err() {
+            return Err(BindError::AlreadyInUse);
+        }
+        // nobody holds the lock, so whoever created the socket is gone
+        if socket_path.exists() {
+            tracing::info!(path = ?socket_path, "Removing stale wayland socket.");
+            std::fs::remove_file(&socket_path).map_err(BindError::Io)?;
+        }
+
+        Ok(SocketLock {
+            socket_path,
+            lock_path,
+            _lock: lock,
+        })
+    }
+}
+
+impl Drop for SocketLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.socket_path);
+        let _ = std::fs::remove_file(&self.lock_path);
+    }
+}
+
+// the listener is only moved into the compositor thread and never observed after a panic
+impl std::panic::UnwindSafe for Listener {}
+
+impl WaylandSocket {
+    pub(crate) fn bind(self) -> Result<Listener, BindError> {
+        Ok(match self {
+            WaylandSocket::Auto => Listener::Source(ListeningSocketSource::new_auto()?),
+            WaylandSocket::Name(name) => Listener::Source(ListeningSocketSource::with_name(&name)?),
+            WaylandSocket::Path(path) => {
+                let lock = SocketLock::acquire(path)?;
+                let listener = UnixListener::bind(&lock.socket_path).map_err(BindError::Io)?;
+                listener.set_nonblocking(true).map_err(BindError::Io)?;
+                Listener::Unix(listener, Some(lock))
+            }
+            WaylandSocket::Fd(fd) => {
+                let listener = UnixListener::from(fd);
+                listener.set_nonblocking(true).map_err(BindError::Io)?;
+                Listener::Unix(listener, None)
+            }
+        })
+    }
+}
+
+impl Listener {
+    /// Value for `WAYLAND_DISPLAY`, if clients can find the socket on the filesystem.
+    pub fn socket_name(&self) -> Option<String> {
+        match self {
+            Listener::Source(source) => Some(source.socket_name().to_string_lossy().into_owned()),
+            Listener::Unix(listener, _) => listener
+                .local_addr()
+                .ok()
+                .and_then(|addr| addr.as_pathname().map(|path| path.to_string_lossy().into_owned())),
+        }
+    }
+}
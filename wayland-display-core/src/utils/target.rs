@@ -1,4 +1,4 @@
-use std::{ffi::CString, os::unix::fs::MetadataExt, str::FromStr};
+use std::{ffi::CString, fmt, os::unix::fs::MetadataExt, path::PathBuf, str::FromStr};
 
 use smithay::{
     backend::{
@@ -6,12 +6,28 @@ use smithay::{
         udev,
     },
 };
-use smithay::reexports::rustix::fs::major;
+use smithay::reexports::rustix::fs::{major, minor};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum RenderTarget {
     Hardware(DrmNode),
+    /// OpenGL on a software EGL device (llvmpipe).
     Software,
+    /// Pure CPU rendering using pixman, without any GL drivers.
+    Pixman,
+    /// Tries the render nodes matching the preferences first, then all others,
+    /// falling back to software rendering.
+    Auto(Vec<DevicePreference>),
+}
+
+/// Matches a render node for [`RenderTarget::Auto`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum DevicePreference {
+    /// Kernel driver name, e.g. `amdgpu`, `i915` or `nvidia`.
+    Driver(String),
+    /// PCI vendor and optionally device id, written as `vvvv:dddd` or `vvvv:*`.
+    PciId { vendor: u16, device: Option<u16> },
+    Path(PathBuf),
 }
 
 impl FromStr for RenderTarget {
@@ -19,16 +35,58 @@ impl FromStr for RenderTarget {
     fn from_str(s: &str) -> Result<Self, CreateDrmNodeError> {
         Ok(match s {
             "software" => RenderTarget::Software,
+            "pixman" => RenderTarget::Pixman,
+            "auto" => RenderTarget::Auto(Vec::new()),
+            // e.g. `auto:nvidia,1002:*,/dev/dri/renderD129`
+            preferences if preferences.starts_with("auto:") => RenderTarget::Auto(
+                preferences["auto:".len()..]
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|preference| !preference.is_empty())
+                    .map(DevicePreference::from)
+                    .collect(),
+            ),
             path => RenderTarget::Hardware(DrmNode::from_path(path)?),
         })
     }
 }
 
+impl From<&str> for DevicePreference {
+    fn from(s: &str) -> Self {
+        if s.starts_with('/') {
+            return DevicePreference::Path(PathBuf::from(s));
+        }
+        if let Some((vendor, device)) = s.split_once(':') {
+            if let Ok(vendor) = u16::from_str_radix(vendor.trim_start_matches("0x"), 16) {
+                return DevicePreference::PciId {
+                    vendor,
+                    device: u16::from_str_radix(device.trim_start_matches("0x"), 16).ok(),
+                };
+            }
+        }
+        DevicePreference::Driver(s.to_owned())
+    }
+}
+
+impl fmt::Display for RenderTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderTarget::Hardware(node) => match node.dev_path() {
+                Some(path) => write!(f, "{}", path.display()),
+                None => write!(f, "{}", node),
+            },
+            RenderTarget::Software => write!(f, "software"),
+            RenderTarget::Pixman => write!(f, "pixman"),
+            RenderTarget::Auto(_) => write!(f, "auto"),
+        }
+    }
+}
+
 impl Into<Option<DrmNode>> for RenderTarget {
     fn into(self) -> Option<DrmNode> {
         match self {
             RenderTarget::Hardware(node) => Some(node),
-            RenderTarget::Software => None,
+            RenderTarget::Software | RenderTarget::Pixman | RenderTarget::Auto(_) => None,
         }
     }
 }
@@ -44,7 +102,93 @@ const NVIDIA_MAJOR: u32 = 195;
 
 // no clue how this number is on BSDs, feel free to contribute
 
+impl DevicePreference {
+    fn matches(&self, node: &DrmNode) -> bool {
+        match self {
+            DevicePreference::Driver(name) => udev::driver(node.dev_id())
+                .ok()
+                .flatten()
+                .map(|driver| driver.to_str() == Some(name.as_str()))
+                .unwrap_or(false),
+            DevicePreference::PciId { vendor, device } => {
+                pci_id(node).map_or(false, |(node_vendor, node_device)| {
+                    node_vendor == *vendor && device.map_or(true, |device| device == node_device)
+                })
+            }
+            DevicePreference::Path(path) => DrmNode::from_path(path)
+                .ok()
+                .and_then(|other| other.node_with_type(NodeType::Render).and_then(Result::ok))
+                .map_or(false, |other| &other == node),
+        }
+    }
+}
+
+fn pci_id(node: &DrmNode) -> Option<(u16, u16)> {
+    let sysfs = PathBuf::from(format!(
+        "/sys/dev/char/{}:{}/device",
+        major(node.dev_id()),
+        minor(node.dev_id())
+    ));
+    let read_id = |name: &str| {
+        std::fs::read_to_string(sysfs.join(name))
+            .ok()
+            .and_then(|id| u16::from_str_radix(id.trim().trim_start_matches("0x"), 16).ok())
+    };
+    Some((read_id("vendor")?, read_id("device")?))
+}
+
+/// All render nodes on the system, sorted by their path.
+fn render_nodes() -> Vec<DrmNode> {
+    let Ok(entries) = std::fs::read_dir("/dev/dri") else {
+        return Vec::new();
+    };
+    let mut paths = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .map_or(false, |name| name.starts_with("renderD"))
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+        .into_iter()
+        .flat_map(|path| DrmNode::from_path(path).ok())
+        .collect()
+}
+
 impl RenderTarget {
+    /// Concrete targets to try in order, [`RenderTarget::Auto`] ends with [`RenderTarget::Software`]
+    /// and [`RenderTarget::Pixman`].
+    pub fn candidates(&self) -> Vec<RenderTarget> {
+        match self {
+            RenderTarget::Auto(preferences) => {
+                let nodes = render_nodes();
+                let mut candidates = Vec::new();
+                for preference in preferences {
+                    for node in nodes.iter().filter(|node| preference.matches(node)) {
+                        if !candidates.contains(node) {
+                            candidates.push(*node);
+                        }
+                    }
+                }
+                // any other gpu still beats software rendering
+                for node in nodes {
+                    if !candidates.contains(&node) {
+                        candidates.push(node);
+                    }
+                }
+                candidates
+                    .into_iter()
+                    .map(RenderTarget::Hardware)
+                    .chain([RenderTarget::Software, RenderTarget::Pixman])
+                    .collect()
+            }
+            target => vec![target.clone()],
+        }
+    }
+
     pub fn as_devices(&self) -> Vec<CString> {
         match self {
             RenderTarget::Hardware(node) => {
@@ -82,7 +226,7 @@ impl RenderTarget {
                     })
                     .collect()
             }
-            RenderTarget::Software => Vec::new(),
+            RenderTarget::Software | RenderTarget::Pixman | RenderTarget::Auto(_) => Vec::new(),
         }
     }
 }
@@ -0,0 +1,40 @@
+use smithay::{
+    desktop::find_popup_root_surface,
+    output::Output,
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    wayland::compositor::get_parent,
+};
+
+use crate::{
+    comp::State,
+    wayland::protocols::color_management::{
+        delegate_color_management, ColorManagementHandler, ColorManagementState,
+    },
+};
+
+impl ColorManagementHandler for State {
+    fn color_management_state(&mut self) -> &mut ColorManagementState {
+        &mut self.color_management_state
+    }
+
+    fn surface_output(&self, surface: &WlSurface) -> Option<Output> {
+        let mut root = surface.clone();
+        while let Some(parent) = get_parent(&root) {
+            root = parent;
+        }
+        if let Some(popup) = self.popups.find_popup(&root) {
+            if let Ok(popup_root) = find_popup_root_surface(&popup) {
+                root = popup_root;
+            }
+        }
+
+        self.space
+            .elements()
+            .find(|window| window.wl_surface().map_or(false, |s| *s == root))
+            .and_then(|window| self.window_output(window).cloned())
+            .or_else(|| self.layer_output(&root))
+            .or_else(|| self.output.clone())
+    }
+}
+
+delegate_color_management!(State);
@@ -9,7 +9,7 @@ use smithay::{
             Client,
         },
     },
-    utils::{Size, SERIAL_COUNTER},
+    utils::SERIAL_COUNTER,
     wayland::{
         buffer::BufferHandler,
         compositor::{with_states, CompositorClientState, CompositorHandler, CompositorState},
@@ -18,7 +18,10 @@ use smithay::{
     },
 };
 
-use crate::comp::{ClientState, FocusTarget, State};
+use crate::{
+    comp::{ClientState, FocusTarget, State},
+    DisplayEvent,
+};
 
 impl BufferHandler for State {
     fn buffer_destroyed(&mut self, _buffer: &WlBuffer) {}
@@ -33,6 +36,10 @@ impl CompositorHandler for State {
         &client.get_data::<ClientState>().unwrap().compositor_state
     }
 
+    fn new_surface(&mut self, surface: &WlSurface) {
+        self.track_surface(surface);
+    }
+
     fn commit(&mut self, surface: &WlSurface) {
         on_commit_buffer_handler::<Self>(surface);
 
@@ -64,61 +71,31 @@ impl CompositorHandler for State {
                 )
             });
 
-            if self.output.is_none() {
+            let Some(zone) = self.window_output(&window).map(|output| self.output_zone(output)) else {
                 return;
-            }
+            };
 
             if !initial_configure_sent {
                 if max_size.w == 0 && max_size.h == 0 {
                     toplevel.with_pending_state(|state| {
-                        state.size = Some(
-                            self.output
-                                .as_ref()
-                                .unwrap()
-                                .current_mode()
-                                .unwrap()
-                                .size
-                                .to_f64()
-                                .to_logical(
-                                    self.output
-                                        .as_ref()
-                                        .unwrap()
-                                        .current_scale()
-                                        .fractional_scale(),
-                                )
-                                .to_i32_round(),
-                        );
+                        state.size = Some(zone.size);
                         state.states.set(XdgState::Fullscreen);
                     });
                 }
                 toplevel.with_pending_state(|state| {
                     state.states.set(XdgState::Activated);
                 });
+                self.send_preferred_scale(&window);
                 toplevel.send_configure();
                 self.pending_windows.push(window);
             } else {
                 let window_size = toplevel.current_state().size.unwrap_or((0, 0).into());
-                let output_size: Size<i32, _> = self
-                    .output
-                    .as_ref()
-                    .unwrap()
-                    .current_mode()
-                    .unwrap()
-                    .size
-                    .to_f64()
-                    .to_logical(
-                        self.output
-                            .as_ref()
-                            .unwrap()
-                            .current_scale()
-                            .fractional_scale(),
-                    )
-                    .to_i32_round();
                 let loc = (
-                    (output_size.w / 2) - (window_size.w / 2),
-                    (output_size.h / 2) - (window_size.h / 2),
+                    zone.loc.x + (zone.size.w / 2) - (window_size.w / 2),
+                    zone.loc.y + (zone.size.h / 2) - (window_size.h / 2),
                 );
                 self.space.map_element(window.clone(), loc, true);
+                self.emit(DisplayEvent::WindowCreated(self.window_info(&window)));
                 self.seat.get_keyboard().unwrap().set_focus(
                     self,
                     Some(FocusTarget::from(window)),
@@ -129,6 +106,10 @@ impl CompositorHandler for State {
             return;
         }
 
+        if self.layer_commit(surface) {
+            return;
+        }
+
         if let Some(popup) = self.popups.find_popup(surface) {
             let PopupKind::Xdg(ref popup) = popup else {
                 // Our compositor doesn't do input handling in the popup code
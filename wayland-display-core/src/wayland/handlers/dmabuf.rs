@@ -1,5 +1,5 @@
 use smithay::{
-    backend::{allocator::dmabuf::Dmabuf, renderer::ImportDma},
+    backend::allocator::dmabuf::Dmabuf,
     delegate_dmabuf,
     wayland::dmabuf::{DmabufGlobal, DmabufHandler, DmabufState, ImportNotifier},
 };
@@ -17,7 +17,7 @@ impl DmabufHandler for State {
         _global: &DmabufGlobal,
         dmabuf: Dmabuf,
         notifier: ImportNotifier) {
-        if self.renderer.import_dmabuf(&dmabuf, None).is_ok() {
+        if self.renderer.import_dmabuf(&dmabuf) {
             let _ = notifier.successful::<State>();
         } else {
             notifier.failed();
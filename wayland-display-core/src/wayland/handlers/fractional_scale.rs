@@ -0,0 +1,16 @@
+use smithay::{
+    delegate_fractional_scale,
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    wayland::{compositor::with_states, fractional_scale::FractionalScaleHandler},
+};
+
+use crate::comp::{set_preferred_scale, State};
+
+impl FractionalScaleHandler for State {
+    fn new_fractional_scale(&mut self, surface: WlSurface) {
+        let scale = self.output_scale();
+        with_states(&surface, |states| set_preferred_scale(states, scale));
+    }
+}
+
+delegate_fractional_scale!(State);
@@ -0,0 +1,209 @@
+use std::cell::Cell;
+
+use smithay::{
+    delegate_layer_shell,
+    desktop::{
+        find_popup_root_surface, get_popup_toplevel_coords, layer_map_for_output, LayerSurface,
+        PopupKind, WindowSurfaceType,
+    },
+    output::Output,
+    reexports::wayland_server::protocol::{wl_output::WlOutput, wl_surface::WlSurface},
+    utils::SERIAL_COUNTER,
+    wayland::{
+        compositor::with_states,
+        shell::{
+            wlr_layer::{
+                KeyboardInteractivity, Layer, LayerSurface as WlrLayerSurface, LayerSurfaceData,
+                WlrLayerShellHandler, WlrLayerShellState,
+            },
+            xdg::PopupSurface,
+        },
+    },
+};
+
+use crate::comp::{FocusTarget, State};
+
+impl WlrLayerShellHandler for State {
+    fn shell_state(&mut self) -> &mut WlrLayerShellState {
+        &mut self.layer_shell_state
+    }
+
+    fn new_layer_surface(
+        &mut self,
+        surface: WlrLayerSurface,
+        wl_output: Option<WlOutput>,
+        _layer: Layer,
+        namespace: String,
+    ) {
+        let layer = LayerSurface::new(surface, namespace);
+        let output = wl_output
+            .as_ref()
+            .and_then(Output::from_resource)
+            .or_else(|| self.output.clone());
+
+        match output {
+            Some(output) => {
+                if let Err(err) = layer_map_for_output(&output).map_layer(&layer) {
+                    tracing::warn!(?err, "Failed to map layer surface.");
+                }
+            }
+            // we don't have an output before the caps are negotiated, map it once we do
+            None => self.pending_layers.push(layer),
+        }
+    }
+
+    fn new_popup(&mut self, _parent: WlrLayerSurface, popup: PopupSurface) {
+        self.unconstrain_layer_popup(&popup);
+    }
+
+    fn layer_destroyed(&mut self, surface: WlrLayerSurface) {
+        self.pending_layers.retain(|layer| layer.layer_surface() != &surface);
+
+        let Some(output) = self.layer_output(surface.wl_surface()) else {
+            return;
+        };
+        let mut map = layer_map_for_output(&output);
+        let zone = map.non_exclusive_zone();
+        if let Some(layer) = map
+            .layers()
+            .find(|layer| layer.layer_surface() == &surface)
+            .cloned()
+        {
+            map.unmap_layer(&layer);
+            map.arrange();
+            let zone_changed = map.non_exclusive_zone() != zone;
+            std::mem::drop(map);
+
+            if zone_changed {
+                self.configure_toplevels();
+            }
+
+            // hand the keyboard back to the topmost window
+            let keyboard = self.seat.get_keyboard().unwrap();
+            if keyboard.current_focus() == Some(FocusTarget::from(layer)) {
+                let focus = self.space.elements().last().cloned().map(FocusTarget::from);
+                keyboard.set_focus(self, focus, SERIAL_COUNTER.next_serial());
+            }
+        }
+    }
+}
+
+impl State {
+    /// Handles a commit on a layer surface, returns `false` if the surface isn't a mapped layer surface.
+    pub fn layer_commit(&mut self, surface: &WlSurface) -> bool {
+        let Some(output) = self.layer_output(surface) else {
+            return false;
+        };
+        let mut map = layer_map_for_output(&output);
+        let Some(layer) = map
+            .layer_for_surface(surface, WindowSurfaceType::TOPLEVEL)
+            .cloned()
+        else {
+            return false;
+        };
+
+        let zone = map.non_exclusive_zone();
+        map.arrange();
+        let zone_changed = map.non_exclusive_zone() != zone;
+        std::mem::drop(map);
+
+        send_initial_configure(&layer);
+        if zone_changed {
+            self.configure_toplevels();
+        }
+
+        // layers asking for exclusive keyboard interactivity above the windows take the focus, once when they
+        // start asking for it, so anything focused afterwards (e.g. by `focus_window`) keeps it
+        let exclusive = matches!(layer.layer(), Layer::Top | Layer::Overlay)
+            && layer.cached_state().keyboard_interactivity == KeyboardInteractivity::Exclusive;
+        let was_exclusive = with_states(surface, |states| {
+            states.data_map.insert_if_missing(|| ExclusiveKeyboard(Cell::new(false)));
+            states.data_map.get::<ExclusiveKeyboard>().unwrap().0.replace(exclusive)
+        });
+        if exclusive && !was_exclusive {
+            self.seat.get_keyboard().unwrap().set_focus(
+                self,
+                Some(FocusTarget::from(layer)),
+                SERIAL_COUNTER.next_serial(),
+            );
+        }
+
+        true
+    }
+
+    /// The output a layer surface is mapped on.
+    pub fn layer_output(&self, surface: &WlSurface) -> Option<Output> {
+        self.outputs
+            .iter()
+            .map(|virtual_output| &virtual_output.output)
+            .find(|output| {
+                layer_map_for_output(output)
+                    .layer_for_surface(surface, WindowSurfaceType::TOPLEVEL)
+                    .is_some()
+            })
+            .cloned()
+    }
+
+    /// Maps all layer surfaces, that were created before we had an output.
+    pub fn map_pending_layers(&mut self) {
+        let Some(output) = self.output.as_ref() else {
+            return;
+        };
+        let mut map = layer_map_for_output(output);
+        for layer in self.pending_layers.drain(..) {
+            if let Err(err) = map.map_layer(&layer) {
+                tracing::warn!(?err, "Failed to map layer surface.");
+                continue;
+            }
+            send_initial_configure(&layer);
+        }
+        map.arrange();
+    }
+
+    fn unconstrain_layer_popup(&self, popup: &PopupSurface) {
+        let Ok(root) = find_popup_root_surface(&PopupKind::Xdg(popup.clone())) else {
+            return;
+        };
+        let Some(output) = self.layer_output(&root) else {
+            return;
+        };
+        let map = layer_map_for_output(&output);
+        let Some(layer_geo) = map
+            .layer_for_surface(&root, WindowSurfaceType::TOPLEVEL)
+            .and_then(|layer| map.layer_geometry(layer))
+        else {
+            return;
+        };
+        let output_geo = self.space.output_geometry(&output).unwrap();
+
+        // The target geometry for the positioner should be relative to its parent's geometry, so
+        // we will compute that here.
+        let mut target = output_geo;
+        target.loc -= get_popup_toplevel_coords(&PopupKind::Xdg(popup.clone()));
+        target.loc -= output_geo.loc + layer_geo.loc;
+
+        popup.with_pending_state(|state| {
+            state.geometry = state.positioner.get_unconstrained_geometry(target);
+        });
+    }
+}
+
+/// Whether a layer surface asked for exclusive keyboard interactivity on its last commit.
+struct ExclusiveKeyboard(Cell<bool>);
+
+fn send_initial_configure(layer: &LayerSurface) {
+    let initial_configure_sent = with_states(layer.wl_surface(), |states| {
+        states
+            .data_map
+            .get::<LayerSurfaceData>()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .initial_configure_sent
+    });
+    if !initial_configure_sent {
+        layer.layer_surface().send_configure();
+    }
+}
+
+delegate_layer_shell!(State);
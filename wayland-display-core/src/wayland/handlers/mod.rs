@@ -1,10 +1,15 @@
+pub mod color_management;
 pub mod compositor;
 pub mod data_device;
 pub mod dmabuf;
+pub mod fractional_scale;
+pub mod layer_shell;
 pub mod output;
+pub mod output_management;
 pub mod presentation;
 pub mod relative_pointer;
 pub mod seat;
+pub mod security_context;
 pub mod shm;
 pub mod viewporter;
 pub mod wl_drm;
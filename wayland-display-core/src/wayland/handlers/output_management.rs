@@ -0,0 +1,34 @@
+use crate::{
+    comp::State,
+    wayland::protocols::output_management::{
+        delegate_output_management, OutputManagementHandler, OutputManagementState,
+    },
+    DisplayEvent, DisplayMode,
+};
+
+impl OutputManagementHandler for State {
+    fn output_management_state(&mut self) -> &mut OutputManagementState {
+        &mut self.output_management_state
+    }
+
+    fn request_mode(&mut self, output: &str, mode: DisplayMode, test_only: bool) -> bool {
+        let Some(id) = self
+            .outputs
+            .iter()
+            .find(|virtual_output| virtual_output.output.name() == output)
+            .map(|virtual_output| virtual_output.id)
+        else {
+            return false;
+        };
+        let Some(mode) = self.allowed_mode(id, mode) else {
+            return false;
+        };
+        if !test_only && Some(mode) != self.current_mode(id) {
+            tracing::info!(output, %mode, "Client requested a mode switch.");
+            self.emit(DisplayEvent::ModeRequested { output: id, mode });
+        }
+        true
+    }
+}
+
+delegate_output_management!(State);
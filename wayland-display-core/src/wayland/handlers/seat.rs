@@ -5,7 +5,10 @@ use smithay::{
     wayland::selection::data_device::set_data_device_focus,
 };
 
-use crate::comp::{FocusTarget, State};
+use crate::{
+    comp::{window_id, FocusTarget, State},
+    DisplayEvent,
+};
 
 impl SeatHandler for State {
     type KeyboardFocus = FocusTarget;
@@ -21,11 +24,22 @@ impl SeatHandler for State {
             let client = match surface {
                 FocusTarget::Wayland(w) => w.toplevel().unwrap().wl_surface().client(),
                 FocusTarget::Popup(p) => p.wl_surface().client(),
+                FocusTarget::LayerSurface(l) => l.wl_surface().client(),
             };
             set_data_device_focus(&self.dh, seat, client);
         } else {
             set_data_device_focus(&self.dh, seat, None);
         }
+
+        // popups belong to their toplevel and layer surfaces aren't windows,
+        // so neither changes the focused window
+        match focus {
+            Some(FocusTarget::Popup(_)) | Some(FocusTarget::LayerSurface(_)) => {}
+            Some(FocusTarget::Wayland(w)) => {
+                self.emit(DisplayEvent::WindowFocusChanged(Some(window_id(w))))
+            }
+            None => self.emit(DisplayEvent::WindowFocusChanged(None)),
+        }
     }
 
     fn cursor_image(&mut self, _seat: &Seat<Self>, image: CursorImageStatus) {
@@ -0,0 +1,32 @@
+use smithay::{
+    delegate_security_context,
+    wayland::security_context::{
+        SecurityContext, SecurityContextHandler, SecurityContextListenerSource,
+    },
+};
+
+use crate::comp::State;
+
+impl SecurityContextHandler for State {
+    fn context_created(
+        &mut self,
+        source: SecurityContextListenerSource,
+        security_context: SecurityContext,
+    ) {
+        // every client connecting through the context is sandboxed
+        if let Err(err) = self
+            .handle
+            .insert_source(source, move |client_stream, _, state| {
+                if let Err(err) =
+                    state.insert_client(client_stream, true, None, Some(security_context.clone()))
+                {
+                    tracing::warn!(?err, "Error adding sandboxed wayland client.");
+                }
+            })
+        {
+            tracing::warn!(?err, "Failed to listen on security context.");
+        }
+    }
+}
+
+delegate_security_context!(State);
@@ -1,6 +1,15 @@
 use smithay::{
-    delegate_shm,
-    wayland::shm::{ShmHandler, ShmState},
+    reexports::wayland_server::{
+        backend::ClientId,
+        delegate_dispatch, delegate_global_dispatch,
+        protocol::{
+            wl_buffer::WlBuffer,
+            wl_shm::{self, WlShm},
+            wl_shm_pool::{self, WlShmPool},
+        },
+        Client, DataInit, Dispatch, DisplayHandle,
+    },
+    wayland::shm::{ShmBufferUserData, ShmHandler, ShmPoolUserData, ShmState},
 };
 
 use crate::comp::State;
@@ -11,4 +20,53 @@ impl ShmHandler for State {
     }
 }
 
-delegate_shm!(State);
+// like `delegate_shm!`, with the pool size quota checked in between
+delegate_global_dispatch!(State: [WlShm: ()] => ShmState);
+delegate_dispatch!(State: [WlBuffer: ShmBufferUserData] => ShmState);
+
+impl Dispatch<WlShm, ()> for State {
+    fn request(
+        state: &mut Self,
+        client: &Client,
+        shm: &WlShm,
+        request: wl_shm::Request,
+        data: &(),
+        dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let request = match request {
+            wl_shm::Request::CreatePool { id, size, .. } if !state.check_shm_pool_size(client, size) => {
+                // the client is gone already, the new pool just needs to be resolved
+                data_init.post_error(id, wl_shm::Error::InvalidFd, "shm-pool-size quota exceeded");
+                return;
+            }
+            request => request,
+        };
+        <ShmState as Dispatch<WlShm, (), Self>>::request(state, client, shm, request, data, dh, data_init);
+    }
+}
+
+impl Dispatch<WlShmPool, ShmPoolUserData> for State {
+    fn request(
+        state: &mut Self,
+        client: &Client,
+        pool: &WlShmPool,
+        request: wl_shm_pool::Request,
+        data: &ShmPoolUserData,
+        dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let wl_shm_pool::Request::Resize { size } = request {
+            if !state.check_shm_pool_size(client, size) {
+                return;
+            }
+        }
+        <ShmState as Dispatch<WlShmPool, ShmPoolUserData, Self>>::request(
+            state, client, pool, request, data, dh, data_init,
+        );
+    }
+
+    fn destroyed(state: &mut Self, client: ClientId, pool: &WlShmPool, data: &ShmPoolUserData) {
+        <ShmState as Dispatch<WlShmPool, ShmPoolUserData, Self>>::destroyed(state, client, pool, data);
+    }
+}
@@ -4,7 +4,6 @@ use smithay::{
     reexports::wayland_server::{protocol::wl_buffer::WlBuffer},
     wayland::dmabuf::DmabufGlobal,
 };
-use smithay::backend::renderer::ImportDma;
 
 impl DrmHandler<()> for State {
     fn dmabuf_imported(
@@ -12,7 +11,11 @@ impl DrmHandler<()> for State {
         _global: &DmabufGlobal,
         dmabuf: Dmabuf,
     ) -> Result<(), ImportError> {
-        self.renderer.import_dmabuf(&dmabuf, None).map(|_| ()).map_err(|_| ImportError::Failed)
+        if self.renderer.import_dmabuf(&dmabuf) {
+            Ok(())
+        } else {
+            Err(ImportError::Failed)
+        }
     }
 
     fn buffer_created(&mut self, _buffer: WlBuffer, _result: ()) {}
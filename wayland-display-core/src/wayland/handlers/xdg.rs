@@ -1,11 +1,13 @@
 use smithay::{
     delegate_xdg_shell,
     desktop::{
-        find_popup_root_surface, get_popup_toplevel_coords, PopupKeyboardGrab, PopupKind,
-        PopupPointerGrab, PopupUngrabStrategy, Window,
+        find_popup_root_surface, get_popup_toplevel_coords, layer_map_for_output,
+        PopupKeyboardGrab, PopupKind, PopupPointerGrab, PopupUngrabStrategy, Window,
+        WindowSurfaceType,
     },
     input::{pointer::Focus, Seat},
-    reexports::wayland_server::protocol::wl_seat::WlSeat,
+    output::Output,
+    reexports::wayland_server::protocol::{wl_output::WlOutput, wl_seat::WlSeat},
     utils::Serial,
     wayland::{
         seat::WaylandFocus,
@@ -15,7 +17,10 @@ use smithay::{
     },
 };
 
-use crate::comp::{FocusTarget, State};
+use crate::{
+    comp::{window_id, FocusTarget, State},
+    DisplayEvent,
+};
 
 impl XdgShellHandler for State {
     fn xdg_shell_state(&mut self) -> &mut XdgShellState {
@@ -27,6 +32,32 @@ impl XdgShellHandler for State {
         self.pending_windows.push(window);
     }
 
+    fn toplevel_destroyed(&mut self, surface: ToplevelSurface) {
+        self.pending_windows.retain(|w| w.toplevel().unwrap() != &surface);
+        if let Some(window) = self
+            .space
+            .elements()
+            .find(|w| w.toplevel().unwrap() == &surface)
+            .cloned()
+        {
+            self.space.unmap_elem(&window);
+            self.remove_window_capture(window_id(&window));
+            self.emit(DisplayEvent::WindowDestroyed(window_id(&window)));
+        }
+    }
+
+    fn title_changed(&mut self, surface: ToplevelSurface) {
+        if let Some(window) = self
+            .space
+            .elements()
+            .find(|w| w.toplevel().unwrap() == &surface)
+            .cloned()
+        {
+            let title = self.window_info(&window).title.unwrap_or_default();
+            self.emit(DisplayEvent::WindowTitleChanged(window_id(&window), title));
+        }
+    }
+
     fn new_popup(&mut self, surface: PopupSurface, _positioner: PositionerState) {
         self.unconstrain_popup(&surface);
         if let Err(err) = self.popups.track_popup(PopupKind::from(surface)) {
@@ -43,6 +74,13 @@ impl XdgShellHandler for State {
                 .find(|w| w.wl_surface().map(|s| *s == root).unwrap_or(false))
                 .cloned()
                 .map(FocusTarget::from)
+                .or_else(|| {
+                    let output = self.layer_output(&root)?;
+                    let layer = layer_map_for_output(&output)
+                        .layer_for_surface(&root, WindowSurfaceType::TOPLEVEL)
+                        .cloned();
+                    layer.map(FocusTarget::from)
+                })
         }) {
             let ret = self.popups.grab_popup(root, surface.into(), &seat, serial);
             if let Ok(mut grab) = ret {
@@ -86,6 +124,28 @@ impl XdgShellHandler for State {
         self.unconstrain_popup(&surface);
         surface.send_repositioned(token);
     }
+
+    fn fullscreen_request(&mut self, surface: ToplevelSurface, wl_output: Option<WlOutput>) {
+        let Some(output) = wl_output.as_ref().and_then(Output::from_resource) else {
+            return;
+        };
+        let Some(id) = self
+            .outputs
+            .iter()
+            .find(|virtual_output| virtual_output.output == output)
+            .map(|virtual_output| virtual_output.id)
+        else {
+            return;
+        };
+        if let Some(window) = self
+            .space
+            .elements()
+            .find(|w| w.toplevel().unwrap() == &surface)
+            .cloned()
+        {
+            self.move_window_to_output(&window, id);
+        }
+    }
 }
 
 impl State {
@@ -101,7 +161,9 @@ impl State {
             return;
         };
 
-        let output = self.space.outputs().next().unwrap();
+        let Some(output) = self.window_output(window) else {
+            return;
+        };
         let output_geo = self.space.output_geometry(output).unwrap();
         let window_geo = self.space.element_geometry(window).unwrap();
 
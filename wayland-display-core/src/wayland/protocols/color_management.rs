@@ -0,0 +1,1100 @@
+// Re-export only the actual code, and then only use this re-export
+// The `generated` module below is just some boilerplate to properly isolate stuff
+// and avoid exposing internal details.
+pub use generated::{
+    wp_color_management_output_v1, wp_color_management_surface_feedback_v1,
+    wp_color_management_surface_v1, wp_color_manager_v1, wp_image_description_creator_icc_v1,
+    wp_image_description_creator_params_v1, wp_image_description_info_v1,
+    wp_image_description_v1,
+};
+
+mod generated {
+    use smithay::reexports::wayland_server::{self, protocol::*};
+
+    pub mod __interfaces {
+        use smithay::reexports::wayland_server::protocol::__interfaces::*;
+        use wayland_backend;
+        wayland_scanner::generate_interfaces!("resources/protocols/color-management-v1.xml");
+    }
+
+    use self::__interfaces::*;
+    wayland_scanner::generate_server_code!("resources/protocols/color-management-v1.xml");
+}
+
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc, Mutex,
+};
+
+use gst_video::{VideoColorPrimaries, VideoColorimetry, VideoTransferFunction};
+use smithay::{
+    output::Output,
+    reexports::wayland_server::{
+        backend::{ClientId, GlobalId},
+        protocol::{wl_output::WlOutput, wl_surface::WlSurface},
+        Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, WEnum, Weak,
+    },
+    wayland::compositor::{with_states, Cacheable},
+};
+
+use crate::HdrMetadata;
+
+use self::{
+    wp_color_management_output_v1::WpColorManagementOutputV1,
+    wp_color_management_surface_feedback_v1::WpColorManagementSurfaceFeedbackV1,
+    wp_color_management_surface_v1::WpColorManagementSurfaceV1,
+    wp_color_manager_v1::{Feature, Primaries, RenderIntent, TransferFunction, WpColorManagerV1},
+    wp_image_description_creator_icc_v1::WpImageDescriptionCreatorIccV1,
+    wp_image_description_creator_params_v1::WpImageDescriptionCreatorParamsV1,
+    wp_image_description_info_v1::WpImageDescriptionInfoV1,
+    wp_image_description_v1::WpImageDescriptionV1,
+};
+
+const SUPPORTED_TFS: [TransferFunction; 6] = [
+    TransferFunction::Srgb,
+    TransferFunction::Gamma22,
+    TransferFunction::Bt1886,
+    TransferFunction::ExtLinear,
+    TransferFunction::St2084Pq,
+    TransferFunction::Hlg,
+];
+
+const SUPPORTED_PRIMARIES: [Primaries; 5] = [
+    Primaries::Srgb,
+    Primaries::Bt2020,
+    Primaries::DisplayP3,
+    Primaries::DciP3,
+    Primaries::AdobeRgb,
+];
+
+/// Identities sent with `ready`, unique for every image description.
+static NEXT_IDENTITY: AtomicU32 = AtomicU32::new(1);
+
+/// CIE 1931 xy chromaticity coordinates of red, green, blue and the white point, multiplied by 1M.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chromaticities {
+    pub red: (i32, i32),
+    pub green: (i32, i32),
+    pub blue: (i32, i32),
+    pub white: (i32, i32),
+}
+
+impl Chromaticities {
+    pub fn from_named(primaries: Primaries) -> Option<Self> {
+        const D65: (i32, i32) = (312_700, 329_000);
+        Some(match primaries {
+            Primaries::Srgb => Chromaticities {
+                red: (640_000, 330_000),
+                green: (300_000, 600_000),
+                blue: (150_000, 60_000),
+                white: D65,
+            },
+            Primaries::Bt2020 => Chromaticities {
+                red: (708_000, 292_000),
+                green: (170_000, 797_000),
+                blue: (131_000, 46_000),
+                white: D65,
+            },
+            Primaries::DisplayP3 => Chromaticities {
+                red: (680_000, 320_000),
+                green: (265_000, 690_000),
+                blue: (150_000, 60_000),
+                white: D65,
+            },
+            Primaries::DciP3 => Chromaticities {
+                red: (680_000, 320_000),
+                green: (265_000, 690_000),
+                blue: (150_000, 60_000),
+                white: (314_000, 351_000),
+            },
+            Primaries::AdobeRgb => Chromaticities {
+                red: (640_000, 330_000),
+                green: (210_000, 710_000),
+                blue: (150_000, 60_000),
+                white: D65,
+            },
+            _ => return None,
+        })
+    }
+
+    fn from_args(args: [i32; 8]) -> Self {
+        Chromaticities {
+            red: (args[0], args[1]),
+            green: (args[2], args[3]),
+            blue: (args[4], args[5]),
+            white: (args[6], args[7]),
+        }
+    }
+}
+
+/// Luminances in the units of the protocol, `min` in 0.0001 cd/m², `max` and `reference` in cd/m².
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Luminances {
+    pub min: u32,
+    pub max: u32,
+    pub reference: u32,
+}
+
+impl Luminances {
+    /// The defaults the protocol specifies for a transfer function.
+    fn default_for(tf: TransferFunction) -> Self {
+        match tf {
+            TransferFunction::St2084Pq => Luminances {
+                min: 50,
+                max: 10000,
+                reference: 203,
+            },
+            TransferFunction::Hlg => Luminances {
+                min: 50,
+                max: 1000,
+                reference: 203,
+            },
+            _ => Luminances {
+                min: 2000,
+                max: 80,
+                reference: 80,
+            },
+        }
+    }
+}
+
+/// A parametric image description, as set by a client or used for the output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageDescription {
+    pub tf: TransferFunction,
+    pub primaries_named: Option<Primaries>,
+    pub primaries: Chromaticities,
+    pub luminances: Luminances,
+    pub mastering_primaries: Option<Chromaticities>,
+    /// Mastering display luminance range, minimum in 0.0001 cd/m², maximum in cd/m².
+    pub mastering_luminance: Option<(u32, u32)>,
+    pub max_cll: Option<u32>,
+    pub max_fall: Option<u32>,
+}
+
+impl Default for ImageDescription {
+    fn default() -> Self {
+        ImageDescription {
+            tf: TransferFunction::Srgb,
+            primaries_named: Some(Primaries::Srgb),
+            primaries: Chromaticities::from_named(Primaries::Srgb).unwrap(),
+            luminances: Luminances::default_for(TransferFunction::Srgb),
+            mastering_primaries: None,
+            mastering_luminance: None,
+            max_cll: None,
+            max_fall: None,
+        }
+    }
+}
+
+impl ImageDescription {
+    /// The image description of video in the given colorimetry.
+    pub fn from_colorimetry(colorimetry: &VideoColorimetry) -> Self {
+        let tf = match colorimetry.transfer() {
+            VideoTransferFunction::Smpte2084 => TransferFunction::St2084Pq,
+            VideoTransferFunction::AribStdB67 => TransferFunction::Hlg,
+            VideoTransferFunction::Gamma10 => TransferFunction::ExtLinear,
+            VideoTransferFunction::Gamma22 => TransferFunction::Gamma22,
+            VideoTransferFunction::Bt709
+            | VideoTransferFunction::Bt601
+            | VideoTransferFunction::Bt202010
+            | VideoTransferFunction::Bt202012 => TransferFunction::Bt1886,
+            _ => TransferFunction::Srgb,
+        };
+        let primaries = match colorimetry.primaries() {
+            VideoColorPrimaries::Bt2020 => Primaries::Bt2020,
+            VideoColorPrimaries::Smpteeg432 => Primaries::DisplayP3,
+            VideoColorPrimaries::Smpterp431 => Primaries::DciP3,
+            VideoColorPrimaries::Adobergb => Primaries::AdobeRgb,
+            _ => Primaries::Srgb,
+        };
+
+        ImageDescription {
+            tf,
+            primaries_named: Some(primaries),
+            primaries: Chromaticities::from_named(primaries).unwrap(),
+            luminances: Luminances::default_for(tf),
+            ..Default::default()
+        }
+    }
+
+    /// Whether content described by `other` can be composited into this color space as is.
+    ///
+    /// Nothing gets converted, so only content in the output's color space is accepted.
+    pub fn same_color_space(&self, other: &ImageDescription) -> bool {
+        self.tf == other.tf && self.primaries == other.primaries
+    }
+
+    /// Whether the description carries HDR mastering metadata.
+    pub fn has_hdr_metadata(&self) -> bool {
+        self.mastering_primaries.is_some()
+            || self.mastering_luminance.is_some()
+            || self.max_cll.is_some()
+            || self.max_fall.is_some()
+    }
+
+    fn send_information(&self, info: &WpImageDescriptionInfoV1) {
+        let Chromaticities {
+            red,
+            green,
+            blue,
+            white,
+        } = self.primaries;
+        info.primaries(red.0, red.1, green.0, green.1, blue.0, blue.1, white.0, white.1);
+        if let Some(primaries) = self.primaries_named {
+            info.primaries_named(primaries);
+        }
+        info.tf_named(self.tf);
+        info.luminances(
+            self.luminances.min,
+            self.luminances.max,
+            self.luminances.reference,
+        );
+        let Chromaticities {
+            red,
+            green,
+            blue,
+            white,
+        } = self.mastering_primaries.unwrap_or(self.primaries);
+        info.target_primaries(red.0, red.1, green.0, green.1, blue.0, blue.1, white.0, white.1);
+        let (min, max) = self
+            .mastering_luminance
+            .unwrap_or((self.luminances.min, self.luminances.max));
+        info.target_luminance(min, max);
+        if let Some(max_cll) = self.max_cll {
+            info.target_max_cll(max_cll);
+        }
+        if let Some(max_fall) = self.max_fall {
+            info.target_max_fall(max_fall);
+        }
+        info.done();
+    }
+}
+
+impl From<&ImageDescription> for HdrMetadata {
+    fn from(description: &ImageDescription) -> Self {
+        let coordinates = |(x, y): (i32, i32)| (x as f32 / 1_000_000., y as f32 / 1_000_000.);
+        HdrMetadata {
+            mastering_primaries: description.mastering_primaries.map(|primaries| {
+                [
+                    coordinates(primaries.red),
+                    coordinates(primaries.green),
+                    coordinates(primaries.blue),
+                    coordinates(primaries.white),
+                ]
+            }),
+            mastering_luminance: description
+                .mastering_luminance
+                .map(|(min, max)| (min as f32 / 10000., max as f32)),
+            max_cll: description.max_cll,
+            max_fall: description.max_fall,
+        }
+    }
+}
+
+/// Image description set on a surface, applied on commit.
+#[derive(Debug, Default, Clone)]
+pub struct ColorManagementSurfaceCachedState {
+    pub image_description: Option<Arc<ImageDescription>>,
+}
+
+impl Cacheable for ColorManagementSurfaceCachedState {
+    fn commit(&mut self, _dh: &DisplayHandle) -> Self {
+        self.clone()
+    }
+
+    fn merge_into(self, into: &mut Self, _dh: &DisplayHandle) {
+        *into = self;
+    }
+}
+
+/// The currently committed image description of a surface, `None` meaning sRGB.
+pub fn surface_image_description(surface: &WlSurface) -> Option<Arc<ImageDescription>> {
+    with_states(surface, |states| {
+        states
+            .cached_state
+            .get::<ColorManagementSurfaceCachedState>()
+            .current()
+            .image_description
+            .clone()
+    })
+}
+
+/// An image description with the identity clients know it by.
+#[derive(Debug, Clone)]
+struct IdentifiedDescription {
+    description: Arc<ImageDescription>,
+    identity: u32,
+}
+
+impl IdentifiedDescription {
+    fn new(description: ImageDescription) -> Self {
+        IdentifiedDescription {
+            description: Arc::new(description),
+            identity: NEXT_IDENTITY.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+}
+
+pub struct ColorManagementState {
+    global: GlobalId,
+    /// Used for outputs without a description of their own.
+    default_description: IdentifiedDescription,
+    output_descriptions: Vec<(Output, IdentifiedDescription)>,
+    outputs: Vec<(WpColorManagementOutputV1, WlOutput)>,
+    feedbacks: Vec<WpColorManagementSurfaceFeedbackV1>,
+}
+
+pub trait ColorManagementHandler {
+    fn color_management_state(&mut self) -> &mut ColorManagementState;
+    /// The output a surface is shown on, its color space is the one the surface should use.
+    fn surface_output(&self, surface: &WlSurface) -> Option<Output>;
+}
+
+pub struct ImageDescriptionData {
+    /// `None` if the description failed to be created.
+    description: Option<Arc<ImageDescription>>,
+    /// Only descriptions from the compositor may be inspected.
+    allow_information: bool,
+}
+
+#[derive(Default)]
+pub struct ParamsCreatorData(Mutex<ParamsBuilder>);
+
+#[derive(Default)]
+struct ParamsBuilder {
+    tf: Option<TransferFunction>,
+    primaries_named: Option<Primaries>,
+    primaries: Option<Chromaticities>,
+    luminances: Option<Luminances>,
+    mastering_primaries: Option<Chromaticities>,
+    mastering_luminance: Option<(u32, u32)>,
+    max_cll: Option<u32>,
+    max_fall: Option<u32>,
+}
+
+pub struct ColorManagementGlobalData {
+    filter: Box<dyn Fn(&Client) -> bool + Send + Sync>,
+}
+
+impl ColorManagementState {
+    pub fn new<D, F>(display: &DisplayHandle, filter: F) -> Self
+    where
+        D: GlobalDispatch<WpColorManagerV1, ColorManagementGlobalData> + 'static,
+        F: Fn(&Client) -> bool + Send + Sync + 'static,
+    {
+        let data = ColorManagementGlobalData {
+            filter: Box::new(filter),
+        };
+        ColorManagementState {
+            global: display.create_global::<D, WpColorManagerV1, _>(1, data),
+            default_description: IdentifiedDescription::new(ImageDescription::default()),
+            output_descriptions: Vec::new(),
+            outputs: Vec::new(),
+            feedbacks: Vec::new(),
+        }
+    }
+
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+
+    /// The color space everything on `output` gets composited in.
+    pub fn output_description(&self, output: &Output) -> Arc<ImageDescription> {
+        self.description_for(Some(output)).description.clone()
+    }
+
+    /// Changes the color space everything on `output` gets composited in, this is also the one
+    /// clients on it are asked to use.
+    pub fn set_output_description<D: ColorManagementHandler>(
+        state: &mut D,
+        output: &Output,
+        description: ImageDescription,
+    ) {
+        let this = state.color_management_state();
+        if this
+            .output_descriptions
+            .iter()
+            .any(|(other, current)| other == output && *current.description == description)
+        {
+            return;
+        }
+
+        let description = IdentifiedDescription::new(description);
+        this.output_descriptions.retain(|(other, _)| other != output);
+        this.output_descriptions.push((output.clone(), description.clone()));
+        for (resource, wl_output) in &this.outputs {
+            if output.owns(wl_output) {
+                resource.image_description_changed();
+            }
+        }
+        for feedback in this.feedbacks.clone() {
+            let Some(surface) = feedback
+                .data::<Weak<WlSurface>>()
+                .and_then(|surface| surface.upgrade().ok())
+            else {
+                continue;
+            };
+            if state.surface_output(&surface).as_ref() == Some(output) {
+                feedback.preferred_changed(description.identity);
+            }
+        }
+    }
+
+    /// Forgets the color space of a removed output.
+    pub fn remove_output(&mut self, output: &Output) {
+        self.output_descriptions.retain(|(other, _)| other != output);
+    }
+
+    fn description_for(&self, output: Option<&Output>) -> &IdentifiedDescription {
+        output
+            .and_then(|output| {
+                self.output_descriptions
+                    .iter()
+                    .find(|(other, _)| other == output)
+            })
+            .map_or(&self.default_description, |(_, description)| description)
+    }
+
+    /// Whether content in this color space can be composited into one of the outputs.
+    fn accepts(&self, description: &ImageDescription) -> bool {
+        if self.output_descriptions.is_empty() {
+            return self.default_description.description.same_color_space(description);
+        }
+        self.output_descriptions
+            .iter()
+            .any(|(_, output)| output.description.same_color_space(description))
+    }
+
+    fn init_output_description<D>(
+        &self,
+        data_init: &mut DataInit<'_, D>,
+        image_description: New<WpImageDescriptionV1>,
+        output: Option<&Output>,
+    ) where
+        D: Dispatch<WpImageDescriptionV1, ImageDescriptionData> + 'static,
+    {
+        let description = self.description_for(output);
+        let image_description = data_init.init(
+            image_description,
+            ImageDescriptionData {
+                description: Some(description.description.clone()),
+                allow_information: true,
+            },
+        );
+        image_description.ready(description.identity);
+    }
+}
+
+impl<D> GlobalDispatch<WpColorManagerV1, ColorManagementGlobalData, D> for ColorManagementState
+where
+    D: GlobalDispatch<WpColorManagerV1, ColorManagementGlobalData>
+        + Dispatch<WpColorManagerV1, ()>
+        + ColorManagementHandler
+        + 'static,
+{
+    fn bind(
+        state: &mut D,
+        _dh: &DisplayHandle,
+        _client: &Client,
+        resource: New<WpColorManagerV1>,
+        _global_data: &ColorManagementGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let manager = data_init.init(resource, ());
+
+        manager.supported_intent(RenderIntent::Perceptual);
+        for feature in [
+            Feature::Parametric,
+            Feature::SetPrimaries,
+            Feature::SetLuminances,
+            Feature::SetMasteringDisplayPrimaries,
+        ] {
+            manager.supported_feature(feature);
+        }
+        // surfaces aren't converted, so only the outputs' color spaces are advertised,
+        // clients get told about changes through `preferred_changed`
+        let this = state.color_management_state();
+        let mut tfs = Vec::new();
+        let mut primaries = Vec::new();
+        let descriptions = if this.output_descriptions.is_empty() {
+            vec![&this.default_description]
+        } else {
+            this.output_descriptions.iter().map(|(_, description)| description).collect()
+        };
+        for description in descriptions {
+            if !tfs.contains(&description.description.tf) {
+                tfs.push(description.description.tf);
+            }
+            if let Some(named) = description.description.primaries_named {
+                if !primaries.contains(&named) {
+                    primaries.push(named);
+                }
+            }
+        }
+        for tf in tfs {
+            manager.supported_tf_named(tf);
+        }
+        for named in primaries {
+            manager.supported_primaries_named(named);
+        }
+        manager.done();
+    }
+
+    fn can_view(client: Client, global_data: &ColorManagementGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<D> Dispatch<WpColorManagerV1, (), D> for ColorManagementState
+where
+    D: Dispatch<WpColorManagerV1, ()>
+        + Dispatch<WpColorManagementOutputV1, ()>
+        + Dispatch<WpColorManagementSurfaceV1, Weak<WlSurface>>
+        + Dispatch<WpColorManagementSurfaceFeedbackV1, Weak<WlSurface>>
+        + Dispatch<WpImageDescriptionCreatorIccV1, ()>
+        + Dispatch<WpImageDescriptionCreatorParamsV1, ParamsCreatorData>
+        + Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+        + ColorManagementHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        manager: &WpColorManagerV1,
+        request: wp_color_manager_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_color_manager_v1::Request::Destroy => {}
+            wp_color_manager_v1::Request::GetOutput { id, output: wl_output } => {
+                let output = data_init.init(id, ());
+                state.color_management_state().outputs.push((output, wl_output));
+            }
+            wp_color_manager_v1::Request::GetSurface { id, surface } => {
+                if surface_has_color_management(&surface) {
+                    manager.post_error(
+                        wp_color_manager_v1::Error::SurfaceExists,
+                        "wl_surface already has a color management surface",
+                    );
+                    return;
+                }
+                set_surface_color_management(&surface, true);
+                data_init.init(id, surface.downgrade());
+            }
+            wp_color_manager_v1::Request::GetSurfaceFeedback { id, surface } => {
+                let feedback = data_init.init(id, surface.downgrade());
+                state.color_management_state().feedbacks.push(feedback);
+            }
+            wp_color_manager_v1::Request::CreateIccCreator { obj } => {
+                data_init.init(obj, ());
+                manager.post_error(
+                    wp_color_manager_v1::Error::UnsupportedFeature,
+                    "ICC profiles are not supported",
+                );
+            }
+            wp_color_manager_v1::Request::CreateParametricCreator { obj } => {
+                data_init.init(obj, ParamsCreatorData::default());
+            }
+            wp_color_manager_v1::Request::CreateWindowsScrgb { image_description } => {
+                let image_description = data_init.init(
+                    image_description,
+                    ImageDescriptionData {
+                        description: None,
+                        allow_information: false,
+                    },
+                );
+                image_description.failed(
+                    wp_image_description_v1::Cause::Unsupported,
+                    String::from("Windows scRGB is not supported"),
+                );
+            }
+        }
+    }
+}
+
+/// Whether a surface has a `wp_color_management_surface_v1`, there may only be one at a time.
+struct SurfaceColorManagement(Mutex<bool>);
+
+fn surface_has_color_management(surface: &WlSurface) -> bool {
+    with_states(surface, |states| {
+        states
+            .data_map
+            .get::<SurfaceColorManagement>()
+            .map(|data| *data.0.lock().unwrap())
+            .unwrap_or(false)
+    })
+}
+
+fn set_surface_color_management(surface: &WlSurface, active: bool) {
+    with_states(surface, |states| {
+        states
+            .data_map
+            .insert_if_missing_threadsafe(|| SurfaceColorManagement(Mutex::new(false)));
+        *states
+            .data_map
+            .get::<SurfaceColorManagement>()
+            .unwrap()
+            .0
+            .lock()
+            .unwrap() = active;
+    })
+}
+
+impl<D> Dispatch<WpColorManagementOutputV1, (), D> for ColorManagementState
+where
+    D: Dispatch<WpColorManagementOutputV1, ()>
+        + Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+        + ColorManagementHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        resource: &WpColorManagementOutputV1,
+        request: wp_color_management_output_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_color_management_output_v1::Request::Destroy => {}
+            wp_color_management_output_v1::Request::GetImageDescription { image_description } => {
+                let this = state.color_management_state();
+                let output = this
+                    .outputs
+                    .iter()
+                    .find(|(other, _)| other == resource)
+                    .and_then(|(_, wl_output)| Output::from_resource(wl_output));
+                this.init_output_description(data_init, image_description, output.as_ref());
+            }
+        }
+    }
+
+    fn destroyed(state: &mut D, _client: ClientId, output: &WpColorManagementOutputV1, _data: &()) {
+        state
+            .color_management_state()
+            .outputs
+            .retain(|(other, _)| other != output);
+    }
+}
+
+impl<D> Dispatch<WpColorManagementSurfaceV1, Weak<WlSurface>, D> for ColorManagementState
+where
+    D: Dispatch<WpColorManagementSurfaceV1, Weak<WlSurface>> + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        resource: &WpColorManagementSurfaceV1,
+        request: wp_color_management_surface_v1::Request,
+        data: &Weak<WlSurface>,
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        let Ok(surface) = data.upgrade() else {
+            if !matches!(request, wp_color_management_surface_v1::Request::Destroy) {
+                resource.post_error(
+                    wp_color_management_surface_v1::Error::Inert,
+                    "wl_surface was destroyed",
+                );
+            }
+            return;
+        };
+
+        let description = match request {
+            wp_color_management_surface_v1::Request::Destroy => return,
+            wp_color_management_surface_v1::Request::SetImageDescription {
+                image_description,
+                render_intent,
+            } => {
+                if render_intent != WEnum::Value(RenderIntent::Perceptual) {
+                    resource.post_error(
+                        wp_color_management_surface_v1::Error::RenderIntent,
+                        "only the perceptual rendering intent is supported",
+                    );
+                    return;
+                }
+                let Some(description) = image_description
+                    .data::<ImageDescriptionData>()
+                    .and_then(|data| data.description.clone())
+                else {
+                    resource.post_error(
+                        wp_color_management_surface_v1::Error::ImageDescription,
+                        "image description is not ready",
+                    );
+                    return;
+                };
+                Some(description)
+            }
+            wp_color_management_surface_v1::Request::UnsetImageDescription => None,
+        };
+
+        with_states(&surface, |states| {
+            states
+                .cached_state
+                .get::<ColorManagementSurfaceCachedState>()
+                .pending()
+                .image_description = description;
+        });
+    }
+
+    fn destroyed(
+        _state: &mut D,
+        _client: ClientId,
+        _resource: &WpColorManagementSurfaceV1,
+        data: &Weak<WlSurface>,
+    ) {
+        // the surface falls back to sRGB on its next commit
+        if let Ok(surface) = data.upgrade() {
+            set_surface_color_management(&surface, false);
+            with_states(&surface, |states| {
+                states
+                    .cached_state
+                    .get::<ColorManagementSurfaceCachedState>()
+                    .pending()
+                    .image_description = None;
+            });
+        }
+    }
+}
+
+impl<D> Dispatch<WpColorManagementSurfaceFeedbackV1, Weak<WlSurface>, D> for ColorManagementState
+where
+    D: Dispatch<WpColorManagementSurfaceFeedbackV1, Weak<WlSurface>>
+        + Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+        + ColorManagementHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        feedback: &WpColorManagementSurfaceFeedbackV1,
+        request: wp_color_management_surface_feedback_v1::Request,
+        data: &Weak<WlSurface>,
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_color_management_surface_feedback_v1::Request::Destroy => {}
+            wp_color_management_surface_feedback_v1::Request::GetPreferred { image_description }
+            | wp_color_management_surface_feedback_v1::Request::GetPreferredParametric {
+                image_description,
+            } => {
+                let Ok(surface) = data.upgrade() else {
+                    feedback.post_error(
+                        wp_color_management_surface_feedback_v1::Error::Inert,
+                        "wl_surface was destroyed",
+                    );
+                    return;
+                };
+                // every surface is composited in the color space of the output it is on
+                let output = state.surface_output(&surface);
+                state.color_management_state().init_output_description(
+                    data_init,
+                    image_description,
+                    output.as_ref(),
+                );
+            }
+        }
+    }
+
+    fn destroyed(
+        state: &mut D,
+        _client: ClientId,
+        feedback: &WpColorManagementSurfaceFeedbackV1,
+        _data: &Weak<WlSurface>,
+    ) {
+        state
+            .color_management_state()
+            .feedbacks
+            .retain(|other| other != feedback);
+    }
+}
+
+impl<D> Dispatch<WpImageDescriptionCreatorIccV1, (), D> for ColorManagementState
+where
+    D: Dispatch<WpImageDescriptionCreatorIccV1, ()> + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _creator: &WpImageDescriptionCreatorIccV1,
+        _request: wp_image_description_creator_icc_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        // never created successfully, the client got an unsupported_feature error
+    }
+}
+
+impl<D> Dispatch<WpImageDescriptionCreatorParamsV1, ParamsCreatorData, D> for ColorManagementState
+where
+    D: Dispatch<WpImageDescriptionCreatorParamsV1, ParamsCreatorData>
+        + Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+        + ColorManagementHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        creator: &WpImageDescriptionCreatorParamsV1,
+        request: wp_image_description_creator_params_v1::Request,
+        data: &ParamsCreatorData,
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        use wp_image_description_creator_params_v1::{Error, Request};
+
+        let mut params = data.0.lock().unwrap();
+        let already_set = |set: bool| {
+            if set {
+                creator.post_error(Error::AlreadySet, "parameter was already set");
+            }
+            set
+        };
+
+        match request {
+            Request::Create { image_description } => {
+                let (Some(tf), Some(primaries)) = (params.tf, params.primaries) else {
+                    data_init.init(
+                        image_description,
+                        ImageDescriptionData {
+                            description: None,
+                            allow_information: false,
+                        },
+                    );
+                    creator.post_error(
+                        Error::IncompleteSet,
+                        "transfer characteristic and primaries are required",
+                    );
+                    return;
+                };
+
+                let description = ImageDescription {
+                    tf,
+                    primaries_named: params.primaries_named,
+                    primaries,
+                    luminances: params
+                        .luminances
+                        .unwrap_or_else(|| Luminances::default_for(tf)),
+                    mastering_primaries: params.mastering_primaries,
+                    mastering_luminance: params.mastering_luminance,
+                    max_cll: params.max_cll,
+                    max_fall: params.max_fall,
+                };
+                if !state.color_management_state().accepts(&description) {
+                    let image_description = data_init.init(
+                        image_description,
+                        ImageDescriptionData {
+                            description: None,
+                            allow_information: false,
+                        },
+                    );
+                    image_description.failed(
+                        wp_image_description_v1::Cause::Unsupported,
+                        String::from("only the outputs' color spaces are supported, surfaces are not converted"),
+                    );
+                    return;
+                }
+                let image_description = data_init.init(
+                    image_description,
+                    ImageDescriptionData {
+                        description: Some(Arc::new(description)),
+                        allow_information: false,
+                    },
+                );
+                image_description.ready(NEXT_IDENTITY.fetch_add(1, Ordering::Relaxed));
+            }
+            Request::SetTfNamed { tf } => {
+                if already_set(params.tf.is_some()) {
+                    return;
+                }
+                match tf {
+                    WEnum::Value(tf) if SUPPORTED_TFS.contains(&tf) => params.tf = Some(tf),
+                    _ => creator.post_error(Error::InvalidTf, "unsupported transfer characteristic"),
+                }
+            }
+            Request::SetTfPower { .. } => {
+                creator.post_error(Error::UnsupportedFeature, "set_tf_power is not supported");
+            }
+            Request::SetPrimariesNamed { primaries } => {
+                if already_set(params.primaries.is_some()) {
+                    return;
+                }
+                match primaries {
+                    WEnum::Value(named) if SUPPORTED_PRIMARIES.contains(&named) => {
+                        params.primaries_named = Some(named);
+                        params.primaries = Chromaticities::from_named(named);
+                    }
+                    _ => creator.post_error(Error::InvalidPrimariesNamed, "unsupported primaries"),
+                }
+            }
+            Request::SetPrimaries {
+                r_x,
+                r_y,
+                g_x,
+                g_y,
+                b_x,
+                b_y,
+                w_x,
+                w_y,
+            } => {
+                if already_set(params.primaries.is_some()) {
+                    return;
+                }
+                params.primaries = Some(Chromaticities::from_args([
+                    r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y,
+                ]));
+            }
+            Request::SetLuminances {
+                min_lum,
+                max_lum,
+                reference_lum,
+            } => {
+                if already_set(params.luminances.is_some()) {
+                    return;
+                }
+                // min_lum is in 0.0001 cd/m²
+                if max_lum as u64 * 10000 <= min_lum as u64 || reference_lum as u64 * 10000 <= min_lum as u64 {
+                    creator.post_error(Error::InvalidLuminance, "invalid luminance range");
+                    return;
+                }
+                params.luminances = Some(Luminances {
+                    min: min_lum,
+                    max: max_lum,
+                    reference: reference_lum,
+                });
+            }
+            Request::SetMasteringDisplayPrimaries {
+                r_x,
+                r_y,
+                g_x,
+                g_y,
+                b_x,
+                b_y,
+                w_x,
+                w_y,
+            } => {
+                if already_set(params.mastering_primaries.is_some()) {
+                    return;
+                }
+                params.mastering_primaries = Some(Chromaticities::from_args([
+                    r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y,
+                ]));
+            }
+            Request::SetMasteringLuminance { min_lum, max_lum } => {
+                if already_set(params.mastering_luminance.is_some()) {
+                    return;
+                }
+                if max_lum as u64 * 10000 <= min_lum as u64 {
+                    creator.post_error(Error::InvalidLuminance, "invalid mastering luminance range");
+                    return;
+                }
+                params.mastering_luminance = Some((min_lum, max_lum));
+            }
+            Request::SetMaxCll { max_cll } => {
+                if !already_set(params.max_cll.is_some()) {
+                    params.max_cll = Some(max_cll);
+                }
+            }
+            Request::SetMaxFall { max_fall } => {
+                if !already_set(params.max_fall.is_some()) {
+                    params.max_fall = Some(max_fall);
+                }
+            }
+        }
+    }
+}
+
+impl<D> Dispatch<WpImageDescriptionV1, ImageDescriptionData, D> for ColorManagementState
+where
+    D: Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+        + Dispatch<WpImageDescriptionInfoV1, ()>
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        image_description: &WpImageDescriptionV1,
+        request: wp_image_description_v1::Request,
+        data: &ImageDescriptionData,
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_image_description_v1::Request::Destroy => {}
+            wp_image_description_v1::Request::GetInformation { information } => {
+                let info = data_init.init(information, ());
+                let Some(description) = data.description.as_ref() else {
+                    image_description.post_error(
+                        wp_image_description_v1::Error::NotReady,
+                        "image description failed",
+                    );
+                    return;
+                };
+                if !data.allow_information {
+                    image_description.post_error(
+                        wp_image_description_v1::Error::NoInformation,
+                        "only compositor image descriptions can be inspected",
+                    );
+                    return;
+                }
+                description.send_information(&info);
+            }
+        }
+    }
+}
+
+impl<D> Dispatch<WpImageDescriptionInfoV1, (), D> for ColorManagementState
+where
+    D: Dispatch<WpImageDescriptionInfoV1, ()> + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _info: &WpImageDescriptionInfoV1,
+        _request: wp_image_description_info_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        // has no requests
+    }
+}
+
+macro_rules! delegate_color_management {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::protocols::color_management::wp_color_manager_v1::WpColorManagerV1: $crate::wayland::protocols::color_management::ColorManagementGlobalData
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::protocols::color_management::wp_color_manager_v1::WpColorManagerV1: ()
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::protocols::color_management::wp_color_management_output_v1::WpColorManagementOutputV1: ()
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::protocols::color_management::wp_color_management_surface_v1::WpColorManagementSurfaceV1: smithay::reexports::wayland_server::Weak<smithay::reexports::wayland_server::protocol::wl_surface::WlSurface>
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::protocols::color_management::wp_color_management_surface_feedback_v1::WpColorManagementSurfaceFeedbackV1: smithay::reexports::wayland_server::Weak<smithay::reexports::wayland_server::protocol::wl_surface::WlSurface>
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::protocols::color_management::wp_image_description_creator_icc_v1::WpImageDescriptionCreatorIccV1: ()
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::protocols::color_management::wp_image_description_creator_params_v1::WpImageDescriptionCreatorParamsV1: $crate::wayland::protocols::color_management::ParamsCreatorData
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::protocols::color_management::wp_image_description_v1::WpImageDescriptionV1: $crate::wayland::protocols::color_management::ImageDescriptionData
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::protocols::color_management::wp_image_description_info_v1::WpImageDescriptionInfoV1: ()
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+    };
+}
+pub(crate) use delegate_color_management;
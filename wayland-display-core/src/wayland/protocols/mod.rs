@@ -1 +1,3 @@
+pub mod color_management;
+pub mod output_management;
 pub mod wl_drm;
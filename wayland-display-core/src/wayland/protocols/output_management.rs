@@ -0,0 +1,669 @@
+use std::sync::Mutex;
+
+use smithay::{
+    reexports::{
+        wayland_protocols_wlr::output_management::v1::server::{
+            zwlr_output_configuration_head_v1::{self, ZwlrOutputConfigurationHeadV1},
+            zwlr_output_configuration_v1::{self, ZwlrOutputConfigurationV1},
+            zwlr_output_head_v1::{self, AdaptiveSyncState, ZwlrOutputHeadV1},
+            zwlr_output_manager_v1::{self, ZwlrOutputManagerV1},
+            zwlr_output_mode_v1::{self, ZwlrOutputModeV1},
+        },
+        wayland_server::{
+            backend::{ClientId, GlobalId},
+            Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+        },
+    },
+    utils::Transform,
+};
+
+use crate::DisplayMode;
+
+/// Everything `wlr-output-management` reports about an output, heads are told apart by their name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeadInfo {
+    pub name: String,
+    pub description: String,
+    pub make: String,
+    pub model: String,
+    pub serial: Option<String>,
+    /// Physical size in millimeters, `(0, 0)` if unknown.
+    pub physical_size: (i32, i32),
+    /// All modes clients may switch to, including the current one.
+    pub modes: Vec<DisplayMode>,
+    pub current_mode: DisplayMode,
+    pub preferred_mode: Option<DisplayMode>,
+    /// Position in the desktop, in logical coordinates.
+    pub position: (i32, i32),
+    pub transform: Transform,
+    pub scale: f64,
+}
+
+pub struct OutputManagementState {
+    global: GlobalId,
+    serial: u32,
+    heads: Vec<HeadInfo>,
+    managers: Vec<ManagerInstance>,
+}
+
+/// The objects a single `zwlr_output_manager_v1` was told about.
+struct ManagerInstance {
+    manager: ZwlrOutputManagerV1,
+    /// Heads released by the client are gone from here.
+    heads: Vec<AdvertisedHead>,
+}
+
+struct AdvertisedHead {
+    name: String,
+    head: ZwlrOutputHeadV1,
+    modes: Vec<(DisplayMode, ZwlrOutputModeV1)>,
+}
+
+pub struct OutputManagementGlobalData {
+    filter: Box<dyn Fn(&Client) -> bool + Send + Sync>,
+}
+
+/// The changes a client wants to apply, as collected by a `zwlr_output_configuration_v1`.
+pub struct ConfigurationData {
+    serial: u32,
+    inner: Mutex<ConfigurationInner>,
+}
+
+#[derive(Default)]
+struct ConfigurationInner {
+    used: bool,
+    enabled: Vec<(ZwlrOutputHeadV1, ZwlrOutputConfigurationHeadV1)>,
+    disabled: Vec<ZwlrOutputHeadV1>,
+}
+
+impl ConfigurationInner {
+    fn is_configured(&self, head: &ZwlrOutputHeadV1) -> bool {
+        self.enabled.iter().any(|(enabled, _)| enabled == head) || self.disabled.contains(head)
+    }
+}
+
+#[derive(Default)]
+pub struct ConfigurationHeadData(Mutex<PendingHead>);
+
+#[derive(Default)]
+struct PendingHead {
+    mode: Option<DisplayMode>,
+    position: Option<(i32, i32)>,
+    transform: Option<Transform>,
+    scale: Option<f64>,
+    adaptive_sync: Option<bool>,
+}
+
+pub trait OutputManagementHandler {
+    fn output_management_state(&mut self) -> &mut OutputManagementState;
+    /// A client asked to switch the output named `output` to `mode`, returns whether the switch is possible.
+    ///
+    /// A `refresh` of 0 matches any refresh rate. With `test_only` nothing should be changed.
+    fn request_mode(&mut self, output: &str, mode: DisplayMode, test_only: bool) -> bool;
+}
+
+impl OutputManagementState {
+    pub fn new<D, F>(display: &DisplayHandle, filter: F) -> Self
+    where
+        D: GlobalDispatch<ZwlrOutputManagerV1, OutputManagementGlobalData> + 'static,
+        F: Fn(&Client) -> bool + Send + Sync + 'static,
+    {
+        let data = OutputManagementGlobalData {
+            filter: Box::new(filter),
+        };
+        OutputManagementState {
+            global: display.create_global::<D, ZwlrOutputManagerV1, _>(4, data),
+            serial: 0,
+            heads: Vec::new(),
+            managers: Vec::new(),
+        }
+    }
+
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+
+    /// Adds or updates an output reported to clients, pending configurations based on an older state get cancelled.
+    pub fn update<D>(&mut self, dh: &DisplayHandle, head: HeadInfo)
+    where
+        D: Dispatch<ZwlrOutputHeadV1, ()> + Dispatch<ZwlrOutputModeV1, DisplayMode> + 'static,
+    {
+        let old = self.heads.iter().position(|old| old.name == head.name);
+        if old.map_or(false, |idx| self.heads[idx] == head) {
+            return;
+        }
+
+        self.serial = self.serial.wrapping_add(1);
+        for instance in &mut self.managers {
+            match old.map(|idx| &self.heads[idx]) {
+                Some(old) => {
+                    // not found if released by the client
+                    if let Some(advertised) = instance.heads.iter_mut().find(|advertised| advertised.name == head.name) {
+                        advertised.update::<D>(dh, old, &head);
+                    }
+                }
+                None => instance
+                    .heads
+                    .extend(advertise_head::<D>(dh, &instance.manager, &head)),
+            }
+            instance.manager.done(self.serial);
+        }
+        match old {
+            Some(idx) => self.heads[idx] = head,
+            None => self.heads.push(head),
+        }
+    }
+
+    /// Removes an output reported to clients.
+    pub fn remove(&mut self, name: &str) {
+        let Some(idx) = self.heads.iter().position(|head| head.name == name) else {
+            return;
+        };
+        self.heads.remove(idx);
+
+        self.serial = self.serial.wrapping_add(1);
+        for instance in &mut self.managers {
+            instance.heads.retain(|advertised| {
+                if advertised.name != name {
+                    return true;
+                }
+                for (_, mode) in &advertised.modes {
+                    mode.finished();
+                }
+                advertised.head.finished();
+                false
+            });
+            instance.manager.done(self.serial);
+        }
+    }
+
+    /// The name of the output a head object stands for.
+    fn head_name(&self, head: &ZwlrOutputHeadV1) -> Option<&str> {
+        self.managers
+            .iter()
+            .flat_map(|instance| &instance.heads)
+            .find(|advertised| advertised.head == *head)
+            .map(|advertised| advertised.name.as_str())
+    }
+}
+
+fn advertise_head<D>(dh: &DisplayHandle, manager: &ZwlrOutputManagerV1, info: &HeadInfo) -> Option<AdvertisedHead>
+where
+    D: Dispatch<ZwlrOutputHeadV1, ()> + Dispatch<ZwlrOutputModeV1, DisplayMode> + 'static,
+{
+    let client = manager.client()?;
+    let head = client
+        .create_resource::<ZwlrOutputHeadV1, _, D>(dh, manager.version(), ())
+        .ok()?;
+    manager.head(&head);
+
+    head.name(info.name.clone());
+    head.description(info.description.clone());
+    if info.physical_size.0 > 0 && info.physical_size.1 > 0 {
+        head.physical_size(info.physical_size.0, info.physical_size.1);
+    }
+    let mut advertised = AdvertisedHead {
+        name: info.name.clone(),
+        head,
+        modes: Vec::new(),
+    };
+    for mode in &info.modes {
+        advertised.advertise_mode::<D>(dh, &client, *mode, info.preferred_mode == Some(*mode));
+    }
+
+    let head = &advertised.head;
+    head.enabled(1);
+    if let Some(current) = advertised.mode_resource(info.current_mode) {
+        head.current_mode(current);
+    }
+    head.position(info.position.0, info.position.1);
+    head.transform(info.transform.into());
+    head.scale(info.scale);
+    if head.version() >= zwlr_output_head_v1::EVT_MAKE_SINCE {
+        head.make(info.make.clone());
+        head.model(info.model.clone());
+        if let Some(serial) = info.serial.clone() {
+            head.serial_number(serial);
+        }
+    }
+    if head.version() >= zwlr_output_head_v1::EVT_ADAPTIVE_SYNC_SINCE {
+        head.adaptive_sync(AdaptiveSyncState::Disabled);
+    }
+
+    Some(advertised)
+}
+
+impl AdvertisedHead {
+    fn advertise_mode<D>(&mut self, dh: &DisplayHandle, client: &Client, mode: DisplayMode, preferred: bool)
+    where
+        D: Dispatch<ZwlrOutputModeV1, DisplayMode> + 'static,
+    {
+        let Ok(resource) = client.create_resource::<ZwlrOutputModeV1, _, D>(dh, self.head.version(), mode) else {
+            return;
+        };
+        self.head.mode(&resource);
+        resource.size(mode.width, mode.height);
+        resource.refresh(mode.refresh);
+        if preferred {
+            resource.preferred();
+        }
+        self.modes.push((mode, resource));
+    }
+
+    fn mode_resource(&self, mode: DisplayMode) -> Option<&ZwlrOutputModeV1> {
+        self.modes
+            .iter()
+            .find(|(advertised, _)| *advertised == mode)
+            .map(|(_, resource)| resource)
+    }
+
+    fn update<D>(&mut self, dh: &DisplayHandle, old: &HeadInfo, new: &HeadInfo)
+    where
+        D: Dispatch<ZwlrOutputModeV1, DisplayMode> + 'static,
+    {
+        if old.description != new.description {
+            self.head.description(new.description.clone());
+        }
+
+        let mut modes_changed = false;
+        self.modes.retain(|(mode, resource)| {
+            let keep = new.modes.contains(mode);
+            if !keep {
+                resource.finished();
+                modes_changed = true;
+            }
+            keep
+        });
+        if let Some(client) = self.head.client() {
+            for mode in &new.modes {
+                if self.mode_resource(*mode).is_none() {
+                    self.advertise_mode::<D>(dh, &client, *mode, new.preferred_mode == Some(*mode));
+                    modes_changed = true;
+                }
+            }
+        }
+
+        if modes_changed || old.current_mode != new.current_mode {
+            if let Some(current) = self.mode_resource(new.current_mode) {
+                self.head.current_mode(current);
+            }
+        }
+        if old.position != new.position {
+            self.head.position(new.position.0, new.position.1);
+        }
+        if old.transform != new.transform {
+            self.head.transform(new.transform.into());
+        }
+        if old.scale != new.scale {
+            self.head.scale(new.scale);
+        }
+    }
+}
+
+impl<D> GlobalDispatch<ZwlrOutputManagerV1, OutputManagementGlobalData, D> for OutputManagementState
+where
+    D: GlobalDispatch<ZwlrOutputManagerV1, OutputManagementGlobalData>
+        + Dispatch<ZwlrOutputManagerV1, ()>
+        + Dispatch<ZwlrOutputHeadV1, ()>
+        + Dispatch<ZwlrOutputModeV1, DisplayMode>
+        + OutputManagementHandler
+        + 'static,
+{
+    fn bind(
+        state: &mut D,
+        dh: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrOutputManagerV1>,
+        _global_data: &OutputManagementGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let manager = data_init.init(resource, ());
+        let state = state.output_management_state();
+        let heads = state
+            .heads
+            .iter()
+            .filter_map(|info| advertise_head::<D>(dh, &manager, info))
+            .collect();
+        manager.done(state.serial);
+        state.managers.push(ManagerInstance { manager, heads });
+    }
+
+    fn can_view(client: Client, global_data: &OutputManagementGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<D> Dispatch<ZwlrOutputManagerV1, (), D> for OutputManagementState
+where
+    D: Dispatch<ZwlrOutputManagerV1, ()>
+        + Dispatch<ZwlrOutputConfigurationV1, ConfigurationData>
+        + OutputManagementHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        manager: &ZwlrOutputManagerV1,
+        request: zwlr_output_manager_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwlr_output_manager_v1::Request::CreateConfiguration { id, serial } => {
+                data_init.init(
+                    id,
+                    ConfigurationData {
+                        serial,
+                        inner: Mutex::new(ConfigurationInner::default()),
+                    },
+                );
+            }
+            zwlr_output_manager_v1::Request::Stop => {
+                let state = state.output_management_state();
+                state.managers.retain(|instance| instance.manager != *manager);
+                manager.finished();
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(state: &mut D, _client: ClientId, manager: &ZwlrOutputManagerV1, _data: &()) {
+        let state = state.output_management_state();
+        state.managers.retain(|instance| instance.manager != *manager);
+    }
+}
+
+impl<D> Dispatch<ZwlrOutputHeadV1, (), D> for OutputManagementState
+where
+    D: Dispatch<ZwlrOutputHeadV1, ()> + OutputManagementHandler + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        head: &ZwlrOutputHeadV1,
+        request: zwlr_output_head_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwlr_output_head_v1::Request::Release => {
+                for instance in &mut state.output_management_state().managers {
+                    instance.heads.retain(|advertised| advertised.head != *head);
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<ZwlrOutputModeV1, DisplayMode, D> for OutputManagementState
+where
+    D: Dispatch<ZwlrOutputModeV1, DisplayMode> + OutputManagementHandler + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _mode: &ZwlrOutputModeV1,
+        request: zwlr_output_mode_v1::Request,
+        _data: &DisplayMode,
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            // kept around until the mode is gone, so it isn't advertised again
+            zwlr_output_mode_v1::Request::Release => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<ZwlrOutputConfigurationV1, ConfigurationData, D> for OutputManagementState
+where
+    D: Dispatch<ZwlrOutputConfigurationV1, ConfigurationData>
+        + Dispatch<ZwlrOutputConfigurationHeadV1, ConfigurationHeadData>
+        + OutputManagementHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        configuration: &ZwlrOutputConfigurationV1,
+        request: zwlr_output_configuration_v1::Request,
+        data: &ConfigurationData,
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let mut inner = data.inner.lock().unwrap();
+        match request {
+            zwlr_output_configuration_v1::Request::EnableHead { id, head } => {
+                let config_head = data_init.init(id, ConfigurationHeadData::default());
+                if inner.used {
+                    configuration.post_error(
+                        zwlr_output_configuration_v1::Error::AlreadyUsed,
+                        "configuration was already applied or tested",
+                    );
+                } else if inner.is_configured(&head) {
+                    configuration.post_error(
+                        zwlr_output_configuration_v1::Error::AlreadyConfiguredHead,
+                        "head was already configured",
+                    );
+                } else {
+                    inner.enabled.push((head, config_head));
+                }
+            }
+            zwlr_output_configuration_v1::Request::DisableHead { head } => {
+                if inner.used {
+                    configuration.post_error(
+                        zwlr_output_configuration_v1::Error::AlreadyUsed,
+                        "configuration was already applied or tested",
+                    );
+                } else if inner.is_configured(&head) {
+                    configuration.post_error(
+                        zwlr_output_configuration_v1::Error::AlreadyConfiguredHead,
+                        "head was already configured",
+                    );
+                } else {
+                    inner.disabled.push(head);
+                }
+            }
+            zwlr_output_configuration_v1::Request::Apply => {
+                apply_configuration(state, configuration, data.serial, &mut inner, false)
+            }
+            zwlr_output_configuration_v1::Request::Test => {
+                apply_configuration(state, configuration, data.serial, &mut inner, true)
+            }
+            zwlr_output_configuration_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn apply_configuration<D: OutputManagementHandler>(
+    state: &mut D,
+    configuration: &ZwlrOutputConfigurationV1,
+    serial: u32,
+    inner: &mut ConfigurationInner,
+    test_only: bool,
+) {
+    if inner.used {
+        configuration.post_error(
+            zwlr_output_configuration_v1::Error::AlreadyUsed,
+            "configuration was already applied or tested",
+        );
+        return;
+    }
+    inner.used = true;
+
+    let management_state = state.output_management_state();
+    if serial != management_state.serial {
+        configuration.cancelled();
+        return;
+    }
+    // outputs are only turned off by the embedder
+    if !inner.disabled.is_empty() {
+        configuration.failed();
+        return;
+    }
+
+    let mut requests = Vec::new();
+    for head in &management_state.heads {
+        let Some((_, config_head)) = inner
+            .enabled
+            .iter()
+            .find(|(enabled, _)| management_state.head_name(enabled) == Some(head.name.as_str()))
+        else {
+            configuration.post_error(
+                zwlr_output_configuration_v1::Error::UnconfiguredHead,
+                "every output has to be enabled or disabled",
+            );
+            return;
+        };
+
+        let pending = config_head.data::<ConfigurationHeadData>().unwrap().0.lock().unwrap();
+        // only modes can be changed, layout, rotation and scale belong to the embedder
+        if pending.position.map_or(false, |position| position != head.position)
+            || pending.transform.map_or(false, |transform| transform != head.transform)
+            || pending.scale.map_or(false, |scale| scale != head.scale)
+            || pending.adaptive_sync == Some(true)
+        {
+            configuration.failed();
+            return;
+        }
+        requests.push((head.name.clone(), pending.mode.unwrap_or(head.current_mode)));
+    }
+
+    // nothing is switched unless every output can be
+    if !requests
+        .iter()
+        .all(|(output, mode)| state.request_mode(output, *mode, true))
+    {
+        configuration.failed();
+        return;
+    }
+    if !test_only {
+        for (output, mode) in &requests {
+            state.request_mode(output, *mode, false);
+        }
+    }
+    configuration.succeeded();
+}
+
+impl<D> Dispatch<ZwlrOutputConfigurationHeadV1, ConfigurationHeadData, D> for OutputManagementState
+where
+    D: Dispatch<ZwlrOutputConfigurationHeadV1, ConfigurationHeadData> + OutputManagementHandler + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        config_head: &ZwlrOutputConfigurationHeadV1,
+        request: zwlr_output_configuration_head_v1::Request,
+        data: &ConfigurationHeadData,
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        let mut pending = data.0.lock().unwrap();
+        let already_set = || {
+            config_head.post_error(
+                zwlr_output_configuration_head_v1::Error::AlreadySet,
+                "property was already set",
+            )
+        };
+        match request {
+            zwlr_output_configuration_head_v1::Request::SetMode { mode } => {
+                if pending.mode.is_some() {
+                    return already_set();
+                }
+                match mode.data::<DisplayMode>() {
+                    Some(mode) => pending.mode = Some(*mode),
+                    None => config_head.post_error(
+                        zwlr_output_configuration_head_v1::Error::InvalidMode,
+                        "mode doesn't belong to this head",
+                    ),
+                }
+            }
+            zwlr_output_configuration_head_v1::Request::SetCustomMode { width, height, refresh } => {
+                if pending.mode.is_some() {
+                    return already_set();
+                }
+                if width <= 0 || height <= 0 || refresh < 0 {
+                    config_head.post_error(
+                        zwlr_output_configuration_head_v1::Error::InvalidCustomMode,
+                        "invalid custom mode",
+                    );
+                    return;
+                }
+                pending.mode = Some(DisplayMode { width, height, refresh });
+            }
+            zwlr_output_configuration_head_v1::Request::SetPosition { x, y } => {
+                if pending.position.is_some() {
+                    return already_set();
+                }
+                pending.position = Some((x, y));
+            }
+            zwlr_output_configuration_head_v1::Request::SetTransform { transform } => {
+                if pending.transform.is_some() {
+                    return already_set();
+                }
+                match transform.into_result() {
+                    Ok(transform) => pending.transform = Some(transform.into()),
+                    Err(_) => config_head.post_error(
+                        zwlr_output_configuration_head_v1::Error::InvalidTransform,
+                        "invalid transform",
+                    ),
+                }
+            }
+            zwlr_output_configuration_head_v1::Request::SetScale { scale } => {
+                if pending.scale.is_some() {
+                    return already_set();
+                }
+                if scale <= 0.0 {
+                    config_head.post_error(
+                        zwlr_output_configuration_head_v1::Error::InvalidScale,
+                        "invalid scale",
+                    );
+                    return;
+                }
+                pending.scale = Some(scale);
+            }
+            zwlr_output_configuration_head_v1::Request::SetAdaptiveSync { state } => {
+                if pending.adaptive_sync.is_some() {
+                    return already_set();
+                }
+                match state.into_result() {
+                    Ok(state) => pending.adaptive_sync = Some(state == AdaptiveSyncState::Enabled),
+                    Err(_) => config_head.post_error(
+                        zwlr_output_configuration_head_v1::Error::InvalidAdaptiveSyncState,
+                        "invalid adaptive sync state",
+                    ),
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+macro_rules! delegate_output_management {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::output_management::v1::server::zwlr_output_manager_v1::ZwlrOutputManagerV1: $crate::wayland::protocols::output_management::OutputManagementGlobalData
+        ] => $crate::wayland::protocols::output_management::OutputManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::output_management::v1::server::zwlr_output_manager_v1::ZwlrOutputManagerV1: ()
+        ] => $crate::wayland::protocols::output_management::OutputManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::output_management::v1::server::zwlr_output_head_v1::ZwlrOutputHeadV1: ()
+        ] => $crate::wayland::protocols::output_management::OutputManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::output_management::v1::server::zwlr_output_mode_v1::ZwlrOutputModeV1: $crate::DisplayMode
+        ] => $crate::wayland::protocols::output_management::OutputManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::output_management::v1::server::zwlr_output_configuration_v1::ZwlrOutputConfigurationV1: $crate::wayland::protocols::output_management::ConfigurationData
+        ] => $crate::wayland::protocols::output_management::OutputManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::output_management::v1::server::zwlr_output_configuration_head_v1::ZwlrOutputConfigurationHeadV1: $crate::wayland::protocols::output_management::ConfigurationHeadData
+        ] => $crate::wayland::protocols::output_management::OutputManagementState);
+    };
+}
+pub(crate) use delegate_output_management;
@@ -199,22 +199,6 @@ impl<D> Dispatch<wl_drm::WlDrm, DrmInstanceData, D> for WlDrmState
     }
 }
 
-pub fn create_drm_global<D>(
-    display: &DisplayHandle,
-    device_path: PathBuf,
-    formats: Vec<Format>,
-    dmabuf_global: &DmabufGlobal,
-) -> GlobalId
-    where
-        D: GlobalDispatch<wl_drm::WlDrm, DrmGlobalData>
-        + Dispatch<wl_drm::WlDrm, DrmInstanceData>
-        + BufferHandler
-        + DmabufHandler
-        + 'static,
-{
-    create_drm_global_with_filter::<D, _>(display, device_path, formats, dmabuf_global, |_| true)
-}
-
 pub fn create_drm_global_with_filter<D, F>(
     display: &DisplayHandle,
     device_path: PathBuf,